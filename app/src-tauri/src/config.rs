@@ -0,0 +1,51 @@
+//! Persistent settings for VietFlux
+//! Stores the engine configuration as JSON in the platform config directory
+
+use crate::keyboard;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Runtime};
+use vietflux_core::EngineConfig;
+
+const CONFIG_FILE: &str = "config.json";
+
+/// Path of the config file (e.g. ~/.config/<app>/config.json on Linux)
+fn config_path<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    app.path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Load the saved configuration into the engine
+/// Falls back to defaults when the file is missing or invalid.
+pub fn load<R: Runtime>(app: &AppHandle<R>) {
+    let Some(path) = config_path(app) else {
+        return;
+    };
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        return;
+    };
+
+    let loaded = EngineConfig::from_json(&json)
+        .map_err(|e| e.to_string())
+        .and_then(|config| keyboard::load_config(&config));
+    if let Err(e) = loaded {
+        eprintln!("Ignoring config {}: {}", path.display(), e);
+    }
+}
+
+/// Save the current engine configuration
+pub fn save<R: Runtime>(app: &AppHandle<R>) {
+    let Some(path) = config_path(app) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("Failed to create {}: {}", dir.display(), e);
+            return;
+        }
+    }
+    if let Err(e) = std::fs::write(&path, keyboard::get_config().to_json()) {
+        eprintln!("Failed to save config {}: {}", path.display(), e);
+    }
+}
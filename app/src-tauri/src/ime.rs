@@ -1,12 +1,16 @@
 //! IME integration for Tauri
 //! Delegates commands to the global keyboard hook engine
+//! Commands that change settings save the configuration afterwards
 
-use crate::keyboard;
+use crate::{config, keyboard};
+use tauri::AppHandle;
 use vietflux_core::shortcut::Shortcut;
+use vietflux_core::EngineOptions;
 
 #[tauri::command]
-pub fn set_method(method: String) {
+pub fn set_method(app: AppHandle, method: String) {
     keyboard::set_method(&method);
+    config::save(&app);
 }
 
 #[tauri::command]
@@ -15,8 +19,24 @@ pub fn get_method() -> String {
 }
 
 #[tauri::command]
-pub fn toggle() -> bool {
-    keyboard::toggle_ime()
+pub fn get_methods() -> Vec<String> {
+    keyboard::get_methods()
+}
+
+/// Load a custom input method from a JSON definition file and switch to it
+#[tauri::command]
+pub fn load_custom_method(app: AppHandle, path: String) -> Result<(), String> {
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+    keyboard::load_custom_method(&json)?;
+    config::save(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn toggle(app: AppHandle) -> bool {
+    let enabled = keyboard::toggle_ime();
+    config::save(&app);
+    enabled
 }
 
 #[tauri::command]
@@ -30,15 +50,27 @@ pub fn clear() {
 }
 
 #[tauri::command]
-pub fn set_options(auto_capitalize: bool, smart_quotes: bool, spell_check: bool) {
-    keyboard::set_options(auto_capitalize, smart_quotes, spell_check);
+pub fn set_options(app: AppHandle, options: EngineOptions) {
+    keyboard::set_options(options);
+    config::save(&app);
 }
 
 #[tauri::command]
-pub fn get_options() -> (bool, bool, bool) {
+pub fn get_options() -> EngineOptions {
     keyboard::get_options()
 }
 
+#[tauri::command]
+pub fn set_undo_key(app: AppHandle, key: Option<char>) {
+    keyboard::set_undo_key(key);
+    config::save(&app);
+}
+
+#[tauri::command]
+pub fn get_undo_key() -> Option<char> {
+    keyboard::get_undo_key()
+}
+
 // Shortcut Management Commands
 
 #[tauri::command]
@@ -47,19 +79,22 @@ pub fn get_shortcuts() -> Vec<Shortcut> {
 }
 
 #[tauri::command]
-pub fn add_shortcut(trigger: String, expansion: String) -> Vec<Shortcut> {
+pub fn add_shortcut(app: AppHandle, trigger: String, expansion: String) -> Vec<Shortcut> {
     keyboard::add_shortcut(&trigger, &expansion);
+    config::save(&app);
     keyboard::get_shortcuts()
 }
 
 #[tauri::command]
-pub fn remove_shortcut(trigger: String) -> Vec<Shortcut> {
+pub fn remove_shortcut(app: AppHandle, trigger: String) -> Vec<Shortcut> {
     keyboard::remove_shortcut(&trigger);
+    config::save(&app);
     keyboard::get_shortcuts()
 }
 
 #[tauri::command]
-pub fn toggle_shortcut(trigger: String) -> Vec<Shortcut> {
+pub fn toggle_shortcut(app: AppHandle, trigger: String) -> Vec<Shortcut> {
     keyboard::toggle_shortcut(&trigger);
+    config::save(&app);
     keyboard::get_shortcuts()
 }
@@ -1,11 +1,19 @@
 //! Windows keyboard hook module for VietFlux IME
 //! Uses WH_KEYBOARD_LL + SendInput approach
 
+/// Built-in input method names (used before the engine starts)
+fn builtin_methods() -> Vec<String> {
+    vietflux_core::methods::BUILTIN_METHODS
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+}
+
 #[cfg(windows)]
 mod windows_impl {
     use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use std::sync::Mutex;
-    use vietflux_core::Engine;
+    use vietflux_core::{Engine, EngineConfig, EngineOptions};
     use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
     use windows::Win32::UI::Input::KeyboardAndMouse::{
         GetAsyncKeyState, GetKeyState, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT,
@@ -41,6 +49,23 @@ mod windows_impl {
         }
     }
 
+    /// Replace the engine with one built from a saved configuration
+    pub fn load_config(config: &EngineConfig) -> Result<(), String> {
+        let engine = Engine::from_config(config).map_err(|err| err.to_string())?;
+        *ENGINE.lock().unwrap() = Some(engine);
+        Ok(())
+    }
+
+    /// Get the current engine configuration
+    pub fn get_config() -> EngineConfig {
+        let engine = ENGINE.lock().unwrap();
+        if let Some(ref e) = *engine {
+            e.config()
+        } else {
+            EngineConfig::default()
+        }
+    }
+
     /// Start the keyboard hook (call from main thread)
     pub fn start_hook() {
         if HOOK_RUNNING.load(Ordering::SeqCst) {
@@ -112,6 +137,25 @@ mod windows_impl {
         }
     }
 
+    /// List available input methods
+    pub fn get_methods() -> Vec<String> {
+        let engine = ENGINE.lock().unwrap();
+        if let Some(ref e) = *engine {
+            e.available_methods()
+        } else {
+            super::builtin_methods()
+        }
+    }
+
+    /// Load a custom input method definition (JSON) and switch to it
+    pub fn load_custom_method(json: &str) -> Result<(), String> {
+        let mut engine = ENGINE.lock().unwrap();
+        match *engine {
+            Some(ref mut e) => e.load_custom_method(json).map_err(|err| err.to_string()),
+            None => Err("IME engine is not running".to_string()),
+        }
+    }
+
     /// Get current method
     pub fn get_method() -> String {
         let engine = ENGINE.lock().unwrap();
@@ -123,23 +167,53 @@ mod windows_impl {
     }
 
     /// Set engine options
-    pub fn set_options(auto_capitalize: bool, smart_quotes: bool, spell_check: bool) {
-        let mut engine = ENGINE.lock().unwrap();
-        if let Some(ref mut e) = *engine {
-            e.set_options(auto_capitalize, smart_quotes, spell_check);
+    ///
+    /// A tone style or encoding change retypes the word being composed.
+    pub fn set_options(options: EngineOptions) {
+        let result = {
+            let mut engine = ENGINE.lock().unwrap();
+            match *engine {
+                Some(ref mut e) => e.set_options(options),
+                None => return,
+            }
+        };
+
+        if result.action == vietflux_core::engine::Action::Update {
+            IS_PROCESSING.store(true, Ordering::SeqCst);
+            println!(
+                "Options: sending {} backspaces + '{}'",
+                result.backspace, result.output
+            );
+            send_backspaces(result.backspace);
+            send_unicode_text(&result.output);
+            IS_PROCESSING.store(false, Ordering::SeqCst);
         }
     }
 
     /// Get engine options
-    pub fn get_options() -> (bool, bool, bool) {
+    pub fn get_options() -> EngineOptions {
         let engine = ENGINE.lock().unwrap();
         if let Some(ref e) = *engine {
             e.get_options()
         } else {
-            (true, false, true) // Default values
+            EngineOptions::default()
+        }
+    }
+
+    /// Set the undo key (None disables it)
+    pub fn set_undo_key(key: Option<char>) {
+        let mut engine = ENGINE.lock().unwrap();
+        if let Some(ref mut e) = *engine {
+            e.set_undo_key(key);
         }
     }
 
+    /// Get the undo key
+    pub fn get_undo_key() -> Option<char> {
+        let engine = ENGINE.lock().unwrap();
+        engine.as_ref().and_then(|e| e.get_undo_key())
+    }
+
     /// Get shortcuts
     pub fn get_shortcuts() -> Vec<vietflux_core::shortcut::Shortcut> {
         let engine = ENGINE.lock().unwrap();
@@ -220,6 +294,14 @@ mod windows_impl {
                     return call_next_hook(code, w_param, l_param);
                 }
 
+                // Backspace edits the word being composed
+                if vk_code == VK_BACK {
+                    if process_backspace() {
+                        return LRESULT(1); // Block original key
+                    }
+                    return call_next_hook(code, w_param, l_param);
+                }
+
                 // Convert virtual key to char
                 if let Some(ch) = vk_to_char(vk_code) {
                     if let Some(handled) = process_key(ch) {
@@ -350,6 +432,39 @@ mod windows_impl {
         Some(false) // Don't block - let key pass through
     }
 
+    /// Process Backspace through the IME engine
+    ///
+    /// Returns true if the word was retyped and the original key must be
+    /// blocked; otherwise the key deletes the last character as usual.
+    fn process_backspace() -> bool {
+        IS_PROCESSING.store(true, Ordering::SeqCst);
+
+        let result = {
+            let mut engine = ENGINE.lock().unwrap();
+            match *engine {
+                Some(ref mut e) => e.process_backspace(),
+                None => {
+                    IS_PROCESSING.store(false, Ordering::SeqCst);
+                    return false;
+                }
+            }
+        };
+
+        let handled =
+            result.action == vietflux_core::engine::Action::Update && result.backspace > 0;
+        if handled {
+            println!(
+                "Backspace: sending {} backspaces + '{}'",
+                result.backspace, result.output
+            );
+            send_backspaces(result.backspace);
+            send_unicode_text(&result.output);
+        }
+
+        IS_PROCESSING.store(false, Ordering::SeqCst);
+        handled
+    }
+
     /// Send backspace keys
     fn send_backspaces(count: usize) {
         if count == 0 {
@@ -453,7 +568,7 @@ mod macos_impl {
     };
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::Mutex;
-    use vietflux_core::Engine;
+    use vietflux_core::{Engine, EngineConfig, EngineOptions};
 
     /// Global engine instance
     static ENGINE: Mutex<Option<Engine>> = Mutex::new(None);
@@ -461,6 +576,12 @@ mod macos_impl {
     /// Hook running state  
     static HOOK_RUNNING: AtomicBool = AtomicBool::new(false);
 
+    /// Marker to identify events posted by us
+    const INJECTED_EVENT_MARKER: i64 = 0x56464C58; // "VFLX" in hex
+
+    /// macOS keycode for Backspace (Delete)
+    const BACKSPACE_KEYCODE: u16 = 51;
+
     /// Initialize the IME engine
     pub fn init_engine() {
         let mut engine = ENGINE.lock().unwrap();
@@ -469,6 +590,23 @@ mod macos_impl {
         }
     }
 
+    /// Replace the engine with one built from a saved configuration
+    pub fn load_config(config: &EngineConfig) -> Result<(), String> {
+        let engine = Engine::from_config(config).map_err(|err| err.to_string())?;
+        *ENGINE.lock().unwrap() = Some(engine);
+        Ok(())
+    }
+
+    /// Get the current engine configuration
+    pub fn get_config() -> EngineConfig {
+        let engine = ENGINE.lock().unwrap();
+        if let Some(ref e) = *engine {
+            e.config()
+        } else {
+            EngineConfig::default()
+        }
+    }
+
     /// Start the keyboard hook (requires Accessibility permission)
     /// NOTE: User must grant Accessibility API access in System Settings > Privacy & Security > Accessibility
     pub fn start_hook() {
@@ -571,6 +709,33 @@ mod macos_impl {
                 core_graphics::event::CGEventField::KeyboardEventKeycode,
             ) as u16;
 
+            // Skip the backspaces we post ourselves
+            let user_data = core_graphics::event::CGEventGetIntegerValueField(
+                event,
+                core_graphics::event::CGEventField::EventSourceUserData,
+            );
+            if user_data == INJECTED_EVENT_MARKER {
+                return event;
+            }
+
+            // Backspace edits the word being composed
+            if keycode == BACKSPACE_KEYCODE {
+                let mut engine = ENGINE.lock().unwrap();
+                if let Some(ref mut e) = *engine {
+                    let result = e.process_backspace();
+                    if result.action == vietflux_core::Action::Update {
+                        for _ in 0..result.backspace {
+                            send_backspace();
+                        }
+                        for c in result.output.chars() {
+                            send_char(c);
+                        }
+                        return std::ptr::null_mut();
+                    }
+                }
+                return event;
+            }
+
             // Convert keycode to char (simplified - full mapping needed)
             let key_char = keycode_to_char(keycode);
 
@@ -654,9 +819,13 @@ mod macos_impl {
                     core_graphics::event::CGEventSourceStateID::HIDSystemState,
                 )
                 .ok(),
-                51, // Backspace keycode
+                BACKSPACE_KEYCODE,
                 true,
             ) {
+                event.set_integer_value_field(
+                    core_graphics::event::EventField::EVENT_SOURCE_USER_DATA,
+                    INJECTED_EVENT_MARKER,
+                );
                 event.post(core_graphics::event::CGEventTapLocation::HID);
             }
             if let Some(event) = core_graphics::event::CGEvent::new_keyboard_event(
@@ -664,9 +833,13 @@ mod macos_impl {
                     core_graphics::event::CGEventSourceStateID::HIDSystemState,
                 )
                 .ok(),
-                51,
+                BACKSPACE_KEYCODE,
                 false,
             ) {
+                event.set_integer_value_field(
+                    core_graphics::event::EventField::EVENT_SOURCE_USER_DATA,
+                    INJECTED_EVENT_MARKER,
+                );
                 event.post(core_graphics::event::CGEventTapLocation::HID);
             }
         }
@@ -689,6 +862,12 @@ mod macos_impl {
                         chars.len(),
                         chars.as_ptr(),
                     );
+                    // Keycode 0 is 'a': without the marker our own tap
+                    // would feed the character back into the engine
+                    event.set_integer_value_field(
+                        core_graphics::event::EventField::EVENT_SOURCE_USER_DATA,
+                        INJECTED_EVENT_MARKER,
+                    );
                     event.post(CGEventTapLocation::HID);
                 }
             }
@@ -721,6 +900,25 @@ mod macos_impl {
         }
     }
 
+    pub fn get_methods() -> Vec<String> {
+        ENGINE
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|e| e.available_methods())
+            .unwrap_or_else(super::builtin_methods)
+    }
+
+    pub fn load_custom_method(json: &str) -> Result<(), String> {
+        ENGINE
+            .lock()
+            .unwrap()
+            .as_mut()
+            .ok_or_else(|| "IME engine is not running".to_string())?
+            .load_custom_method(json)
+            .map_err(|err| err.to_string())
+    }
+
     pub fn get_method() -> String {
         ENGINE
             .lock()
@@ -730,20 +928,44 @@ mod macos_impl {
             .unwrap_or_else(|| "telex".to_string())
     }
 
-    pub fn set_options(auto_capitalize: bool, smart_quotes: bool, spell_check: bool) {
+    /// A tone style or encoding change retypes the word being composed
+    pub fn set_options(options: EngineOptions) {
         let mut engine = ENGINE.lock().unwrap();
         if let Some(ref mut e) = *engine {
-            e.set_options(auto_capitalize, smart_quotes, spell_check);
+            let result = e.set_options(options);
+            if result.action == vietflux_core::Action::Update {
+                for _ in 0..result.backspace {
+                    send_backspace();
+                }
+                for c in result.output.chars() {
+                    send_char(c);
+                }
+            }
         }
     }
 
-    pub fn get_options() -> (bool, bool, bool) {
+    pub fn get_options() -> EngineOptions {
         ENGINE
             .lock()
             .unwrap()
             .as_ref()
             .map(|e| e.get_options())
-            .unwrap_or((true, false, true))
+            .unwrap_or_default()
+    }
+
+    pub fn set_undo_key(key: Option<char>) {
+        let mut engine = ENGINE.lock().unwrap();
+        if let Some(ref mut e) = *engine {
+            e.set_undo_key(key);
+        }
+    }
+
+    pub fn get_undo_key() -> Option<char> {
+        ENGINE
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|e| e.get_undo_key())
     }
 
     pub fn get_shortcuts() -> Vec<vietflux_core::shortcut::Shortcut> {
@@ -793,18 +1015,64 @@ pub use macos_impl::*;
 #[cfg(target_os = "linux")]
 mod linux_impl {
     use std::sync::Mutex;
-    use vietflux_core::Engine;
+    use vietflux_core::{Engine, EngineConfig, EngineOptions};
+    use vietflux_x11::Hook;
 
     static ENGINE: Mutex<Option<Engine>> = Mutex::new(None);
 
+    /// XRecord/XTest hook, running on X11 sessions without an IM framework
+    static HOOK: Mutex<Option<Hook>> = Mutex::new(None);
+
     pub fn start_hook() {
-        eprintln!("Linux: ibus/fcitx integration required. See: https://github.com/niccokunzmann/rust-ibus");
-        let mut engine = ENGINE.lock().unwrap();
-        if engine.is_none() {
-            *engine = Some(Engine::new());
+        {
+            let mut engine = ENGINE.lock().unwrap();
+            if engine.is_none() {
+                *engine = Some(Engine::new());
+            }
+        }
+
+        // IBus, Fcitx5 and Wayland run their own VietFlux front end; hooking
+        // the same keys here would transform them twice
+        let framework = std::env::var("XMODIFIERS")
+            .map(|im| im.contains("ibus") || im.contains("fcitx"))
+            .unwrap_or(false);
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() || framework {
+            eprintln!(
+                "Linux: typing goes through the IBus, Fcitx5 or Wayland input method, see docs/INSTALL.md"
+            );
+            return;
+        }
+
+        let mut hook = HOOK.lock().unwrap();
+        if hook.is_some() {
+            return;
+        }
+        match Hook::start(None, &ENGINE) {
+            Ok(started) => {
+                *hook = Some(started);
+                eprintln!("VietFlux: X11 keyboard hook started");
+            }
+            Err(e) => eprintln!("VietFlux: X11 keyboard hook failed: {}", e),
         }
     }
-    pub fn stop_hook() {}
+    pub fn stop_hook() {
+        // Dropping the hook waits for the recording thread
+        let hook = HOOK.lock().unwrap().take();
+        drop(hook);
+    }
+    pub fn load_config(config: &EngineConfig) -> Result<(), String> {
+        let engine = Engine::from_config(config).map_err(|err| err.to_string())?;
+        *ENGINE.lock().unwrap() = Some(engine);
+        Ok(())
+    }
+    pub fn get_config() -> EngineConfig {
+        ENGINE
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|e| e.config())
+            .unwrap_or_default()
+    }
     pub fn toggle_ime() -> bool {
         ENGINE
             .lock()
@@ -831,6 +1099,24 @@ mod linux_impl {
             .as_mut()
             .map(|e| e.set_method(method));
     }
+    pub fn get_methods() -> Vec<String> {
+        ENGINE
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|e| e.available_methods())
+            .unwrap_or_else(super::builtin_methods)
+    }
+
+    pub fn load_custom_method(json: &str) -> Result<(), String> {
+        ENGINE
+            .lock()
+            .unwrap()
+            .as_mut()
+            .ok_or_else(|| "IME engine is not running".to_string())?
+            .load_custom_method(json)
+            .map_err(|err| err.to_string())
+    }
     pub fn get_method() -> String {
         ENGINE
             .lock()
@@ -839,20 +1125,30 @@ mod linux_impl {
             .map(|e| e.get_method().to_string())
             .unwrap_or_else(|| "telex".to_string())
     }
-    pub fn set_options(auto_capitalize: bool, smart_quotes: bool, spell_check: bool) {
+    pub fn set_options(options: EngineOptions) {
         ENGINE
             .lock()
             .unwrap()
             .as_mut()
-            .map(|e| e.set_options(auto_capitalize, smart_quotes, spell_check));
+            .map(|e| e.set_options(options));
     }
-    pub fn get_options() -> (bool, bool, bool) {
+    pub fn get_options() -> EngineOptions {
         ENGINE
             .lock()
             .unwrap()
             .as_ref()
             .map(|e| e.get_options())
-            .unwrap_or((true, false, true))
+            .unwrap_or_default()
+    }
+    pub fn set_undo_key(key: Option<char>) {
+        ENGINE.lock().unwrap().as_mut().map(|e| e.set_undo_key(key));
+    }
+    pub fn get_undo_key() -> Option<char> {
+        ENGINE
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|e| e.get_undo_key())
     }
     pub fn get_shortcuts() -> Vec<vietflux_core::shortcut::Shortcut> {
         ENGINE
@@ -900,6 +1196,16 @@ pub fn start_hook() {
 #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
 pub fn stop_hook() {}
 
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn load_config(_config: &vietflux_core::EngineConfig) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn get_config() -> vietflux_core::EngineConfig {
+    vietflux_core::EngineConfig::default()
+}
+
 #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
 pub fn toggle_ime() -> bool {
     false
@@ -913,17 +1219,35 @@ pub fn is_enabled() -> bool {
 #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
 pub fn set_method(_method: &str) {}
 
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn get_methods() -> Vec<String> {
+    builtin_methods()
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn load_custom_method(_json: &str) -> Result<(), String> {
+    Err("Keyboard hook not implemented for this platform".to_string())
+}
+
 #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
 pub fn get_method() -> String {
     "telex".to_string()
 }
 
 #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
-pub fn set_options(_auto_capitalize: bool, _smart_quotes: bool, _spell_check: bool) {}
+pub fn set_options(_options: vietflux_core::EngineOptions) {}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn get_options() -> vietflux_core::EngineOptions {
+    vietflux_core::EngineOptions::default()
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn set_undo_key(_key: Option<char>) {}
 
 #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
-pub fn get_options() -> (bool, bool, bool) {
-    (true, false, true)
+pub fn get_undo_key() -> Option<char> {
+    None
 }
 
 #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
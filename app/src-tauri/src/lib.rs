@@ -1,4 +1,5 @@
 //! VietFlux App Library
 
+pub mod config;
 pub mod ime;
 pub mod keyboard;
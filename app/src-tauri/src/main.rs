@@ -7,7 +7,7 @@ use tauri::{
     Manager,
 };
 
-use vietflux_app_lib::{ime, keyboard};
+use vietflux_app_lib::{config, ime, keyboard};
 
 // Global tray reference for updates
 static TRAY: Mutex<Option<TrayIcon>> = Mutex::new(None);
@@ -27,6 +27,9 @@ fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .setup(|app| {
+            // Restore saved settings before the hook creates a default engine
+            config::load(app.handle());
+
             // Create system tray menu
             let quit = MenuItem::with_id(app, "quit", "Thoát", true, None::<&str>)?;
             let toggle =
@@ -44,7 +47,7 @@ fn main() {
                 .icon(icon)
                 .menu(&menu)
                 .tooltip("VietFlux IME - Telex")
-                .on_menu_event(|_app, event| {
+                .on_menu_event(|app, event| {
                     println!("Menu event: {:?}", event.id.as_ref());
                     match event.id.as_ref() {
                         "quit" => {
@@ -56,6 +59,7 @@ fn main() {
                             println!("Toggle clicked");
                             let enabled = keyboard::toggle_ime();
                             println!("IME enabled: {}", enabled);
+                            config::save(app);
                             if let Ok(guard) = TRAY.lock() {
                                 if let Some(ref tray) = *guard {
                                     let _ = tray.set_tooltip(if enabled {
@@ -69,6 +73,7 @@ fn main() {
                         "telex" => {
                             println!("Telex clicked");
                             keyboard::set_method("telex");
+                            config::save(app);
                             if let Ok(guard) = TRAY.lock() {
                                 if let Some(ref tray) = *guard {
                                     let _ = tray.set_tooltip(Some("VietFlux IME - Telex"));
@@ -78,6 +83,7 @@ fn main() {
                         "vni" => {
                             println!("VNI clicked");
                             keyboard::set_method("vni");
+                            config::save(app);
                             if let Ok(guard) = TRAY.lock() {
                                 if let Some(ref tray) = *guard {
                                     let _ = tray.set_tooltip(Some("VietFlux IME - VNI"));
@@ -116,11 +122,15 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             ime::set_method,
             ime::get_method,
+            ime::get_methods,
+            ime::load_custom_method,
             ime::toggle,
             ime::is_enabled,
             ime::clear,
             ime::set_options,
             ime::get_options,
+            ime::set_undo_key,
+            ime::get_undo_key,
             ime::get_shortcuts,
             ime::add_shortcut,
             ime::remove_shortcut,
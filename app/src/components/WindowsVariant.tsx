@@ -7,12 +7,43 @@ import { ToggleRow } from './ToggleRow';
 import { ShortcutsCard } from './ShortcutsCard';
 import { FooterLinks } from './FooterLinks';
 
+interface EngineOptions {
+  auto_capitalize: boolean;
+  smart_quotes: boolean;
+  spell_check: boolean;
+  modern_tone: boolean;
+  quick_telex: boolean;
+  bracket_shortcuts: boolean;
+  diacritic_removal: boolean;
+  output_encoding: 'nfc' | 'nfd' | 'tcvn3' | 'vni-windows' | 'viscii' | 'vps';
+}
+
+type ToggleOption = Exclude<keyof EngineOptions, 'output_encoding'>;
+
+const ENCODINGS: Record<string, EngineOptions['output_encoding']> = {
+  'NFC': 'nfc',
+  'NFD': 'nfd',
+  'TCVN3': 'tcvn3',
+  'VNI': 'vni-windows',
+  'VISCII': 'viscii',
+  'VPS': 'vps',
+};
+
+const DEFAULT_OPTIONS: EngineOptions = {
+  auto_capitalize: true,
+  smart_quotes: false,
+  spell_check: true,
+  modern_tone: true,
+  quick_telex: true,
+  bracket_shortcuts: true,
+  diacritic_removal: true,
+  output_encoding: 'nfc',
+};
+
 export function WindowsVariant() {
   const [isEnabled, setIsEnabled] = useState(true);
   const [inputMethod, setInputMethod] = useState('Telex');
-  const [autoCapitalize, setAutoCapitalize] = useState(true);
-  const [smartQuotes, setSmartQuotes] = useState(false);
-  const [spellCheck, setSpellCheck] = useState(true);
+  const [options, setOptions] = useState<EngineOptions>(DEFAULT_OPTIONS);
 
   // Fetch initial state
   useEffect(() => {
@@ -29,10 +60,7 @@ export function WindowsVariant() {
         };
         setInputMethod(methodMap[method] || 'Telex');
 
-        const [autoCap, smartQ, spell] = await invoke<[boolean, boolean, boolean]>('get_options');
-        setAutoCapitalize(autoCap);
-        setSmartQuotes(smartQ);
-        setSpellCheck(spell);
+        setOptions(await invoke<EngineOptions>('get_options'));
       } catch (e) {
         console.error('Failed to fetch state:', e);
       }
@@ -50,27 +78,16 @@ export function WindowsVariant() {
     await invoke('set_method', { method: method.toLowerCase() });
   };
 
-  const updateOptions = async (autoCap: boolean, smartQ: boolean, spell: boolean) => {
-    await invoke('set_options', {
-      autoCapitalize: autoCap,
-      smartQuotes: smartQ,
-      spellCheck: spell
-    });
-  };
-
-  const handleAutoCapChange = (val: boolean) => {
-    setAutoCapitalize(val);
-    updateOptions(val, smartQuotes, spellCheck);
+  const updateOption = (key: ToggleOption) => async (val: boolean) => {
+    const next = { ...options, [key]: val };
+    setOptions(next);
+    await invoke('set_options', { options: next });
   };
 
-  const handleSmartQuotesChange = (val: boolean) => {
-    setSmartQuotes(val);
-    updateOptions(autoCapitalize, val, spellCheck);
-  };
-
-  const handleSpellCheckChange = (val: boolean) => {
-    setSpellCheck(val);
-    updateOptions(autoCapitalize, smartQuotes, val);
+  const handleEncodingChange = async (encoding: string) => {
+    const next = { ...options, output_encoding: ENCODINGS[encoding] ?? 'nfc' };
+    setOptions(next);
+    await invoke('set_options', { options: next });
   };
 
   return (
@@ -105,6 +122,22 @@ export function WindowsVariant() {
           </p>
         </div>
 
+        {/* Output Encoding */}
+        <div className="px-4 py-3 border-b border-slate-200/40">
+          <label className="block mb-2 text-[13px] text-slate-700 font-medium leading-tight tracking-[-0.005em]">
+            Output Encoding
+          </label>
+          <SegmentedControl
+            variant="windows"
+            options={Object.keys(ENCODINGS)}
+            value={Object.keys(ENCODINGS).find((label) => ENCODINGS[label] === options.output_encoding) ?? 'NFC'}
+            onChange={handleEncodingChange}
+          />
+          <p className="mt-2 text-[11px] text-slate-500 leading-[1.5] tracking-normal">
+            NFD writes combining marks; TCVN3, VNI, VISCII and VPS are for legacy fonts
+          </p>
+        </div>
+
         {/* Quick Toggles */}
         <div className="px-4 py-3 border-b border-slate-200/40">
           <h3 className="text-[13px] text-slate-700 mb-2 font-medium leading-tight tracking-[-0.005em]">
@@ -115,22 +148,50 @@ export function WindowsVariant() {
               variant="windows"
               label="Auto-capitalize"
               description="First letter after punctuation"
-              enabled={autoCapitalize}
-              onChange={handleAutoCapChange}
+              enabled={options.auto_capitalize}
+              onChange={updateOption('auto_capitalize')}
             />
             <ToggleRow
               variant="windows"
               label="Smart quotes"
               description="Convert to curly quotes"
-              enabled={smartQuotes}
-              onChange={handleSmartQuotesChange}
+              enabled={options.smart_quotes}
+              onChange={updateOption('smart_quotes')}
             />
             <ToggleRow
               variant="windows"
               label="Spell check"
               description="Underline misspelled words"
-              enabled={spellCheck}
-              onChange={handleSpellCheckChange}
+              enabled={options.spell_check}
+              onChange={updateOption('spell_check')}
+            />
+            <ToggleRow
+              variant="windows"
+              label="Modern tone placement"
+              description="hoà, thuỷ instead of hòa, thủy"
+              enabled={options.modern_tone}
+              onChange={updateOption('modern_tone')}
+            />
+            <ToggleRow
+              variant="windows"
+              label="Quick Telex"
+              description="cc → ch, nn → nh, tt → th"
+              enabled={options.quick_telex}
+              onChange={updateOption('quick_telex')}
+            />
+            <ToggleRow
+              variant="windows"
+              label="Bracket shortcuts"
+              description="[ → ư, ] → ơ"
+              enabled={options.bracket_shortcuts}
+              onChange={updateOption('bracket_shortcuts')}
+            />
+            <ToggleRow
+              variant="windows"
+              label="Diacritic removal"
+              description="z (Telex) or 0 (VNI) clears marks"
+              enabled={options.diacritic_removal}
+              onChange={updateOption('diacritic_removal')}
             />
           </div>
         </div>
@@ -0,0 +1,87 @@
+//! Compile the bundled word lists into FSTs
+//!
+//! Syllable keys are the toneless syllable followed by the tone's digit,
+//! matching `dictionary::key`, so the tone mark may sit on any vowel in the
+//! list. English words map to 1 if marked with `!`, as in `Lexicon::parse`.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const SYLLABLES: &str = "data/syllables.txt";
+const ENGLISH: &str = "data/english.txt";
+
+/// Toned forms of each vowel, in `ToneMark` order (sắc, huyền, hỏi, ngã, nặng)
+const TONED: [(char, &str); 12] = [
+    ('a', "áàảãạ"),
+    ('ă', "ắằẳẵặ"),
+    ('â', "ấầẩẫậ"),
+    ('e', "éèẻẽẹ"),
+    ('ê', "ếềểễệ"),
+    ('i', "íìỉĩị"),
+    ('o', "óòỏõọ"),
+    ('ô', "ốồổỗộ"),
+    ('ơ', "ớờởỡợ"),
+    ('u', "úùủũụ"),
+    ('ư', "ứừửữự"),
+    ('y', "ýỳỷỹỵ"),
+];
+
+fn key(syllable: &str) -> String {
+    let mut key = String::with_capacity(syllable.len() + 1);
+    let mut tone = 0;
+    for ch in syllable.chars() {
+        let toned = TONED.iter().find_map(|&(base, forms)| {
+            forms
+                .chars()
+                .position(|form| form == ch)
+                .map(|index| (base, index + 1))
+        });
+        match toned {
+            Some((base, index)) => {
+                key.push(base);
+                tone = index;
+            }
+            None => key.push(ch),
+        }
+    }
+    key.push(char::from(b'0' + tone as u8));
+    key
+}
+
+/// Lines of a list, without blanks and `#` comments
+fn entries(path: &str) -> Vec<String> {
+    println!("cargo:rerun-if-changed={}", path);
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn write(name: &str, bytes: &[u8]) {
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join(name);
+    fs::write(out, bytes).expect("write FST");
+}
+
+fn main() {
+    let mut keys: Vec<String> = entries(SYLLABLES).iter().map(|s| key(s)).collect();
+    keys.sort();
+    keys.dedup();
+    let set = fst::Set::from_iter(keys).expect("sorted keys");
+    write("syllables.fst", set.as_fst().as_bytes());
+
+    let mut words = BTreeMap::new();
+    for line in entries(ENGLISH) {
+        let (word, certain) = match line.strip_prefix('!') {
+            Some(word) => (word.to_lowercase(), 1),
+            None => (line.to_lowercase(), 0),
+        };
+        let entry = words.entry(word).or_insert(certain);
+        *entry = (*entry).max(certain);
+    }
+    let map = fst::Map::from_iter(words).expect("sorted words");
+    write("english.fst", map.as_fst().as_bytes());
+}
@@ -0,0 +1,130 @@
+//! vietflux-convert - batch text conversion
+//!
+//! Reads a file (or stdin) and writes the converted text to stdout:
+//!
+//! ```text
+//! vietflux-convert keys --method vni notes.txt
+//! vietflux-convert strip < title.txt
+//! vietflux-convert charset --from tcvn3 --to unicode old.txt > new.txt
+//! ```
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use vietflux_core::charset::{self, Charset};
+use vietflux_core::{convert, methods};
+
+const USAGE: &str = "\
+Usage: vietflux-convert <command> [options] [file]
+
+Commands:
+  keys [--method NAME]         Type keystroke text through the engine
+                               (telex, simple-telex, telex-w, vni, viqr;
+                               default telex)
+  strip                        Remove all diacritics
+  strip-tones                  Remove tone marks only
+  charset --from CS --to CS    Convert between unicode, tcvn3,
+                               vni-windows, viscii and vps
+
+Reads the file, or stdin if none is given, and writes to stdout.";
+
+enum Command {
+    Keys { method: String },
+    Strip,
+    StripTones,
+    Charset { from: Charset, to: Charset },
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let (command, path) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("vietflux-convert: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&command, path.as_deref()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("vietflux-convert: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(Command, Option<String>), String> {
+    let (name, rest) = args.split_first().ok_or("missing command")?;
+
+    let mut method = "telex".to_string();
+    let mut from = None;
+    let mut to = None;
+    let mut path = None;
+
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--method" => method = value()?.to_lowercase(),
+            "--from" => from = Some(parse_charset(&value()?)?),
+            "--to" => to = Some(parse_charset(&value()?)?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if path.is_none() => path = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let command = match name.as_str() {
+        "keys" => {
+            if !methods::BUILTIN_METHODS.contains(&method.as_str()) {
+                return Err(format!("unknown input method {}", method));
+            }
+            Command::Keys { method }
+        }
+        "strip" => Command::Strip,
+        "strip-tones" => Command::StripTones,
+        "charset" => Command::Charset {
+            from: from.ok_or("charset needs --from")?,
+            to: to.ok_or("charset needs --to")?,
+        },
+        _ => return Err(format!("unknown command {}", name)),
+    };
+    Ok((command, path))
+}
+
+fn parse_charset(name: &str) -> Result<Charset, String> {
+    Charset::from_name(name).ok_or_else(|| format!("unknown charset {}", name))
+}
+
+fn run(command: &Command, path: Option<&str>) -> io::Result<()> {
+    let input = match path {
+        Some(path) => std::fs::read(path)?,
+        None => {
+            let mut buf = Vec::new();
+            io::stdin().read_to_end(&mut buf)?;
+            buf
+        }
+    };
+
+    let output = match command {
+        Command::Charset { from, to } => charset::convert(&input, *from, *to),
+        Command::Keys { method } => convert::keystrokes_to_text(&utf8(input)?, method).into_bytes(),
+        Command::Strip => convert::strip_diacritics(&utf8(input)?).into_bytes(),
+        Command::StripTones => convert::strip_tones(&utf8(input)?).into_bytes(),
+    };
+
+    io::stdout().write_all(&output)
+}
+
+fn utf8(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
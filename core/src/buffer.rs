@@ -3,11 +3,13 @@
 //! Zero-copy buffer for efficient keystroke processing.
 //! Stores raw input and transformed Vietnamese text.
 
+use serde::{Deserialize, Serialize};
+
 /// Maximum buffer size (one Vietnamese word rarely exceeds 10 chars)
 pub const MAX_BUFFER_SIZE: usize = 32;
 
 /// A single character in the buffer with metadata
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct BufferChar {
     /// The actual character
     pub ch: char,
@@ -69,6 +71,15 @@ impl Buffer {
         result
     }
 
+    /// Replace `len` characters at `start` with `chars`
+    pub fn splice(&mut self, start: usize, len: usize, chars: &[BufferChar]) {
+        let start = start.min(self.chars.len());
+        let end = (start + len).min(self.chars.len());
+        self.chars.splice(start..end, chars.iter().copied());
+        self.chars.truncate(MAX_BUFFER_SIZE);
+        self.cursor = self.chars.len();
+    }
+
     /// Clear the buffer
     pub fn clear(&mut self) {
         self.chars.clear();
@@ -197,4 +208,17 @@ mod tests {
         let vowels = buf.find_vowels();
         assert_eq!(vowels, vec![1, 2]); // 'i' and 'e'
     }
+
+    #[test]
+    fn test_buffer_splice() {
+        let mut buf = Buffer::new();
+        for ch in "chao".chars() {
+            buf.push_simple(ch);
+        }
+        buf.splice(0, 2, &[BufferChar::simple('c'), BufferChar::simple('c')]);
+        assert_eq!(buf.get_text(), "ccao");
+
+        buf.splice(4, 0, &[BufferChar::simple('w')]);
+        assert_eq!(buf.get_text(), "ccaow");
+    }
 }
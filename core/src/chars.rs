@@ -3,6 +3,7 @@
 //! Contains all Vietnamese character mappings, tones, and marks.
 //! Based on Unicode Vietnamese block.
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::LazyLock;
 
@@ -15,7 +16,8 @@ pub const CONSONANTS: &[char] = &[
 ];
 
 /// Tone marks (dấu)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ToneMark {
     None,  // không dấu
     Acute, // sắc (á)
@@ -26,7 +28,8 @@ pub enum ToneMark {
 }
 
 /// Vowel modifiers (mũ/móc)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VowelMod {
     None,       // a, e, o, u
     Circumflex, // â, ê, ô (mũ)
@@ -143,10 +146,20 @@ pub static CHAR_MAP: LazyLock<HashMap<(char, VowelMod, ToneMark), char>> = LazyL
 pub static REVERSE_MAP: LazyLock<HashMap<char, (char, VowelMod, ToneMark)>> =
     LazyLock::new(|| CHAR_MAP.iter().map(|(&k, &v)| (v, k)).collect());
 
+/// Lowercase a single character (Vietnamese letters map one-to-one)
+pub fn to_lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Uppercase a single character (Vietnamese letters map one-to-one)
+pub fn to_upper(c: char) -> char {
+    c.to_uppercase().next().unwrap_or(c)
+}
+
 /// Check if a character is a vowel (including modified forms)
 pub fn is_vowel(c: char) -> bool {
-    let lower = c.to_ascii_lowercase();
-    VOWELS.contains(&lower) || REVERSE_MAP.contains_key(&c)
+    let lower = to_lower(c);
+    VOWELS.contains(&lower) || REVERSE_MAP.contains_key(&lower)
 }
 
 /// Check if a character is a consonant
@@ -156,20 +169,18 @@ pub fn is_consonant(c: char) -> bool {
 
 /// Get the base form of a Vietnamese character
 pub fn get_base(c: char) -> char {
-    REVERSE_MAP
-        .get(&c.to_ascii_lowercase())
-        .map_or(c, |&(base, _, _)| {
-            if c.is_uppercase() {
-                base.to_ascii_uppercase()
-            } else {
-                base
-            }
-        })
+    REVERSE_MAP.get(&to_lower(c)).map_or(c, |&(base, _, _)| {
+        if c.is_uppercase() {
+            to_upper(base)
+        } else {
+            base
+        }
+    })
 }
 
 /// Get character with new tone mark
 pub fn with_tone(c: char, tone: ToneMark) -> Option<char> {
-    let lower = c.to_ascii_lowercase();
+    let lower = to_lower(c);
     let (base, modifier, _) =
         REVERSE_MAP
             .get(&lower)
@@ -178,7 +189,7 @@ pub fn with_tone(c: char, tone: ToneMark) -> Option<char> {
 
     CHAR_MAP.get(&(base, modifier, tone)).map(|&result| {
         if c.is_uppercase() {
-            result.to_uppercase().next().unwrap_or(result)
+            to_upper(result)
         } else {
             result
         }
@@ -187,7 +198,7 @@ pub fn with_tone(c: char, tone: ToneMark) -> Option<char> {
 
 /// Get character with new vowel modifier
 pub fn with_modifier(c: char, modifier: VowelMod) -> Option<char> {
-    let lower = c.to_ascii_lowercase();
+    let lower = to_lower(c);
     let (base, _, tone) =
         REVERSE_MAP
             .get(&lower)
@@ -196,7 +207,7 @@ pub fn with_modifier(c: char, modifier: VowelMod) -> Option<char> {
 
     CHAR_MAP.get(&(base, modifier, tone)).map(|&result| {
         if c.is_uppercase() {
-            result.to_ascii_uppercase()
+            to_upper(result)
         } else {
             result
         }
@@ -227,5 +238,14 @@ mod tests {
         assert_eq!(with_modifier('a', VowelMod::Circumflex), Some('â'));
         assert_eq!(with_modifier('á', VowelMod::Circumflex), Some('ấ'));
         assert_eq!(with_modifier('o', VowelMod::Horn), Some('ơ'));
+        assert_eq!(with_modifier('Ố', VowelMod::Horn), Some('Ớ'));
+    }
+
+    #[test]
+    fn test_uppercase_vietnamese() {
+        assert!(is_vowel('Ư'));
+        assert_eq!(get_base('Ầ'), 'A');
+        assert_eq!(with_tone('Ơ', ToneMark::Grave), Some('Ờ'));
+        assert_eq!(with_tone('Ấ', ToneMark::None), Some('Â'));
     }
 }
@@ -0,0 +1,392 @@
+//! Legacy Charsets
+//!
+//! Converts Vietnamese text between Unicode and the 8-bit encodings still
+//! found in older documents and fonts:
+//! - TCVN3 (ABC): one byte per letter. Capitals with marks only exist in
+//!   the uppercase fonts (.VnTimeH), which reuse the lowercase codes, so
+//!   only Ă Â Ê Ô Ơ Ư Đ keep their case.
+//! - VNI-Windows: a letter followed by a mark byte (á = "aù"), so some
+//!   letters take two bytes
+//! - VISCII: one byte per letter in both cases
+//! - VPS: one byte per letter, except that Ạ Ặ Ậ Ẫ Ẹ Ệ Ị Ọ Ộ Ợ Ụ Ự Ữ Ỵ
+//!   share the code of their lowercase letter, so they decode lowercase
+
+use crate::chars::{self, ToneMark, VowelMod};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Text encoding for conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Charset {
+    /// UTF-8
+    Unicode,
+    /// TCVN 5712 VN3, used by the ABC fonts
+    Tcvn3,
+    /// VNI fonts on Windows
+    VniWindows,
+    /// VISCII (RFC 1456)
+    Viscii,
+    /// VPS (Vietnamese Professional Society)
+    Vps,
+}
+
+/// All supported charsets
+pub const CHARSETS: &[Charset] = &[
+    Charset::Unicode,
+    Charset::Tcvn3,
+    Charset::VniWindows,
+    Charset::Viscii,
+    Charset::Vps,
+];
+
+impl Charset {
+    /// Canonical name
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unicode => "unicode",
+            Self::Tcvn3 => "tcvn3",
+            Self::VniWindows => "vni-windows",
+            Self::Viscii => "viscii",
+            Self::Vps => "vps",
+        }
+    }
+
+    /// Look up a charset by name (case-insensitive, common aliases accepted)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "unicode" | "utf-8" | "utf8" => Some(Self::Unicode),
+            "tcvn3" | "tcvn" | "abc" => Some(Self::Tcvn3),
+            "vni-windows" | "vni" => Some(Self::VniWindows),
+            "viscii" => Some(Self::Viscii),
+            "vps" => Some(Self::Vps),
+            _ => None,
+        }
+    }
+
+    /// Encode text; characters the charset lacks become '?'
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        let Some(table) = self.table() else {
+            return text.as_bytes().to_vec();
+        };
+        let mut out = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            table.encode_char(c, &mut out);
+        }
+        out
+    }
+
+    /// Decode bytes; unknown bytes become U+FFFD
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self.table() {
+            Some(table) => table.decode(bytes),
+            None => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+
+    /// Number of bytes a character takes in this charset
+    pub fn char_len(self, c: char) -> usize {
+        match self.table() {
+            Some(table) => table.lookup(c).map_or(1, |bytes| bytes.len()),
+            None => c.len_utf8(),
+        }
+    }
+
+    fn table(self) -> Option<&'static Table> {
+        match self {
+            Self::Unicode => None,
+            Self::Tcvn3 => Some(&TCVN3),
+            Self::VniWindows => Some(&VNI_WINDOWS),
+            Self::Viscii => Some(&VISCII),
+            Self::Vps => Some(&VPS),
+        }
+    }
+}
+
+/// Convert text from one charset to another
+pub fn convert(text: &[u8], from: Charset, to: Charset) -> Vec<u8> {
+    to.encode(&from.decode(text))
+}
+
+/// Two-way mapping for the non-ASCII part of a charset
+struct Table {
+    encode: HashMap<char, Vec<u8>>,
+    decode: HashMap<Vec<u8>, char>,
+    /// Encode capitals the charset lacks as their lowercase form
+    fold_case: bool,
+}
+
+impl Table {
+    /// Build from (character, bytes) pairs; when several characters share
+    /// bytes, the first one is what they decode to
+    fn new(entries: impl IntoIterator<Item = (char, Vec<u8>)>, fold_case: bool) -> Self {
+        let mut table = Self {
+            encode: HashMap::new(),
+            decode: HashMap::new(),
+            fold_case,
+        };
+        for (c, bytes) in entries {
+            table.decode.entry(bytes.clone()).or_insert(c);
+            table.encode.insert(c, bytes);
+        }
+        table
+    }
+
+    fn single_byte(entries: &[(char, u8)], fold_case: bool) -> Self {
+        Self::new(entries.iter().map(|&(c, b)| (c, vec![b])), fold_case)
+    }
+
+    fn lookup(&self, c: char) -> Option<&[u8]> {
+        self.encode
+            .get(&c)
+            .or_else(|| {
+                self.fold_case
+                    .then(|| self.encode.get(&chars::to_lower(c)))
+                    .flatten()
+            })
+            .map(Vec::as_slice)
+    }
+
+    fn encode_char(&self, c: char, out: &mut Vec<u8>) {
+        if c.is_ascii() {
+            out.push(c as u8);
+        } else {
+            out.extend_from_slice(self.lookup(c).unwrap_or(b"?"));
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        let mut out = String::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            // Longest match first: VNI "aù" is á, not a + mark
+            let pair = bytes.get(i..i + 2).and_then(|p| self.decode.get(p));
+            if let Some(&c) = pair {
+                out.push(c);
+                i += 2;
+                continue;
+            }
+            let b = bytes[i];
+            out.push(match self.decode.get(&bytes[i..=i]) {
+                Some(&c) => c,
+                None if b.is_ascii() => char::from(b),
+                None => char::REPLACEMENT_CHARACTER,
+            });
+            i += 1;
+        }
+        out
+    }
+}
+
+static TCVN3: LazyLock<Table> = LazyLock::new(|| Table::single_byte(TCVN3_BYTES, true));
+
+static VISCII: LazyLock<Table> = LazyLock::new(|| Table::single_byte(VISCII_BYTES, false));
+
+static VPS: LazyLock<Table> = LazyLock::new(|| Table::single_byte(VPS_BYTES, true));
+
+static VNI_WINDOWS: LazyLock<Table> = LazyLock::new(|| {
+    let mut entries = vec![('đ', vec![0xF1]), ('Đ', vec![0xD1])];
+    for (&c, &(base, modifier, tone)) in chars::REVERSE_MAP.iter() {
+        if c.is_ascii() {
+            continue;
+        }
+        entries.push((c, vni_bytes(base, modifier, tone, false)));
+        entries.push((chars::to_upper(c), vni_bytes(base, modifier, tone, true)));
+    }
+    Table::new(entries, false)
+});
+
+/// VNI-Windows bytes for a vowel
+///
+/// Capital forms use the capital letter and a mark byte 0x20 lower.
+fn vni_bytes(base: char, modifier: VowelMod, tone: ToneMark, upper: bool) -> Vec<u8> {
+    let case = |b: u8| if upper { b - 0x20 } else { b };
+
+    // Letters VNI stores in a single byte
+    let single = match (base, tone) {
+        ('i', ToneMark::Acute) => Some(0xED),
+        ('i', ToneMark::Grave) => Some(0xEC),
+        ('i', ToneMark::Hook) => Some(0xE6),
+        ('i', ToneMark::Tilde) => Some(0xF3),
+        ('i', ToneMark::Dot) => Some(0xF2),
+        ('y', ToneMark::Dot) => Some(0xEE),
+        _ => None,
+    };
+    if let Some(b) = single {
+        return vec![case(b)];
+    }
+
+    // ơ and ư are letters of their own; the tone follows as a mark
+    let (lead, modifier) = match (base, modifier) {
+        ('o', VowelMod::Horn) => (case(0xF4), VowelMod::None),
+        ('u', VowelMod::Horn) => (case(0xF6), VowelMod::None),
+        _ if upper => (base.to_ascii_uppercase() as u8, modifier),
+        _ => (base as u8, modifier),
+    };
+
+    let mark = match (modifier, tone) {
+        (VowelMod::None, ToneMark::None) => None,
+        (VowelMod::None, ToneMark::Acute) => Some(0xF9),
+        (VowelMod::None, ToneMark::Grave) => Some(0xF8),
+        (VowelMod::None, ToneMark::Hook) => Some(0xFB),
+        (VowelMod::None, ToneMark::Tilde) => Some(0xF5),
+        (VowelMod::None, ToneMark::Dot) => Some(0xEF),
+        (VowelMod::Circumflex, ToneMark::None) => Some(0xE2),
+        (VowelMod::Circumflex, ToneMark::Acute) => Some(0xE1),
+        (VowelMod::Circumflex, ToneMark::Grave) => Some(0xE0),
+        (VowelMod::Circumflex, ToneMark::Hook) => Some(0xE5),
+        (VowelMod::Circumflex, ToneMark::Tilde) => Some(0xE3),
+        (VowelMod::Circumflex, ToneMark::Dot) => Some(0xE4),
+        (VowelMod::Breve, ToneMark::None) => Some(0xEA),
+        (VowelMod::Breve, ToneMark::Acute) => Some(0xE9),
+        (VowelMod::Breve, ToneMark::Grave) => Some(0xE8),
+        (VowelMod::Breve, ToneMark::Hook) => Some(0xFA),
+        (VowelMod::Breve, ToneMark::Tilde) => Some(0xFC),
+        (VowelMod::Breve, ToneMark::Dot) => Some(0xEB),
+        (VowelMod::Horn, _) => unreachable!("horn vowels are handled above"),
+    };
+
+    std::iter::once(lead).chain(mark.map(case)).collect()
+}
+
+/// TCVN3 (VN3) codes: capitals with their own code, then lowercase letters
+#[rustfmt::skip]
+const TCVN3_BYTES: &[(char, u8)] = &[
+    ('Ă', 0xA1), ('Â', 0xA2), ('Ê', 0xA3), ('Ô', 0xA4), ('Ơ', 0xA5), ('Ư', 0xA6), ('Đ', 0xA7),
+    ('à', 0xB5), ('á', 0xB8), ('â', 0xA9), ('ầ', 0xC7), ('ấ', 0xCA), ('ẫ', 0xC9), ('ẩ', 0xC8),
+    ('ã', 0xB7), ('ă', 0xA8), ('ằ', 0xBB), ('ắ', 0xBE), ('ẵ', 0xBD), ('ẳ', 0xBC), ('ả', 0xB6),
+    ('ạ', 0xB9), ('ậ', 0xCB), ('ặ', 0xC6), ('è', 0xCC), ('é', 0xD0), ('ê', 0xAA), ('ề', 0xD2),
+    ('ế', 0xD5), ('ễ', 0xD4), ('ể', 0xD3), ('ẽ', 0xCF), ('ẻ', 0xCE), ('ẹ', 0xD1), ('ệ', 0xD6),
+    ('ì', 0xD7), ('í', 0xDD), ('ĩ', 0xDC), ('ỉ', 0xD8), ('ị', 0xDE), ('ò', 0xDF), ('ó', 0xE3),
+    ('ô', 0xAB), ('ồ', 0xE5), ('ố', 0xE8), ('ỗ', 0xE7), ('ổ', 0xE6), ('õ', 0xE2), ('ỏ', 0xE1),
+    ('ơ', 0xAC), ('ờ', 0xEA), ('ớ', 0xED), ('ỡ', 0xEC), ('ở', 0xEB), ('ợ', 0xEE), ('ọ', 0xE4),
+    ('ộ', 0xE9), ('ù', 0xEF), ('ú', 0xF3), ('ũ', 0xF2), ('ủ', 0xF1), ('ư', 0xAD), ('ừ', 0xF5),
+    ('ứ', 0xF8), ('ữ', 0xF7), ('ử', 0xF6), ('ự', 0xF9), ('ụ', 0xF4), ('ỳ', 0xFA), ('ý', 0xFD),
+    ('ỹ', 0xFC), ('ỷ', 0xFB), ('ỵ', 0xFE), ('đ', 0xAE),
+];
+
+/// VISCII codes
+#[rustfmt::skip]
+const VISCII_BYTES: &[(char, u8)] = &[
+    ('à', 0xE0), ('À', 0xC0), ('á', 0xE1), ('Á', 0xC1), ('â', 0xE2), ('Â', 0xC2), ('ầ', 0xA5),
+    ('Ầ', 0x85), ('ấ', 0xA4), ('Ấ', 0x84), ('ẫ', 0xE7), ('Ẫ', 0x06), ('ẩ', 0xA6), ('Ẩ', 0x86),
+    ('ã', 0xE3), ('Ã', 0xC3), ('ă', 0xE5), ('Ă', 0xC5), ('ằ', 0xA2), ('Ằ', 0x82), ('ắ', 0xA1),
+    ('Ắ', 0x81), ('ẵ', 0xC7), ('Ẵ', 0x05), ('ẳ', 0xC6), ('Ẳ', 0x02), ('ả', 0xE4), ('Ả', 0xC4),
+    ('ạ', 0xD5), ('Ạ', 0x80), ('ậ', 0xA7), ('Ậ', 0x87), ('ặ', 0xA3), ('Ặ', 0x83), ('è', 0xE8),
+    ('È', 0xC8), ('é', 0xE9), ('É', 0xC9), ('ê', 0xEA), ('Ê', 0xCA), ('ề', 0xAB), ('Ề', 0x8B),
+    ('ế', 0xAA), ('Ế', 0x8A), ('ễ', 0xAD), ('Ễ', 0x8D), ('ể', 0xAC), ('Ể', 0x8C), ('ẽ', 0xA8),
+    ('Ẽ', 0x88), ('ẻ', 0xEB), ('Ẻ', 0xCB), ('ẹ', 0xA9), ('Ẹ', 0x89), ('ệ', 0xAE), ('Ệ', 0x8E),
+    ('ì', 0xEC), ('Ì', 0xCC), ('í', 0xED), ('Í', 0xCD), ('ĩ', 0xEE), ('Ĩ', 0xCE), ('ỉ', 0xEF),
+    ('Ỉ', 0x9B), ('ị', 0xB8), ('Ị', 0x98), ('ò', 0xF2), ('Ò', 0xD2), ('ó', 0xF3), ('Ó', 0xD3),
+    ('ô', 0xF4), ('Ô', 0xD4), ('ồ', 0xB0), ('Ồ', 0x90), ('ố', 0xAF), ('Ố', 0x8F), ('ỗ', 0xB2),
+    ('Ỗ', 0x92), ('ổ', 0xB1), ('Ổ', 0x91), ('õ', 0xF5), ('Õ', 0xA0), ('ỏ', 0xF6), ('Ỏ', 0x99),
+    ('ơ', 0xBD), ('Ơ', 0xB4), ('ờ', 0xB6), ('Ờ', 0x96), ('ớ', 0xBE), ('Ớ', 0x95), ('ỡ', 0xDE),
+    ('Ỡ', 0xB3), ('ở', 0xB7), ('Ở', 0x97), ('ợ', 0xFE), ('Ợ', 0x94), ('ọ', 0xF7), ('Ọ', 0x9A),
+    ('ộ', 0xB5), ('Ộ', 0x93), ('ù', 0xF9), ('Ù', 0xD9), ('ú', 0xFA), ('Ú', 0xDA), ('ũ', 0xFB),
+    ('Ũ', 0x9D), ('ủ', 0xFC), ('Ủ', 0x9C), ('ư', 0xDF), ('Ư', 0xBF), ('ừ', 0xD7), ('Ừ', 0xBB),
+    ('ứ', 0xD1), ('Ứ', 0xBA), ('ữ', 0xE6), ('Ữ', 0xFF), ('ử', 0xD8), ('Ử', 0xBC), ('ự', 0xF1),
+    ('Ự', 0xB9), ('ụ', 0xF8), ('Ụ', 0x9E), ('ỳ', 0xCF), ('Ỳ', 0x9F), ('ý', 0xFD), ('Ý', 0xDD),
+    ('ỹ', 0xDB), ('Ỹ', 0x19), ('ỷ', 0xD6), ('Ỷ', 0x14), ('ỵ', 0xDC), ('Ỵ', 0x1E), ('đ', 0xF0),
+    ('Đ', 0xD0),
+];
+
+/// VPS codes; the capitals missing here fold to their lowercase code
+#[rustfmt::skip]
+const VPS_BYTES: &[(char, u8)] = &[
+    ('à', 0xE0), ('À', 0x80), ('á', 0xE1), ('Á', 0xC1), ('â', 0xE2), ('Â', 0xC2), ('ầ', 0xC0),
+    ('Ầ', 0x84), ('ấ', 0xC3), ('Ấ', 0x83), ('ẫ', 0xC5), ('ẩ', 0xC4), ('Ẩ', 0x85), ('ã', 0xE3),
+    ('Ã', 0x82), ('ă', 0xE6), ('Ă', 0x88), ('ằ', 0xA2), ('Ằ', 0x8E), ('ắ', 0xA1), ('Ắ', 0x8D),
+    ('ẵ', 0xA4), ('Ẵ', 0xF0), ('ẳ', 0xA3), ('Ẳ', 0x8F), ('ả', 0xE4), ('Ả', 0x81), ('ạ', 0xE5),
+    ('ậ', 0xC6), ('ặ', 0xA5), ('è', 0xE8), ('È', 0xD7), ('é', 0xE9), ('É', 0xC9), ('ê', 0xEA),
+    ('Ê', 0xCA), ('ề', 0x8A), ('Ề', 0x93), ('ế', 0x89), ('Ế', 0x90), ('ễ', 0xCD), ('Ễ', 0x95),
+    ('ể', 0x8B), ('Ể', 0x94), ('ẽ', 0xEB), ('Ẽ', 0xFE), ('ẻ', 0xC8), ('Ẻ', 0xDE), ('ẹ', 0xCB),
+    ('ệ', 0x8C), ('ì', 0xEC), ('Ì', 0xB5), ('í', 0xED), ('Í', 0xB4), ('ĩ', 0xEF), ('Ĩ', 0xB8),
+    ('ỉ', 0xCC), ('Ỉ', 0xB7), ('ị', 0xCE), ('ò', 0xF2), ('Ò', 0xBC), ('ó', 0xF3), ('Ó', 0xB9),
+    ('ô', 0xF4), ('Ô', 0xD4), ('ồ', 0xD2), ('Ồ', 0x97), ('ố', 0xD3), ('Ố', 0x96), ('ỗ', 0x87),
+    ('Ỗ', 0x99), ('ổ', 0xB0), ('Ổ', 0x98), ('õ', 0xF5), ('Õ', 0xBE), ('ỏ', 0xD5), ('Ỏ', 0xBD),
+    ('ơ', 0xD6), ('Ơ', 0xF7), ('ờ', 0xA9), ('Ờ', 0x9E), ('ớ', 0xA7), ('Ớ', 0x9D), ('ỡ', 0xAB),
+    ('Ỡ', 0xA6), ('ở', 0xAA), ('Ở', 0x9F), ('ợ', 0xAE), ('ọ', 0x86), ('ộ', 0xB6), ('ù', 0xF9),
+    ('Ù', 0xA8), ('ú', 0xFA), ('Ú', 0xDA), ('ũ', 0xDB), ('Ũ', 0xAC), ('ủ', 0xFB), ('Ủ', 0xD1),
+    ('ư', 0xDC), ('Ư', 0xD0), ('ừ', 0xD8), ('Ừ', 0xAF), ('ứ', 0xD9), ('Ứ', 0xAD), ('ữ', 0xBB),
+    ('ử', 0xBA), ('Ử', 0xB1), ('ự', 0xBF), ('ụ', 0xF8), ('ỳ', 0xFF), ('Ỳ', 0xB2), ('ý', 0x9A),
+    ('Ý', 0xDD), ('ỹ', 0xCF), ('Ỹ', 0xB3), ('ỷ', 0x9B), ('Ỷ', 0xFD), ('ỵ', 0x9C), ('đ', 0xC7),
+    ('Đ', 0xF1),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_legacy() {
+        assert_eq!(Charset::Viscii.encode("Tiếng Việt"), b"Ti\xAAng Vi\xAEt");
+        assert_eq!(Charset::Tcvn3.encode("Tiếng Việt"), b"Ti\xD5ng Vi\xD6t");
+        assert_eq!(
+            Charset::VniWindows.encode("Tiếng Việt"),
+            b"Tie\xE1ng Vie\xE4t"
+        );
+        assert_eq!(Charset::VniWindows.encode("Người"), b"Ng\xF6\xF4\xF8i");
+        assert_eq!(Charset::VniWindows.encode("ĐỊA"), b"\xD1\xD2A");
+    }
+
+    #[test]
+    fn test_tcvn3_capitals_fold() {
+        assert_eq!(Charset::Tcvn3.encode("Ấn Đ"), b"\xCAn \xA7");
+        assert_eq!(Charset::Tcvn3.decode(b"\xCAn \xA7"), "ấn Đ");
+    }
+
+    #[test]
+    fn test_round_trip_all_letters() {
+        let letters: String = chars::REVERSE_MAP
+            .keys()
+            .flat_map(|&c| [c, chars::to_upper(c)])
+            .chain(['đ', 'Đ'])
+            .collect();
+        for charset in [Charset::VniWindows, Charset::Viscii, Charset::Unicode] {
+            let bytes = charset.encode(&letters);
+            assert_eq!(charset.decode(&bytes), letters, "{:?}", charset);
+        }
+        assert!(!Charset::Tcvn3.encode(&letters).contains(&b'?'));
+
+        // VPS keeps every letter but the capitals it folds
+        let folded: String = letters
+            .chars()
+            .map(|c| {
+                if "ẠẶẬẪẸỆỊỌỘỢỤỰỮỴ".contains(c) {
+                    chars::to_lower(c)
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let bytes = Charset::Vps.encode(&letters);
+        assert_eq!(bytes.len(), letters.chars().count());
+        assert_eq!(Charset::Vps.decode(&bytes), folded);
+    }
+
+    #[test]
+    fn test_vps() {
+        assert_eq!(Charset::Vps.encode("Tiếng Việt"), b"Ti\x89ng Vi\x8Ct");
+        assert_eq!(Charset::Vps.decode(b"Ti\x89ng Vi\x8Ct"), "Tiếng Việt");
+        assert_eq!(Charset::Vps.encode("Đà Nẵng"), b"\xF1\xE0 N\xA4ng");
+        assert_eq!(Charset::Vps.decode(b"\xF1\xE0 N\xA4ng"), "Đà Nẵng");
+
+        // Folded capitals come back lowercase
+        assert_eq!(Charset::Vps.encode("Ạ"), Charset::Vps.encode("ạ"));
+        assert_eq!(Charset::Vps.decode(&Charset::Vps.encode("ĐẠI")), "ĐạI");
+        assert_eq!(Charset::from_name("VPS"), Some(Charset::Vps));
+    }
+
+    #[test]
+    fn test_convert() {
+        let vni = Charset::VniWindows.encode("Hồ Chí Minh");
+        let viscii = convert(&vni, Charset::VniWindows, Charset::Viscii);
+        assert_eq!(Charset::Viscii.decode(&viscii), "Hồ Chí Minh");
+        assert_eq!(
+            convert(&viscii, Charset::Viscii, Charset::Unicode),
+            "Hồ Chí Minh".as_bytes()
+        );
+        assert_eq!(Charset::from_name("VNI"), Some(Charset::VniWindows));
+    }
+}
@@ -0,0 +1,158 @@
+//! Engine Configuration
+//!
+//! Everything a user can change on the engine in one serializable value:
+//! input method, feature options (including tone style), shortcut table,
+//! undo key, custom method definitions, foreign word lexicons and enabled
+//! state. Frontends save it as JSON and restore the engine from it on
+//! startup.
+
+use crate::engine::EngineOptions;
+use crate::methods::custom::MethodDefinition;
+use crate::shortcut::ShortcutTable;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Environment variable pointing front ends at a config file of their own
+pub const CONFIG_PATH_VAR: &str = "VIETFLUX_CONFIG";
+
+/// Serializable engine configuration
+///
+/// Missing fields fall back to their defaults, so older config files keep
+/// loading as new settings are added.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// Input method name (built-in or one of `custom_methods`)
+    pub method: String,
+    /// Feature switches
+    pub options: EngineOptions,
+    /// Whether Vietnamese input is on
+    pub enabled: bool,
+    /// Key that undoes the last transform
+    pub undo_key: Option<char>,
+    /// Shortcut table
+    pub shortcuts: ShortcutTable,
+    /// Custom input method definitions
+    pub custom_methods: Vec<MethodDefinition>,
+    /// User lexicons of foreign words (see `Lexicon::parse`)
+    pub lexicons: Vec<String>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            method: "telex".to_string(),
+            options: EngineOptions::default(),
+            enabled: true,
+            undo_key: None,
+            shortcuts: ShortcutTable::with_defaults(),
+            custom_methods: Vec::new(),
+            lexicons: Vec::new(),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Parse a configuration from JSON
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Serialize the configuration to pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Config file a front end should read: `$VIETFLUX_CONFIG` if set,
+/// otherwise `relative` inside the user's config directory
+/// (`$XDG_CONFIG_HOME`, falling back to `~/.config`)
+pub fn config_path(relative: &str) -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CONFIG_PATH_VAR).filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join(relative))
+}
+
+/// Read a saved configuration; `Ok(None)` if there is no file to read
+pub fn load_config(path: &Path) -> Result<Option<EngineConfig>, serde_json::Error> {
+    let Ok(json) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    EngineConfig::from_json(&json).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_json_round_trip() {
+        let mut config = EngineConfig {
+            method: "vni".to_string(),
+            undo_key: Some('`'),
+            ..Default::default()
+        };
+        config.options.modern_tone = false;
+
+        let json = config.to_json();
+        assert_eq!(EngineConfig::from_json(&json).unwrap(), config);
+        assert_eq!(EngineConfig::from_json(&json).unwrap().to_json(), json);
+    }
+
+    #[test]
+    fn test_config_missing_fields_use_defaults() {
+        let config = EngineConfig::from_json(r#"{ "method": "viqr" }"#).unwrap();
+        assert_eq!(config.method, "viqr");
+        assert!(config.enabled);
+        assert_eq!(config.options, EngineOptions::default());
+        assert_eq!(config.shortcuts, ShortcutTable::with_defaults());
+    }
+
+    #[test]
+    fn test_config_shortcuts_map_only() {
+        let json = r#"{
+            "options": { "auto_capitalize": false },
+            "shortcuts": {
+                "shortcuts": {
+                    "hn": {
+                        "trigger": "hn",
+                        "replacement": "Hà Nội",
+                        "condition": "OnWordBoundary",
+                        "enabled": true
+                    }
+                }
+            }
+        }"#;
+        let config = EngineConfig::from_json(json).unwrap();
+        assert!(!config.to_json().contains("sorted_triggers"));
+
+        let mut engine = crate::Engine::from_config(&config).unwrap();
+        engine.process_key('h', false);
+        engine.process_key('n', false);
+        assert_eq!(engine.process_key(' ', false).output, "Hà Nội ");
+    }
+
+    #[test]
+    fn test_config_path_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("vietflux-config-{}.json", std::process::id()));
+        std::env::set_var(CONFIG_PATH_VAR, &path);
+        assert_eq!(config_path("app/config.json"), Some(path.clone()));
+        std::env::remove_var(CONFIG_PATH_VAR);
+        assert!(config_path("app/config.json")
+            .unwrap()
+            .ends_with("app/config.json"));
+
+        assert_eq!(load_config(&path).unwrap(), None);
+        std::fs::write(&path, r#"{ "method": "vni" }"#).unwrap();
+        assert_eq!(load_config(&path).unwrap().unwrap().method, "vni");
+        std::fs::write(&path, "{").unwrap();
+        assert!(load_config(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
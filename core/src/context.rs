@@ -0,0 +1,236 @@
+//! Input Contexts
+//!
+//! One process often serves many text fields at once: an IBus/Fcitx
+//! front end has an input context per client, a web page has many text
+//! areas. `ContextManager` gives each of them its own composition while
+//! the configuration (method, options, shortcut table) is kept once,
+//! behind an `Arc`.
+//!
+//! Every context has its own `Engine`, cloned from one built from the
+//! configuration. The clones share its method, shortcut table and
+//! lexicons, so a context costs only its typing state: the word being
+//! composed, its undo history and capitalization state. Keys for
+//! different contexts need no state swapping, and the manager can be
+//! moved or shared across threads.
+
+use crate::config::EngineConfig;
+use crate::engine::{Engine, ProcessResult};
+use crate::methods::custom::DefinitionError;
+use crate::preedit::PreeditEvent;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Identifier of an input context, chosen by the front end (window
+/// handle, IBus context path hash, text area index...)
+pub type ContextId = u64;
+
+/// Independent input contexts sharing one configuration
+pub struct ContextManager {
+    /// Shared configuration
+    config: Arc<EngineConfig>,
+    /// Engine built from `config` with nothing typed; contexts start as
+    /// clones of it
+    configured: Engine,
+    /// Engine of every context that has typed something
+    contexts: HashMap<ContextId, Engine>,
+}
+
+impl ContextManager {
+    /// Create a manager with the given configuration
+    ///
+    /// Fails if one of the custom method definitions is invalid.
+    pub fn new(config: EngineConfig) -> Result<Self, DefinitionError> {
+        Ok(Self {
+            configured: Engine::from_config(&config)?,
+            config: Arc::new(config),
+            contexts: HashMap::new(),
+        })
+    }
+
+    /// Shared configuration
+    pub fn config(&self) -> Arc<EngineConfig> {
+        Arc::clone(&self.config)
+    }
+
+    /// Replace the configuration for every context
+    ///
+    /// Words being typed are kept; they pick up the new settings with
+    /// their next key.
+    pub fn set_config(&mut self, config: EngineConfig) -> Result<(), DefinitionError> {
+        self.configured = Engine::from_config(&config)?;
+        for engine in self.contexts.values_mut() {
+            let state = engine.snapshot();
+            *engine = self.configured.clone();
+            engine.restore_snapshot(&state);
+        }
+        self.config = Arc::new(config);
+        Ok(())
+    }
+
+    /// Process a key press in a context
+    pub fn process_key(&mut self, id: ContextId, key: char, shift: bool) -> ProcessResult {
+        self.context(id).process_key(key, shift)
+    }
+
+    /// Process a Backspace key press in a context
+    pub fn process_backspace(&mut self, id: ContextId) -> ProcessResult {
+        self.context(id).process_backspace()
+    }
+
+    /// Process a key press in a context that shows a preedit string
+    pub fn process_key_preedit(
+        &mut self,
+        id: ContextId,
+        key: char,
+        shift: bool,
+    ) -> Vec<PreeditEvent> {
+        self.context(id).process_key_preedit(key, shift)
+    }
+
+    /// Process a Backspace key press in a context that shows a preedit
+    /// string
+    pub fn process_backspace_preedit(&mut self, id: ContextId) -> Vec<PreeditEvent> {
+        self.context(id).process_backspace_preedit()
+    }
+
+    /// Commit the composition of a context as shown
+    pub fn commit_preedit(&mut self, id: ContextId) -> Vec<PreeditEvent> {
+        self.context(id).commit_preedit()
+    }
+
+    /// Drop the composition of a context without committing it
+    pub fn cancel_preedit(&mut self, id: ContextId) -> Vec<PreeditEvent> {
+        self.context(id).cancel_preedit()
+    }
+
+    /// Tell a context what precedes its caret, as its host's surrounding
+    /// text reports (see `Engine::set_text_before_cursor`)
+    pub fn set_text_before_cursor(&mut self, id: ContextId, text: &str) {
+        self.context(id).set_text_before_cursor(text);
+    }
+
+    /// Text of the word being typed in a context
+    pub fn get_buffer(&self, id: ContextId) -> String {
+        self.contexts
+            .get(&id)
+            .map(Engine::get_buffer)
+            .unwrap_or_default()
+    }
+
+    /// Forget a context (text field closed, or the caret moved so the
+    /// word being typed no longer applies); its next key starts fresh
+    pub fn remove(&mut self, id: ContextId) {
+        self.contexts.remove(&id);
+    }
+
+    /// Number of contexts with saved state
+    pub fn len(&self) -> usize {
+        self.contexts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+
+    /// Engine of a context, created from the configuration on first use
+    fn context(&mut self, id: ContextId) -> &mut Engine {
+        self.contexts
+            .entry(id)
+            .or_insert_with(|| self.configured.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineOptions;
+
+    fn manager() -> ContextManager {
+        let config = EngineConfig {
+            options: EngineOptions {
+                auto_capitalize: false,
+                ..EngineOptions::default()
+            },
+            ..EngineConfig::default()
+        };
+        ContextManager::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_contexts_compose_independently() {
+        let mut contexts = manager();
+
+        // Keys for two fields arrive interleaved
+        for (a, b) in "vieetj".chars().zip("dduwowcj".chars()) {
+            contexts.process_key(1, a, false);
+            contexts.process_key(2, b, false);
+        }
+        for key in "cj".chars() {
+            contexts.process_key(2, key, false);
+        }
+
+        assert_eq!(contexts.get_buffer(1), "việt");
+        assert_eq!(contexts.get_buffer(2), "được");
+        assert_eq!(contexts.len(), 2);
+
+        contexts.remove(1);
+        assert_eq!(contexts.get_buffer(1), "");
+        assert_eq!(contexts.len(), 1);
+    }
+
+    #[test]
+    fn test_set_config_keeps_words() {
+        let mut contexts = manager();
+        let before = contexts.config();
+
+        for key in "hoa".chars() {
+            contexts.process_key(7, key, false);
+        }
+        contexts.process_key(8, 'a', false);
+
+        let mut config = (*before).clone();
+        config.method = "vni".to_string();
+        contexts.set_config(config).unwrap();
+        assert_eq!(contexts.config().method, "vni");
+        assert_eq!(before.method, "telex");
+
+        contexts.process_key(7, '2', false);
+        assert_eq!(contexts.get_buffer(7), "hoà");
+        contexts.process_key(8, '6', false);
+        assert_eq!(contexts.get_buffer(8), "â");
+    }
+
+    #[test]
+    fn test_cancel_preedit_per_context() {
+        let mut contexts = manager();
+        for key in "chaof".chars() {
+            contexts.process_key_preedit(1, key, false);
+            contexts.process_key_preedit(2, key, false);
+        }
+
+        assert_eq!(contexts.cancel_preedit(1), vec![PreeditEvent::Cancel]);
+        assert_eq!(contexts.get_buffer(1), "");
+        assert_eq!(contexts.get_buffer(2), "chào");
+        assert_eq!(contexts.cancel_preedit(1), Vec::new());
+    }
+
+    #[test]
+    fn test_text_before_cursor_per_context() {
+        let mut contexts = ContextManager::new(EngineConfig::default()).unwrap();
+        contexts.set_text_before_cursor(1, "Xin chào ");
+        contexts.set_text_before_cursor(2, "Xong. ");
+
+        assert_eq!(contexts.process_key(1, 'b', false).output, "b");
+        assert_eq!(contexts.process_key(2, 'b', false).output, "B");
+    }
+
+    #[test]
+    fn test_manager_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ContextManager>();
+
+        let contexts = manager();
+        let config = contexts.config();
+        assert!(Arc::ptr_eq(&config, &contexts.config()));
+    }
+}
@@ -0,0 +1,172 @@
+//! Bulk Text Conversion
+//!
+//! Whole-document counterparts of what the engine does per key:
+//! - Strip tone marks or all diacritics (tiếng việt → tieng viet)
+//! - Replay raw keystroke text through `Engine` (Tieesng Vieetj → Tiếng Việt)
+//!
+//! Charset transcoding lives in `charset::convert`.
+
+use crate::engine::{Action, Engine, EngineOptions, ProcessResult};
+use crate::transform;
+
+/// Backspace in keystroke text
+pub const BACKSPACE: char = '\u{8}';
+
+/// Remove tone marks, keeping vowel modifiers and đ (tiếng → tiêng)
+pub fn strip_tones(text: &str) -> String {
+    text.chars()
+        .filter(|&c| !is_combining_tone(c))
+        .map(|c| transform::remove_tone(c).0)
+        .collect()
+}
+
+/// Remove all diacritics, including đ → d (Việt Nam → Viet Nam)
+pub fn strip_diacritics(text: &str) -> String {
+    text.chars()
+        .filter(|&c| !is_combining_mark(c))
+        .map(transform::remove_diacritics)
+        .collect()
+}
+
+/// Combining marks left in decomposed (NFD) input
+fn is_combining_mark(c: char) -> bool {
+    ('\u{0300}'..='\u{036F}').contains(&c)
+}
+
+/// Combining tone marks (sắc, huyền, hỏi, ngã, nặng)
+fn is_combining_tone(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}' | '\u{0301}' | '\u{0303}' | '\u{0309}' | '\u{0323}'
+    )
+}
+
+/// Type raw keystroke text through an engine and return what a text field
+/// would show afterwards
+///
+/// `BACKSPACE` characters in `keys` act as the Backspace key.
+pub fn replay(engine: &mut Engine, keys: &str) -> String {
+    let mut field = TextField::new();
+    for key in keys.chars() {
+        if key == BACKSPACE {
+            field.press_backspace(engine);
+        } else {
+            field.type_key(engine, key);
+        }
+    }
+    field.into_text()
+}
+
+/// Convert keystroke text typed with an input method to Vietnamese
+///
+/// Case is kept as typed (no auto-capitalization or smart quotes).
+pub fn keystrokes_to_text(keys: &str, method: &str) -> String {
+    let mut engine = Engine::new();
+    engine.set_method(method);
+    engine.set_options(EngineOptions {
+        auto_capitalize: false,
+        smart_quotes: false,
+        ..EngineOptions::default()
+    });
+    replay(&mut engine, keys)
+}
+
+/// Simulated host text field
+///
+/// Applies engine results the way the keyboard hooks do: Update and
+/// Restore delete `backspace` characters and type the output; Passthrough
+/// and Commit let the key itself through.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TextField {
+    text: String,
+}
+
+impl TextField {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Field that already holds some text before the cursor
+    pub fn with_text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+        }
+    }
+
+    /// Current contents
+    #[cfg(test)]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    /// Press a key with the engine attached
+    pub fn type_key(&mut self, engine: &mut Engine, key: char) -> ProcessResult {
+        let result = engine.process_key(key, key.is_uppercase());
+        self.apply(&result, Some(key));
+        result
+    }
+
+    /// Press Backspace with the engine attached
+    pub fn press_backspace(&mut self, engine: &mut Engine) -> ProcessResult {
+        let result = engine.process_backspace();
+        self.apply(&result, None);
+        result
+    }
+
+    /// Apply a result for `key` (None for Backspace)
+    ///
+    /// Backspace counts are code points, matching `OutputEncoding`.
+    pub fn apply(&mut self, result: &ProcessResult, key: Option<char>) {
+        match result.action {
+            Action::Update | Action::Restore => {
+                for _ in 0..result.backspace {
+                    self.text.pop();
+                }
+                self.text.push_str(&result.output);
+            }
+            Action::Commit | Action::Passthrough => match key {
+                Some(k) => self.text.push(k),
+                None => {
+                    self.text.pop();
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(
+            strip_diacritics("Tiếng Việt, Đà Nẵng"),
+            "Tieng Viet, Da Nang"
+        );
+        assert_eq!(strip_tones("Tiếng Việt, Đà Nẵng"), "Tiêng Viêt, Đa Năng");
+        // Decomposed input
+        assert_eq!(strip_diacritics("Vie\u{0302}\u{0323}t"), "Viet");
+        assert_eq!(strip_tones("Vie\u{0323}\u{0302}t"), "Vie\u{0302}t");
+    }
+
+    #[test]
+    fn test_keystrokes_to_text() {
+        assert_eq!(
+            keystrokes_to_text("Tieesng Vieetj laf ngoon nguwx.\nXin chaof!", "telex"),
+            "Tiếng Việt là ngôn ngữ.\nXin chào!"
+        );
+        assert_eq!(keystrokes_to_text("Tie61ng Vie65t", "vni"), "Tiếng Việt");
+        // English words are restored at the boundary
+        assert_eq!(keystrokes_to_text("windows ", "telex"), "windows ");
+    }
+
+    #[test]
+    fn test_replay_backspace() {
+        assert_eq!(keystrokes_to_text("tas\u{8}af", "telex"), "tà");
+    }
+}
@@ -0,0 +1,111 @@
+//! Vietnamese Syllable Dictionary
+//!
+//! Spell checking against `data/syllables.txt`, the syllables attested in
+//! real text, which `build.rs` compiles into an FST bundled with the
+//! library. A syllable is looked up by its
+//! toneless spelling plus its tone, so the tone mark may sit on either
+//! vowel (hoà, hòa) and capitalization does not matter.
+
+use crate::chars::{self, ToneMark};
+use crate::transform;
+use std::sync::LazyLock;
+
+static SYLLABLES: LazyLock<fst::Set<&'static [u8]>> = LazyLock::new(|| {
+    let bytes = include_bytes!(concat!(env!("OUT_DIR"), "/syllables.fst"));
+    fst::Set::new(&bytes[..]).expect("syllable FST built by build.rs")
+});
+
+/// Lookup key: lowercase toneless syllable, then the tone as a digit
+///
+/// `None` if more than one character carries a tone.
+fn key(syllable: &str) -> Option<String> {
+    let mut key = String::with_capacity(syllable.len() + 1);
+    let mut tone = ToneMark::None;
+    for ch in syllable.chars() {
+        let lower = chars::to_lower(ch);
+        let (base, toned) = transform::remove_tone(lower);
+        if toned {
+            if tone != ToneMark::None {
+                return None;
+            }
+            tone = transform::get_tone(lower);
+        }
+        key.push(base);
+    }
+    key.push(char::from(b'0' + tone as u8));
+    Some(key)
+}
+
+/// Check if a single syllable is Vietnamese (việt, Nẵng)
+pub fn contains_syllable(syllable: &str) -> bool {
+    key(syllable).is_some_and(|key| SYLLABLES.contains(key))
+}
+
+/// Check if every syllable of a word is Vietnamese (Việt Nam, Bà-rịa)
+pub fn contains(word: &str) -> bool {
+    let mut syllables = word
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter(|s| !s.is_empty())
+        .peekable();
+    syllables.peek().is_some() && syllables.all(contains_syllable)
+}
+
+/// Number of syllables in the dictionary
+pub fn len() -> usize {
+    SYLLABLES.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation;
+
+    #[test]
+    fn test_syllables() {
+        for syllable in [
+            "việt",
+            "nam",
+            "quốc",
+            "được",
+            "gì",
+            "giường",
+            "nghiêng",
+            "Đắk",
+        ] {
+            assert!(contains_syllable(syllable), "{}", syllable);
+        }
+        // Stop finals take only sắc or nặng; c/k and g/gh follow the vowel
+        for syllable in [
+            "tàc", "bàp", "cia", "ka", "ngiêng", "kuốc", "tiéng", "hello",
+        ] {
+            assert!(!contains_syllable(syllable), "{}", syllable);
+        }
+        assert!(len() > 3_000);
+    }
+
+    #[test]
+    fn test_unattested_syllables() {
+        // The spelling rules allow these, but no Vietnamese word uses them
+        for syllable in ["gưởm", "nhuỷnh", "bưởng", "khuých", "thoẹt"] {
+            assert!(validation::validate(syllable).is_valid(), "{}", syllable);
+            assert!(!contains_syllable(syllable), "{}", syllable);
+        }
+    }
+
+    #[test]
+    fn test_tone_placement_and_case() {
+        assert!(contains_syllable("hoà"));
+        assert!(contains_syllable("hòa"));
+        assert!(contains_syllable("VIỆT"));
+        assert!(!contains_syllable("viếtj"));
+        assert!(!contains_syllable("ÁÀ"));
+    }
+
+    #[test]
+    fn test_words() {
+        assert!(contains("Việt Nam"));
+        assert!(contains("Bà-rịa"));
+        assert!(!contains("Việt Namm"));
+        assert!(!contains(""));
+    }
+}
@@ -0,0 +1,172 @@
+//! Output Encoding
+//!
+//! The engine works on precomposed characters; text is converted to the
+//! host's preferred form only when it is emitted:
+//! - NFC: precomposed code points only (ấ = U+1EA5)
+//! - NFD: base letter + combining marks (ấ = a + U+0302 + U+0301)
+//! - Legacy charsets (TCVN3, VNI-Windows, VISCII, VPS): each byte is emitted as
+//!   the character with the same code point, which is what 8-bit fonts
+//!   render (VNI ấ = "aá")
+//!
+//! Backspace counts are code points, so they depend on the form too.
+
+use crate::chars::{self, ToneMark, VowelMod};
+use crate::charset::Charset;
+use serde::{Deserialize, Serialize};
+
+/// Form of emitted text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputEncoding {
+    /// Precomposed characters (default)
+    #[default]
+    Nfc,
+    /// Decomposed combining sequences
+    Nfd,
+    /// TCVN3 (ABC) font bytes
+    Tcvn3,
+    /// VNI-Windows font bytes
+    VniWindows,
+    /// VISCII bytes
+    Viscii,
+    /// VPS bytes
+    Vps,
+}
+
+impl OutputEncoding {
+    /// Legacy charset behind this form, if any
+    pub fn charset(self) -> Option<Charset> {
+        match self {
+            Self::Nfc | Self::Nfd => None,
+            Self::Tcvn3 => Some(Charset::Tcvn3),
+            Self::VniWindows => Some(Charset::VniWindows),
+            Self::Viscii => Some(Charset::Viscii),
+            Self::Vps => Some(Charset::Vps),
+        }
+    }
+
+    /// Convert precomposed text to this form
+    pub fn encode(self, text: &str) -> String {
+        match (self, self.charset()) {
+            (_, Some(charset)) => charset.encode(text).into_iter().map(char::from).collect(),
+            (Self::Nfd, None) => {
+                let mut out = String::with_capacity(text.len() * 2);
+                for c in text.chars() {
+                    decompose(c, &mut out);
+                }
+                out
+            }
+            _ => text.to_string(),
+        }
+    }
+
+    /// Number of code points `text` takes in this form
+    pub fn encoded_len(self, text: &str) -> usize {
+        match (self, self.charset()) {
+            (_, Some(charset)) => text.chars().map(|c| charset.char_len(c)).sum(),
+            (Self::Nfd, None) => text.chars().map(decomposed_len).sum(),
+            _ => text.chars().count(),
+        }
+    }
+}
+
+/// Combining mark for a vowel modifier
+fn modifier_mark(modifier: VowelMod) -> Option<char> {
+    match modifier {
+        VowelMod::None => None,
+        VowelMod::Circumflex => Some('\u{0302}'),
+        VowelMod::Breve => Some('\u{0306}'),
+        VowelMod::Horn => Some('\u{031B}'),
+    }
+}
+
+/// Combining mark for a tone
+fn tone_mark(tone: ToneMark) -> Option<char> {
+    match tone {
+        ToneMark::None => None,
+        ToneMark::Acute => Some('\u{0301}'),
+        ToneMark::Grave => Some('\u{0300}'),
+        ToneMark::Hook => Some('\u{0309}'),
+        ToneMark::Tilde => Some('\u{0303}'),
+        ToneMark::Dot => Some('\u{0323}'),
+    }
+}
+
+/// Canonical combining class of the marks above
+fn combining_class(mark: char) -> u8 {
+    match mark {
+        '\u{031B}' => 216, // horn
+        '\u{0323}' => 220, // dot below
+        _ => 230,          // everything written above the letter
+    }
+}
+
+/// Append the canonical decomposition of a Vietnamese letter
+///
+/// Marks are in canonical order, so ự is u + horn + dot below and ậ is
+/// a + dot below + circumflex. Other characters (including đ, which has
+/// no decomposition) are copied unchanged.
+fn decompose(c: char, out: &mut String) {
+    let Some(&(base, modifier, tone)) = chars::REVERSE_MAP.get(&chars::to_lower(c)) else {
+        out.push(c);
+        return;
+    };
+
+    out.push(if c.is_uppercase() {
+        chars::to_upper(base)
+    } else {
+        base
+    });
+
+    let mut marks: Vec<char> = [modifier_mark(modifier), tone_mark(tone)]
+        .into_iter()
+        .flatten()
+        .collect();
+    // Stable sort keeps modifier-before-tone among marks of equal class
+    marks.sort_by_key(|&m| combining_class(m));
+    out.extend(marks);
+}
+
+/// Code points in the decomposition of a character
+fn decomposed_len(c: char) -> usize {
+    chars::REVERSE_MAP
+        .get(&chars::to_lower(c))
+        .map_or(1, |&(_, modifier, tone)| {
+            1 + usize::from(modifier != VowelMod::None) + usize::from(tone != ToneMark::None)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nfd_canonical_order() {
+        let nfd = OutputEncoding::Nfd;
+        assert_eq!(nfd.encode("ấ"), "a\u{0302}\u{0301}");
+        assert_eq!(nfd.encode("ậ"), "a\u{0323}\u{0302}");
+        assert_eq!(nfd.encode("ờ"), "o\u{031B}\u{0300}");
+        assert_eq!(nfd.encode("Ự"), "U\u{031B}\u{0323}");
+        assert_eq!(nfd.encode("Đỗ"), "Đo\u{0302}\u{0303}");
+        assert_eq!(nfd.encode("hello"), "hello");
+    }
+
+    #[test]
+    fn test_legacy_output() {
+        let vni = OutputEncoding::VniWindows;
+        assert_eq!(vni.encode("Việt"), "Vie\u{E4}t");
+        assert_eq!(vni.encoded_len("Việt"), 5);
+        assert_eq!(OutputEncoding::Viscii.encoded_len("Việt"), 4);
+        assert_eq!(OutputEncoding::Vps.encoded_len("Việt"), 4);
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        assert_eq!(OutputEncoding::Nfc.encoded_len("Việt"), 4);
+        assert_eq!(OutputEncoding::Nfd.encoded_len("Việt"), 6);
+        for word in ["người", "Đặng", "quyển", "ưa"] {
+            let nfd = OutputEncoding::Nfd;
+            assert_eq!(nfd.encoded_len(word), nfd.encode(word).chars().count());
+        }
+    }
+}
@@ -2,20 +2,32 @@
 //!
 //! Main engine with advanced features:
 //! - Validation-First approach
-//! - Foreign Word Detection
+//! - Foreign Word Detection (lexicons matched on raw keys)
 //! - Auto-restore on word boundary
 //! - UO Compound handling
+//! - Order-independent modifiers (syllable model)
 //! - Tone repositioning
 //! - Double mark undo
+//! - Multi-level undo history
 //! - Shortcut expansion
+//! - State snapshots for switching between text fields
+//! - Preedit (composition) events as an alternative to backspace edits
 
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, BufferChar};
 use crate::chars::{self, ToneMark, VowelMod};
+use crate::config::EngineConfig;
+use crate::dictionary;
+use crate::encoding::OutputEncoding;
+use crate::foreign::{Confidence, ForeignWordDetector, Lexicon};
+use crate::methods::custom::{CustomMethod, DefinitionError};
 use crate::methods::{self, InputMethod, KeyAction};
+use crate::preedit::{self, PreeditEvent};
 use crate::shortcut::ShortcutTable;
+use crate::syllable;
 use crate::transform;
 use crate::validation::{self, ValidationResult};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// Engine action result
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -42,6 +54,9 @@ pub struct ProcessResult {
     pub backspace: usize,
     /// Whether this was an auto-restore
     pub restored: bool,
+    /// Committed word is not in the dictionary (with `spell_check` on)
+    #[serde(default)]
+    pub misspelled: bool,
 }
 
 impl ProcessResult {
@@ -51,6 +66,7 @@ impl ProcessResult {
             output: String::new(),
             backspace: 0,
             restored: false,
+            misspelled: false,
         }
     }
 
@@ -60,6 +76,7 @@ impl ProcessResult {
             output: text,
             backspace: 0,
             restored: false,
+            misspelled: false,
         }
     }
 
@@ -69,6 +86,7 @@ impl ProcessResult {
             output: text,
             backspace,
             restored: false,
+            misspelled: false,
         }
     }
 
@@ -78,55 +96,171 @@ impl ProcessResult {
             output: raw_text,
             backspace,
             restored: true,
+            misspelled: false,
         }
     }
 }
 
-/// Last transform info for undo
-#[derive(Debug, Clone, Default)]
-struct LastTransform {
-    /// Position of last transform
-    position: Option<usize>,
+/// Engine feature switches
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineOptions {
+    /// Auto-capitalize first letter of sentences
+    pub auto_capitalize: bool,
+    /// Smart quotes (replace ' and " with curly variants)
+    pub smart_quotes: bool,
+    /// Spell check enabled
+    pub spell_check: bool,
+    /// Tone placement style: modern (hoà) or traditional (hòa)
+    pub modern_tone: bool,
+    /// Quick Telex double consonants (cc → ch, nn → nh)
+    pub quick_telex: bool,
+    /// Direct character insertions ([ → ư, ] → ơ, standalone w → ư)
+    pub bracket_shortcuts: bool,
+    /// Diacritic removal key (z in Telex, 0 in VNI)
+    pub diacritic_removal: bool,
+    /// Unicode form of emitted text (NFC or NFD)
+    pub output_encoding: OutputEncoding,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            auto_capitalize: true,
+            smart_quotes: false,
+            spell_check: true,
+            modern_tone: true,
+            quick_telex: true,
+            bracket_shortcuts: true,
+            diacritic_removal: true,
+            output_encoding: OutputEncoding::Nfc,
+        }
+    }
+}
+
+impl EngineOptions {
+    /// Change the options named in a JSON object, keeping the others
+    /// (`{ "smart_quotes": true }` turns on smart quotes and nothing else)
+    pub fn merge_json(self, json: &str) -> Result<Self, serde_json::Error> {
+        let changes: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)?;
+        let mut options = serde_json::to_value(self)?;
+        if let serde_json::Value::Object(fields) = &mut options {
+            fields.extend(changes);
+        }
+        serde_json::from_value(options)
+    }
+}
+
+/// One transformation of the current word, kept for undo
+///
+/// Records form a per-word stack. Undoing a record puts back the
+/// characters it replaced and types the key it consumed, so popping every
+/// record leaves the raw keystrokes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TransformRecord {
     /// Type of transform
-    #[allow(dead_code)]
     transform_type: TransformType,
-    /// Original character before transform
-    original: Option<char>,
+    /// First buffer index the transform replaced
+    start: usize,
+    /// Characters at `start` before the transform
+    before: Vec<BufferChar>,
+    /// Number of characters the transform left at `start`
+    after_len: usize,
+    /// Buffer length right after the transform (later keys start here)
+    end: usize,
+    /// Key consumed by the transform
+    key: Option<char>,
+    /// Word tone before the transform
+    previous_tone: ToneMark,
+    /// Shortcut prefix consumed by an expansion
+    prefix: Option<char>,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum TransformType {
-    #[default]
-    None,
-    Tone(ToneMark),
-    Modifier(VowelMod),
+    Tone,
+    Modifier,
     Stroke,
+    QuickTelex,
+    InsertChar,
+    /// Horn carried over to "o" after "ư" (and the key that became redundant)
+    UoCompletion,
+    Shortcut,
+    RemoveDiacritics,
+    /// Tone, modifier or stroke taken back by pressing its key again; kept
+    /// so the key it swallowed still counts as typed (boss, not bos)
+    Undone,
+}
+
+/// State of the word being typed, saved when focus leaves a text field
+///
+/// Restoring it lets a half-typed word keep its raw keys, undo history,
+/// foreign-word flag and capitalization state. Method and options are
+/// not part of it; they belong to `EngineConfig`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    buffer: Vec<BufferChar>,
+    history: Vec<TransformRecord>,
+    tone: ToneMark,
+    possible_foreign: bool,
+    shortcut_prefix: Option<char>,
+    next_char_upper: bool,
+    last_committed_char: Option<char>,
+}
+
+impl EngineSnapshot {
+    /// Parse a snapshot from JSON
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Serialize the snapshot to JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Text of the word being typed when the snapshot was taken
+    pub fn get_buffer(&self) -> String {
+        self.buffer.iter().map(|bc| bc.ch).collect()
+    }
 }
 
 /// Main IME Engine with advanced features
+///
+/// Cloning is cheap: the method, shortcut table and lexicons are shared
+/// with the clone until one of them changes its own.
+#[derive(Clone)]
 pub struct Engine {
     /// Input buffer
     buffer: Buffer,
     /// Current input method
-    method: Box<dyn InputMethod>,
+    method: Arc<dyn InputMethod>,
     /// Method name
     method_name: String,
+    /// Custom methods loaded from definitions
+    custom_methods: Arc<Vec<CustomMethod>>,
     /// Engine enabled state
     enabled: bool,
     /// Shortcut table
-    shortcuts: ShortcutTable,
-    /// Last transform for undo
-    last_transform: LastTransform,
-    /// Track if current word might be foreign
+    shortcuts: Arc<ShortcutTable>,
+    /// Transforms applied to the current word, most recent last
+    history: Vec<TransformRecord>,
+    /// Key that undoes the last transform
+    undo_key: Option<char>,
+    /// Tone of the current word, placed by the tone rules on every change
+    tone: ToneMark,
+    /// Whether the keys typed so far start a foreign word
     possible_foreign: bool,
+    /// Detectors asked whether a word is foreign (bundled English first)
+    detectors: Vec<Arc<dyn ForeignWordDetector>>,
+    /// User lexicons as loaded, kept for `config`
+    lexicons: Arc<Vec<String>>,
+    /// Words of every user lexicon
+    user_lexicon: Option<Arc<Lexicon>>,
     /// Special character prefix for shortcuts (e.g., #vn)
     shortcut_prefix: Option<char>,
-    /// Auto-capitalize first letter of sentences
-    auto_capitalize: bool,
-    /// Smart quotes (replace ' and " with curly variants)
-    smart_quotes: bool,
-    /// Spell check enabled
-    spell_check: bool,
+    /// Feature switches
+    options: EngineOptions,
     /// Flag to capitalize next character
     next_char_upper: bool,
     /// Last committed character (for context)
@@ -137,39 +271,176 @@ impl Engine {
     pub fn new() -> Self {
         Self {
             buffer: Buffer::new(),
-            method: methods::get_method("telex"),
+            method: methods::get_method("telex").into(),
             method_name: "telex".to_string(),
+            custom_methods: Arc::default(),
             enabled: true,
-            shortcuts: ShortcutTable::with_defaults(),
-            last_transform: LastTransform::default(),
+            shortcuts: Arc::new(ShortcutTable::with_defaults()),
+            history: Vec::new(),
+            undo_key: None,
+            tone: ToneMark::None,
             possible_foreign: false,
+            detectors: vec![Arc::new(Lexicon::english())],
+            lexicons: Arc::default(),
+            user_lexicon: None,
             shortcut_prefix: None,
-            auto_capitalize: true,
-            smart_quotes: false,
-            spell_check: true,
+            options: EngineOptions::default(),
             next_char_upper: true, // Start with capital
             last_committed_char: None,
         }
     }
 
-    /// Set input method by name
+    /// Create an engine from a saved configuration
+    ///
+    /// Fails if one of the custom method definitions is invalid.
+    pub fn from_config(config: &EngineConfig) -> Result<Self, DefinitionError> {
+        let mut engine = Self::new();
+        engine.custom_methods = Arc::new(
+            config
+                .custom_methods
+                .iter()
+                .cloned()
+                .map(CustomMethod::new)
+                .collect::<Result<_, _>>()?,
+        );
+        engine.options = config.options;
+        engine.shortcuts = Arc::new(config.shortcuts.clone());
+        engine.undo_key = config.undo_key;
+        engine.set_method(&config.method);
+        engine.enabled = config.enabled;
+        for lexicon in &config.lexicons {
+            engine.load_lexicon(lexicon);
+        }
+        Ok(engine)
+    }
+
+    /// Current configuration, suitable for saving
+    pub fn config(&self) -> EngineConfig {
+        EngineConfig {
+            method: self.method_name.clone(),
+            options: self.options,
+            enabled: self.enabled,
+            undo_key: self.undo_key,
+            shortcuts: (*self.shortcuts).clone(),
+            custom_methods: self
+                .custom_methods
+                .iter()
+                .map(|m| m.definition().clone())
+                .collect(),
+            lexicons: (*self.lexicons).clone(),
+        }
+    }
+
+    /// Save the state of the current word
+    pub fn snapshot(&self) -> EngineSnapshot {
+        EngineSnapshot {
+            buffer: self.buffer.as_slice().to_vec(),
+            history: self.history.clone(),
+            tone: self.tone,
+            possible_foreign: self.possible_foreign,
+            shortcut_prefix: self.shortcut_prefix,
+            next_char_upper: self.next_char_upper,
+            last_committed_char: self.last_committed_char,
+        }
+    }
+
+    /// Continue a word saved by `snapshot`
+    ///
+    /// The host must still show the word as it was when saved.
+    pub fn restore_snapshot(&mut self, snapshot: &EngineSnapshot) {
+        self.buffer.clear();
+        self.buffer.splice(0, 0, &snapshot.buffer);
+        self.history = snapshot.history.clone();
+        self.tone = snapshot.tone;
+        self.possible_foreign = snapshot.possible_foreign;
+        self.shortcut_prefix = snapshot.shortcut_prefix;
+        self.next_char_upper = snapshot.next_char_upper;
+        self.last_committed_char = snapshot.last_committed_char;
+    }
+
+    /// Set input method by name (built-in or loaded custom method)
     pub fn set_method(&mut self, name: &str) {
-        self.method = methods::get_method(name);
         self.method_name = name.to_lowercase();
+        self.build_method();
         self.buffer.clear();
         self.reset_state();
     }
 
+    /// Build the method named `method_name` with the current undo key
+    fn build_method(&mut self) {
+        let name = &self.method_name;
+        let mut method = match self.custom_methods.iter().find(|m| m.name() == name) {
+            Some(custom) => Box::new(custom.clone()),
+            None => methods::get_method(name),
+        };
+        method.set_undo_key(self.undo_key);
+        self.method = method.into();
+    }
+
+    /// Load a custom input method from a JSON definition and switch to it
+    ///
+    /// A method with the same name replaces the earlier one. Definitions
+    /// with conflicting rules are rejected.
+    pub fn load_custom_method(&mut self, json: &str) -> Result<(), DefinitionError> {
+        let method = CustomMethod::from_json(json)?;
+        let name = method.name().to_string();
+        let custom_methods = Arc::make_mut(&mut self.custom_methods);
+        custom_methods.retain(|m| m.name() != name);
+        custom_methods.push(method);
+        self.set_method(&name);
+        Ok(())
+    }
+
     /// Set engine options
-    pub fn set_options(&mut self, auto_capitalize: bool, smart_quotes: bool, spell_check: bool) {
-        self.auto_capitalize = auto_capitalize;
-        self.smart_quotes = smart_quotes;
-        self.spell_check = spell_check;
+    ///
+    /// Changing the tone style re-places the tone of the word in the buffer;
+    /// the returned result carries the edit needed to show it (passthrough
+    /// when nothing changed).
+    pub fn set_options(&mut self, options: EngineOptions) -> ProcessResult {
+        self.settle_expansion();
+        let before = self.displayed_word();
+        let shown_as = self.options.output_encoding;
+        let tone_style_changed = self.options.modern_tone != options.modern_tone;
+        let encoding_changed = shown_as != options.output_encoding;
+        self.options = options;
+
+        let tone_moved = tone_style_changed && self.place_tone();
+        if tone_moved || (encoding_changed && !self.buffer.is_empty()) {
+            let text = self.buffer.get_text();
+            let result = ProcessResult::update(text, self.buffer.len());
+            self.encode_result(result, &before, shown_as)
+        } else {
+            ProcessResult::passthrough()
+        }
     }
 
     /// Get engine options
-    pub fn get_options(&self) -> (bool, bool, bool) {
-        (self.auto_capitalize, self.smart_quotes, self.spell_check)
+    pub fn get_options(&self) -> EngineOptions {
+        self.options
+    }
+
+    /// Set the key that undoes the last transform (None disables it)
+    ///
+    /// Pressing it repeatedly walks back through every tone, modifier,
+    /// stroke, Quick Telex and shortcut applied to the current word.
+    pub fn set_undo_key(&mut self, key: Option<char>) {
+        self.undo_key = key;
+        self.build_method();
+    }
+
+    /// Get the undo key
+    pub fn get_undo_key(&self) -> Option<char> {
+        self.undo_key
+    }
+
+    /// Names of every method `set_method` accepts: built-ins first, then
+    /// loaded custom methods
+    pub fn available_methods(&self) -> Vec<String> {
+        methods::BUILTIN_METHODS
+            .iter()
+            .map(|name| name.to_string())
+            .chain(self.custom_methods.iter().map(|m| m.name().to_string()))
+            .collect()
     }
 
     /// Get current method name
@@ -177,25 +448,53 @@ impl Engine {
         &self.method_name
     }
 
+    /// Add a user lexicon: foreign words, one per line (see `Lexicon::parse`)
+    ///
+    /// Typed keys spelling one of its words are restored at the word
+    /// boundary, like English words.
+    pub fn load_lexicon(&mut self, text: &str) {
+        Arc::make_mut(&mut self.lexicons).push(text.to_string());
+        self.user_lexicon = Some(Arc::new(Lexicon::parse(&self.lexicons.join("\n"))));
+    }
+
+    /// Remove every user lexicon
+    pub fn clear_lexicons(&mut self) {
+        self.lexicons = Arc::default();
+        self.user_lexicon = None;
+    }
+
+    /// Ask another detector whether words are foreign
+    ///
+    /// Detectors are not part of `EngineConfig`; add them again to an
+    /// engine created from a saved configuration.
+    pub fn add_foreign_detector(&mut self, detector: Box<dyn ForeignWordDetector>) {
+        self.detectors.push(detector.into());
+    }
+
+    /// Replace every detector, including the bundled English lexicon
+    pub fn set_foreign_detectors(&mut self, detectors: Vec<Box<dyn ForeignWordDetector>>) {
+        self.detectors = detectors.into_iter().map(Arc::from).collect();
+    }
+
     /// Set custom shortcuts
     pub fn set_shortcuts(&mut self, shortcuts: ShortcutTable) {
-        self.shortcuts = shortcuts;
+        self.shortcuts = Arc::new(shortcuts);
     }
 
     /// Add a shortcut
     pub fn add_shortcut(&mut self, trigger: &str, replacement: &str) {
         use crate::shortcut::Shortcut;
-        self.shortcuts.add(Shortcut::new(trigger, replacement));
+        Arc::make_mut(&mut self.shortcuts).add(Shortcut::new(trigger, replacement));
     }
 
     /// Remove a shortcut
     pub fn remove_shortcut(&mut self, trigger: &str) {
-        self.shortcuts.remove(trigger);
+        Arc::make_mut(&mut self.shortcuts).remove(trigger);
     }
 
     /// Toggle a shortcut
     pub fn toggle_shortcut(&mut self, trigger: &str) {
-        self.shortcuts.toggle(trigger);
+        Arc::make_mut(&mut self.shortcuts).toggle(trigger);
     }
 
     /// Get all shortcuts
@@ -204,13 +503,37 @@ impl Engine {
     }
 
     /// Process a key press
-    pub fn process_key(&mut self, key: char, _shift: bool) -> ProcessResult {
+    ///
+    /// Output and backspace count are in the configured output encoding.
+    pub fn process_key(&mut self, key: char, shift: bool) -> ProcessResult {
+        let before = self.displayed_word();
+        let result = self.handle_key(key, shift);
+        self.possible_foreign = self.is_foreign_prefix();
+        self.encode_result(result, &before, self.options.output_encoding)
+    }
+
+    /// Process a key press, producing precomposed text
+    fn handle_key(&mut self, key: char, _shift: bool) -> ProcessResult {
         if !self.enabled {
             return ProcessResult::passthrough();
         }
 
+        // Only the undo key still sees an immediate shortcut's expansion
+        if self.method_action(key, self.buffer.last().map(|bc| bc.ch)) != KeyAction::Undo {
+            self.settle_expansion();
+        }
+
+        // Punctuation the method uses as a mark inside a word (VIQR a' → á)
+        // is not a word boundary
+        let claimed = !self.buffer.is_empty()
+            && validation::is_word_boundary(key)
+            && !matches!(
+                self.method_action(key, self.buffer.last().map(|bc| bc.ch)),
+                KeyAction::None | KeyAction::Undo
+            );
+
         // Smart Quotes
-        if self.smart_quotes && (key == '"' || key == '\'') {
+        if self.options.smart_quotes && !claimed && (key == '"' || key == '\'') {
             let is_open = self.buffer.is_empty()
                 && (self.last_committed_char.is_none()
                     || self.last_committed_char.unwrap().is_whitespace());
@@ -232,13 +555,21 @@ impl Engine {
 
         // Auto-capitalize
         let mut key_to_process = key;
-        if self.auto_capitalize && self.next_char_upper && key.is_alphabetic() {
+        if self.options.auto_capitalize && self.next_char_upper && key.is_alphabetic() {
             key_to_process = key.to_uppercase().next().unwrap();
             self.next_char_upper = false;
         }
 
+        // Undo key wins over word boundaries while there is something to undo
+        if !self.history.is_empty()
+            && self.method_action(key_to_process, self.buffer.last().map(|bc| bc.ch))
+                == KeyAction::Undo
+        {
+            return self.undo_last_transform(key_to_process);
+        }
+
         // Check for word boundary - triggers auto-restore check
-        if validation::is_word_boundary(key_to_process) {
+        if !claimed && validation::is_word_boundary(key_to_process) {
             // Special case: Allow specific symbols as shortcut prefix if buffer is empty
             if self.buffer.is_empty() && self.is_valid_prefix(key_to_process) {
                 self.shortcut_prefix = Some(key_to_process);
@@ -250,59 +581,263 @@ impl Engine {
         // Get previous character for context
         let prev_char = self.buffer.last().map(|bc| bc.ch);
 
-        // Check for foreign word pattern BEFORE processing
-        let current_text = self.buffer.get_text();
-        if validation::is_foreign_word_pattern(&current_text, Some(key_to_process)) {
-            self.possible_foreign = true;
-        }
-
         // Process through input method
-        let action = self.method.process(key_to_process, prev_char);
+        let action = self.method_action(key_to_process, prev_char);
+
+        // Same key again after a direct insert types the key itself (ww → w)
+        let repeats_insert = self.history.last().is_some_and(|r| {
+            r.transform_type == TransformType::InsertChar
+                && r.end == self.buffer.len()
+                && r.key
+                    .is_some_and(|k| chars::to_lower(k) == chars::to_lower(key_to_process))
+        });
+        if action != KeyAction::None && repeats_insert {
+            self.history.pop();
+            self.buffer.pop();
+            // The host still shows the inserted character
+            let mut result = self.handle_regular_char(key_to_process);
+            result.backspace += 1;
+            return result;
+        }
 
         match action {
             KeyAction::None => self.handle_regular_char(key_to_process),
 
-            KeyAction::Tone(tone) => {
-                // Skip if foreign word
-                if self.possible_foreign {
-                    return self.handle_regular_char(key_to_process);
-                }
-                self.apply_tone(tone, key_to_process)
+            KeyAction::Tone(tone) => self.apply_tone(tone, key_to_process),
+
+            KeyAction::Modifier(modifier) => self.apply_modifier(modifier, key_to_process),
+
+            KeyAction::Stroke => self.apply_stroke(key_to_process),
+
+            KeyAction::RemoveDiacritics => self.remove_all_diacritics(key_to_process),
+
+            KeyAction::Undo => self.undo_last_transform(key_to_process),
+
+            KeyAction::QuickTelex(replacement) => {
+                self.apply_quick_telex(&replacement, key_to_process)
             }
 
-            KeyAction::Modifier(modifier) => {
-                if self.possible_foreign {
-                    return self.handle_regular_char(key_to_process);
+            KeyAction::InsertChar(ch) => self.insert_char_directly(ch, key_to_process),
+
+            KeyAction::Escape => self.handle_regular_char(key_to_process),
+
+            KeyAction::Escaped => self.type_escaped(key_to_process),
+        }
+    }
+
+    /// Ask the input method what a key does, dropping actions whose
+    /// feature is switched off
+    fn method_action(&self, key: char, prev_char: Option<char>) -> KeyAction {
+        match self.method.process(key, prev_char) {
+            KeyAction::QuickTelex(_) if !self.options.quick_telex => KeyAction::None,
+            KeyAction::InsertChar(_) if !self.options.bracket_shortcuts => KeyAction::None,
+            KeyAction::RemoveDiacritics if !self.options.diacritic_removal => KeyAction::None,
+            action => action,
+        }
+    }
+
+    /// Process a Backspace key press
+    ///
+    /// Pops the last character of the word and rolls back the tone it
+    /// carried. Passthrough lets the host delete the character itself;
+    /// Update means the rest of the word changed (tone moved) and must be
+    /// retyped.
+    pub fn process_backspace(&mut self) -> ProcessResult {
+        let before = self.displayed_word();
+        let encoding = self.options.output_encoding;
+        let mut result = self.handle_backspace();
+        self.possible_foreign = self.is_foreign_prefix();
+
+        // A host Backspace removes one code point, which would leave the
+        // base letter of a decomposed character behind
+        if result.action == Action::Passthrough {
+            if let Some(last) = before.chars().last() {
+                if encoding.encoded_len(&last.to_string()) > 1 {
+                    result = ProcessResult::update(String::new(), 1);
                 }
-                self.apply_modifier(modifier, key_to_process)
             }
+        }
+        self.encode_result(result, &before, encoding)
+    }
 
-            KeyAction::Stroke => self.apply_stroke(),
+    /// Process a Backspace key press on precomposed text
+    fn handle_backspace(&mut self) -> ProcessResult {
+        if !self.enabled {
+            return ProcessResult::passthrough();
+        }
+        if self.settle_expansion() {
+            // The host deletes the last character of the expansion
+            return ProcessResult::passthrough();
+        }
 
-            KeyAction::RemoveDiacritics => self.remove_all_diacritics(),
+        let Some(removed) = self.buffer.pop() else {
+            // Nothing typed yet: the shortcut prefix is what gets deleted
+            self.shortcut_prefix = None;
+            return ProcessResult::passthrough();
+        };
+        let old_len = self.buffer.len() + 1;
 
-            KeyAction::Undo => self.undo_last_transform(),
+        if self.buffer.is_empty() {
+            self.reset_state();
+            return ProcessResult::passthrough();
+        }
 
-            KeyAction::QuickTelex(replacement) => {
-                self.apply_quick_telex(replacement, key_to_process)
-            }
+        if transform::has_tone(removed.ch) {
+            self.tone = ToneMark::None;
+        }
+        self.drop_history_after(self.buffer.len());
 
-            KeyAction::InsertChar(ch) => self.insert_char_directly(ch),
+        if self.place_tone() {
+            let text = self.buffer.get_text();
+            ProcessResult::update(text, old_len)
+        } else {
+            ProcessResult::passthrough()
         }
     }
 
+    /// Process a key press for a host that shows the word as a preedit
+    /// string; an empty list means the host handles the key itself
+    pub fn process_key_preedit(&mut self, key: char, shift: bool) -> Vec<PreeditEvent> {
+        let before = self.composition();
+        let result = self.process_key(key, shift);
+        preedit::events(&before, &result, Some(key), &self.composition())
+    }
+
+    /// Process a Backspace key press for a preedit host
+    pub fn process_backspace_preedit(&mut self) -> Vec<PreeditEvent> {
+        let before = self.composition();
+        let result = self.process_backspace();
+        preedit::events(&before, &result, None, &self.composition())
+    }
+
+    /// Commit the word being composed as it is shown (focus lost, or the
+    /// caret moved)
+    pub fn commit_preedit(&mut self) -> Vec<PreeditEvent> {
+        let text = self.composition();
+        self.clear();
+        if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![PreeditEvent::Commit { text }]
+        }
+    }
+
+    /// Drop the word being composed (e.g. on Escape)
+    pub fn cancel_preedit(&mut self) -> Vec<PreeditEvent> {
+        let text = self.composition();
+        self.clear();
+        if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![PreeditEvent::Cancel]
+        }
+    }
+
+    /// Current word in the output encoding, as a preedit host shows it
+    fn composition(&self) -> String {
+        self.options.output_encoding.encode(&self.displayed_word())
+    }
+
+    /// Text of the current word as the host shows it (precomposed),
+    /// including a shortcut prefix typed before it
+    fn displayed_word(&self) -> String {
+        let mut text: String = self.shortcut_prefix.into_iter().collect();
+        text.push_str(&self.buffer.get_text());
+        text
+    }
+
+    /// Convert a precomposed result to the output encoding
+    ///
+    /// The backspace count covers the tail of `before`, the word as it was
+    /// shown (in `shown_as`) before the change; anything beyond it is
+    /// counted one code point per character. Code points that would be
+    /// deleted and typed again unchanged are left alone, so only the
+    /// changed suffix is retyped (việ + t: no backspace, output "t").
+    fn encode_result(
+        &self,
+        mut result: ProcessResult,
+        before: &str,
+        shown_as: OutputEncoding,
+    ) -> ProcessResult {
+        let chars: Vec<char> = before.chars().collect();
+        let covered = result.backspace.min(chars.len());
+        let deleted: String = chars[chars.len() - covered..].iter().collect();
+        let beyond_word = result.backspace - covered;
+        let deleted = shown_as.encode(&deleted);
+        let output = self.options.output_encoding.encode(&result.output);
+
+        let unchanged = if beyond_word == 0 {
+            deleted
+                .chars()
+                .zip(output.chars())
+                .take_while(|(old, new)| old == new)
+                .count()
+        } else {
+            0
+        };
+        result.backspace = deleted.chars().count() - unchanged + beyond_word;
+        result.output = output.chars().skip(unchanged).collect();
+        result
+    }
+
     /// Insert a character directly into the buffer (for quick shortcuts like [ → ư)
-    fn insert_char_directly(&mut self, ch: char) -> ProcessResult {
+    fn insert_char_directly(&mut self, ch: char, raw_key: char) -> ProcessResult {
+        let start = self.buffer.len();
         self.buffer.push_simple(ch);
-        self.last_transform = LastTransform::default();
+        self.place_tone();
+        self.history.push(TransformRecord {
+            transform_type: TransformType::InsertChar,
+            start,
+            before: Vec::new(),
+            after_len: 1,
+            end: self.buffer.len(),
+            key: Some(raw_key),
+            previous_tone: self.tone,
+            prefix: None,
+        });
         let text = self.buffer.get_text();
-        ProcessResult::update(text, self.buffer.len())
+        ProcessResult::update(text, start)
+    }
+
+    /// Replace the escape character before the caret with `key`, typed as
+    /// if it were a letter so the word keeps it at the boundary (ta\. → ta.)
+    fn type_escaped(&mut self, key: char) -> ProcessResult {
+        let old_len = self.buffer.len();
+        self.buffer.pop();
+        self.buffer.push_simple(key);
+        let text = self.buffer.get_text();
+        ProcessResult::update(text, old_len)
+    }
+
+    /// Handle a key whose transform did not apply: punctuation claimed as a
+    /// mark falls back to being a word boundary
+    fn handle_plain_key(&mut self, key: char) -> ProcessResult {
+        if validation::is_word_boundary(key) {
+            self.handle_word_boundary(key)
+        } else {
+            self.handle_regular_char(key)
+        }
     }
 
     /// Handle regular character input
     fn handle_regular_char(&mut self, key: char) -> ProcessResult {
         self.buffer.push_simple(key);
-        self.last_transform = LastTransform::default();
+        let chars: Vec<char> = self.buffer.iter().map(|bc| bc.ch).collect();
+        if let Some((pos, ch)) = syllable::complete_uo_horn(&chars) {
+            let before = vec![self.buffer.as_slice()[pos]];
+            self.buffer.replace(pos, ch);
+            self.history.push(TransformRecord {
+                transform_type: TransformType::UoCompletion,
+                start: pos,
+                before,
+                after_len: 1,
+                end: self.buffer.len(),
+                key: None,
+                previous_tone: self.tone,
+                prefix: None,
+            });
+        }
+        self.place_tone();
 
         // Check for shortcut match
         let current_text = self.buffer.get_text();
@@ -317,21 +852,38 @@ impl Engine {
             // Note: m.backspace_count from try_match is the length of the trigger
             // which matches full_text length.
             // But we only need to backspace what's in the buffer + the prefix char that was passed through
+            let shown = self.buffer.len() - 1;
             let backspace = if self.shortcut_prefix.is_some() {
-                self.buffer.len() + 1
+                shown + 1
             } else {
-                self.buffer.len()
+                shown
             };
 
+            let before = self.buffer.as_slice().to_vec();
+            let previous_tone = self.tone;
+            let prefix = self.shortcut_prefix;
+
             // Remove trigger chars from buffer
             self.buffer.clear();
             self.shortcut_prefix = None; // Reset prefix
+            self.tone = ToneMark::None;
 
             // Add replacement chars
             for ch in replacement.chars() {
                 self.buffer.push_simple(ch);
             }
 
+            self.history.push(TransformRecord {
+                transform_type: TransformType::Shortcut,
+                start: 0,
+                before,
+                after_len: self.buffer.len(),
+                end: self.buffer.len(),
+                key: None,
+                previous_tone,
+                prefix,
+            });
+
             let text = self.buffer.get_text();
             return ProcessResult::update(text, backspace);
         }
@@ -340,6 +892,25 @@ impl Engine {
         ProcessResult::update(text, self.buffer.len() - 1)
     }
 
+    /// End the word at the expansion of an immediate shortcut, leaving it
+    /// on screen as typed
+    ///
+    /// The replacement is finished text: keys after it start a new word
+    /// instead of re-placing tones across it (Việt Nam + b, not Viêt Namb).
+    /// Returns true if there was an expansion to end.
+    fn settle_expansion(&mut self) -> bool {
+        let expanded = self
+            .history
+            .last()
+            .is_some_and(|r| r.transform_type == TransformType::Shortcut);
+        if expanded {
+            self.last_committed_char = self.buffer.last().map(|bc| bc.ch);
+            self.buffer.clear();
+            self.reset_state();
+        }
+        expanded
+    }
+
     /// Check if char is a valid shortcut prefix
     fn is_valid_prefix(&self, key: char) -> bool {
         matches!(
@@ -390,13 +961,33 @@ impl Engine {
         }
 
         let transformed = self.buffer.get_text();
-        let raw = self.buffer.get_raw();
+        let keys = self.typed_keys(true);
+        // Keys swallowed by a double-mark undo were typed on purpose, so a
+        // word left as its literal keys (aaa → aa) is not restored; any
+        // restore types back every key, as `get_raw_buffer` reports them
+        let literal = transformed == self.typed_keys(false);
+
+        // Keys spelling a foreign word are typed back, unless they also
+        // type a Vietnamese word and the detector is not certain
+        if self.possible_foreign && transformed != keys {
+            let restore = match self.foreign_confidence(&keys) {
+                Some(Confidence::Certain) => true,
+                Some(Confidence::Likely) => !dictionary::contains(&transformed),
+                None => false,
+            };
+            if restore {
+                return self.restore_keys(&keys, &transformed, boundary_char);
+            }
+        }
 
         // Validate the transformed text
         let validation = validation::validate(&transformed);
+        let known = self.options.spell_check && dictionary::contains(&transformed);
 
-        // If invalid Vietnamese AND looks foreign, restore to raw ASCII
-        if !validation.is_valid() {
+        // A dictionary word is never restored; otherwise restore to raw
+        // ASCII if it looks foreign. Words the rules accept are kept even
+        // when the dictionary does not know them, and reported below.
+        if !known {
             let should_restore = matches!(
                 validation,
                 ValidationResult::ForeignWord
@@ -404,25 +995,14 @@ impl Engine {
                     | ValidationResult::InvalidSpelling
             );
 
-            if should_restore && transformed != raw {
-                // Restore to raw ASCII
-                let backspace_count = transformed.chars().count();
-                let output = format!("{}{}", raw, boundary_char);
-
-                self.buffer.clear();
-                self.reset_state();
-
-                // Update state
-                self.last_committed_char = Some(boundary_char);
-                if matches!(boundary_char, '.' | '!' | '?') {
-                    self.next_char_upper = true;
-                }
-
-                return ProcessResult::restore(output, backspace_count);
+            if should_restore && !literal {
+                return self.restore_keys(&keys, &transformed, boundary_char);
             }
         }
 
-        // Valid or acceptable - commit as-is
+        // Valid or acceptable - commit as-is, flagging Vietnamese-looking
+        // words the dictionary does not know
+        let misspelled = self.options.spell_check && !known && !transformed.is_ascii() && !literal;
         let text = format!("{}{}", transformed, boundary_char);
         self.buffer.clear();
         self.reset_state();
@@ -433,129 +1013,215 @@ impl Engine {
             self.next_char_upper = true;
         }
 
-        ProcessResult::commit(text)
+        let mut result = ProcessResult::commit(text);
+        result.misspelled = misspelled;
+        result
+    }
+
+    /// End the word by typing its raw keys in place of `transformed`
+    fn restore_keys(&mut self, raw: &str, transformed: &str, boundary_char: char) -> ProcessResult {
+        let backspace_count = transformed.chars().count();
+        let output = format!("{}{}", raw, boundary_char);
+
+        self.buffer.clear();
+        self.reset_state();
+
+        // Update state
+        self.last_committed_char = Some(boundary_char);
+        if matches!(boundary_char, '.' | '!' | '?') {
+            self.next_char_upper = true;
+        }
+
+        ProcessResult::restore(output, backspace_count)
+    }
+
+    /// Whether the keys typed so far start a word of some detector
+    fn is_foreign_prefix(&self) -> bool {
+        if self.buffer.is_empty() {
+            return false;
+        }
+        let keys = self.typed_keys(true);
+        self.foreign_detectors().any(|d| d.is_prefix(&keys))
+    }
+
+    /// Highest confidence of any detector that the keys are a foreign word
+    fn foreign_confidence(&self, keys: &str) -> Option<Confidence> {
+        self.foreign_detectors()
+            .filter_map(|d| d.detect(keys))
+            .max()
+    }
+
+    fn foreign_detectors(&self) -> impl Iterator<Item = &dyn ForeignWordDetector> {
+        let user = self
+            .user_lexicon
+            .iter()
+            .map(|l| l.as_ref() as &dyn ForeignWordDetector);
+        self.detectors.iter().map(|d| d.as_ref()).chain(user)
     }
 
     /// Apply tone mark with smart positioning
+    ///
+    /// The tone is word state rather than a property of one vowel, so later
+    /// keys can still move it (hòa → hoàn).
     fn apply_tone(&mut self, tone: ToneMark, raw_key: char) -> ProcessResult {
-        let chars: Vec<char> = self.buffer.iter().map(|bc| bc.ch).collect();
-        let vowel_indices = transform::find_vowel_indices(&chars);
-
         // Check for double-mark undo
-        if let Some(&last_idx) = vowel_indices.last() {
-            if transform::should_undo_tone(chars[last_idx], tone) {
-                // Undo: remove tone and output raw key
-                let (without_tone, _) = transform::remove_tone(chars[last_idx]);
-                self.buffer.replace(last_idx, without_tone);
-                self.buffer.push_simple(raw_key);
-
-                self.last_transform = LastTransform {
-                    position: Some(last_idx),
-                    transform_type: TransformType::None,
-                    original: None,
-                };
-
-                let text = self.buffer.get_text();
-                return ProcessResult::update(text, self.buffer.len());
+        if self.tone == tone {
+            // Undo: remove tone and output raw key
+            let old_len = self.buffer.len();
+            self.tone = ToneMark::None;
+            self.place_tone();
+            self.buffer.push_simple(raw_key);
+            if let Some(i) = self
+                .history
+                .iter()
+                .rposition(|r| r.transform_type == TransformType::Tone)
+            {
+                self.history[i].transform_type = TransformType::Undone;
             }
+
+            let text = self.buffer.get_text();
+            return ProcessResult::update(text, old_len);
         }
 
         // Find best position for tone
-        if let Some(pos) = transform::find_tone_position(&chars, &vowel_indices) {
-            let old_char = chars[pos];
-            if let Some(new_char) = transform::apply_tone(old_char, tone) {
-                self.buffer.replace(pos, new_char);
-
-                self.last_transform = LastTransform {
-                    position: Some(pos),
-                    transform_type: TransformType::Tone(tone),
-                    original: Some(old_char),
-                };
+        let chars: Vec<char> = self.buffer.iter().map(|bc| bc.ch).collect();
+        let vowel_indices = transform::find_vowel_indices(&chars);
+        if transform::find_tone_position_styled(&chars, &vowel_indices, self.options.modern_tone)
+            .is_some()
+        {
+            let previous_tone = self.tone;
+            self.tone = tone;
+            self.place_tone();
+
+            let end = self.buffer.len();
+            self.history.push(TransformRecord {
+                transform_type: TransformType::Tone,
+                start: end,
+                before: Vec::new(),
+                after_len: 0,
+                end,
+                key: Some(raw_key),
+                previous_tone,
+                prefix: None,
+            });
 
-                let text = self.buffer.get_text();
-                return ProcessResult::update(text, self.buffer.len());
-            }
+            let text = self.buffer.get_text();
+            return ProcessResult::update(text, self.buffer.len());
         }
 
         // No valid vowel found - treat as regular character
-        self.handle_regular_char(raw_key)
+        self.handle_plain_key(raw_key)
     }
 
-    /// Apply vowel modifier with UO compound handling
-    fn apply_modifier(&mut self, modifier: VowelMod, raw_key: char) -> ProcessResult {
-        let mut chars: Vec<char> = self.buffer.iter().map(|bc| bc.ch).collect();
-
-        // Check for UO compound first (uo → ươ)
-        if modifier == VowelMod::Horn {
-            if let Some(pos) = self.find_uo_compound(&chars) {
-                let result = transform::apply_uo_compound(&mut chars, pos);
-                if result.success {
-                    // Update buffer with transformed chars
-                    self.buffer.replace(pos, chars[pos]);
-                    self.buffer.replace(pos + 1, chars[pos + 1]);
-
-                    self.last_transform = LastTransform {
-                        position: Some(pos),
-                        transform_type: TransformType::Modifier(modifier),
-                        original: None,
-                    };
-
-                    let text = self.buffer.get_text();
-                    return ProcessResult::update(text, self.buffer.len());
+    /// Place the word tone on the vowel chosen by the tone rules, clearing
+    /// it from any other vowel. Returns true if the buffer changed.
+    fn place_tone(&mut self) -> bool {
+        let chars: Vec<char> = self.buffer.iter().map(|bc| bc.ch).collect();
+        let mut placed: Vec<char> = chars.iter().map(|&c| transform::remove_tone(c).0).collect();
+
+        if self.tone != ToneMark::None {
+            let vowel_indices = transform::find_vowel_indices(&placed);
+            if let Some(pos) = transform::find_tone_position_styled(
+                &placed,
+                &vowel_indices,
+                self.options.modern_tone,
+            ) {
+                if let Some(with_tone) = transform::apply_tone(placed[pos], self.tone) {
+                    placed[pos] = with_tone;
                 }
             }
         }
 
-        // Find position for modifier
-        if let Some(pos) = transform::find_modifier_position(&chars, modifier) {
-            let old_char = chars[pos];
-
-            // Check for double-mark undo
-            if transform::should_undo_modifier(old_char, modifier) {
-                let (without_mod, _) = transform::remove_modifier(old_char);
-                self.buffer.replace(pos, without_mod);
-                self.buffer.push_simple(raw_key);
-
-                let text = self.buffer.get_text();
-                return ProcessResult::update(text, self.buffer.len());
+        let mut changed = false;
+        for (i, (&old, &new)) in chars.iter().zip(&placed).enumerate() {
+            if old != new {
+                self.buffer.replace(i, new);
+                changed = true;
             }
+        }
+        changed
+    }
 
-            if let Some(new_char) = transform::apply_modifier(old_char, modifier) {
-                self.buffer.replace(pos, new_char);
+    /// Apply vowel modifier to the syllable nucleus
+    ///
+    /// The target vowel comes from the syllable model, so the modifier lands
+    /// on the right vowel wherever it was typed (tuoiw → tươi).
+    fn apply_modifier(&mut self, modifier: VowelMod, raw_key: char) -> ProcessResult {
+        let chars: Vec<char> = self.buffer.iter().map(|bc| bc.ch).collect();
 
-                self.last_transform = LastTransform {
-                    position: Some(pos),
-                    transform_type: TransformType::Modifier(modifier),
-                    original: Some(old_char),
-                };
+        let Some(edit) = syllable::resolve_modifier(&chars, modifier, raw_key) else {
+            // No valid vowel found - treat as regular character
+            return self.handle_plain_key(raw_key);
+        };
 
-                let text = self.buffer.get_text();
-                return ProcessResult::update(text, self.buffer.len());
-            }
+        let last_key_was_modifier = self.history.last().is_some_and(|r| {
+            r.transform_type == TransformType::Modifier && r.end == self.buffer.len()
+        });
+        if edit.undo && !last_key_was_modifier {
+            // Already applied by an earlier key (uwow → ươ): swallow the key
+            let end = self.buffer.len();
+            self.history.push(TransformRecord {
+                transform_type: TransformType::UoCompletion,
+                start: end,
+                before: Vec::new(),
+                after_len: 0,
+                end,
+                key: Some(raw_key),
+                previous_tone: self.tone,
+                prefix: None,
+            });
+            let text = self.buffer.get_text();
+            return ProcessResult::update(text, self.buffer.len());
         }
 
-        // No valid vowel found - treat as regular character
-        self.handle_regular_char(raw_key)
-    }
-
-    /// Find UO compound position
-    fn find_uo_compound(&self, chars: &[char]) -> Option<usize> {
-        for i in 0..chars.len().saturating_sub(1) {
-            let first = chars::get_base(chars[i].to_ascii_lowercase());
-            let second = chars::get_base(chars[i + 1].to_ascii_lowercase());
+        let start = edit.changes.iter().map(|&(pos, _)| pos).min().unwrap_or(0);
+        let end = edit
+            .changes
+            .iter()
+            .map(|&(pos, _)| pos + 1)
+            .max()
+            .unwrap_or(0);
+        let before = self.buffer.as_slice()[start..end].to_vec();
+
+        for &(pos, ch) in &edit.changes {
+            self.buffer.replace(pos, ch);
+        }
 
-            if first == 'u' && second == 'o' {
-                return Some(i);
+        // Double-mark undo: modifier removed, raw key typed
+        if edit.undo {
+            let old_len = self.buffer.len();
+            if let Some(record) = self.history.last_mut() {
+                record.transform_type = TransformType::Undone;
             }
+            self.buffer.push_simple(raw_key);
+            self.place_tone();
+
+            let text = self.buffer.get_text();
+            return ProcessResult::update(text, old_len);
         }
-        None
+
+        self.place_tone();
+
+        self.history.push(TransformRecord {
+            transform_type: TransformType::Modifier,
+            start,
+            after_len: before.len(),
+            before,
+            end: self.buffer.len(),
+            key: Some(raw_key),
+            previous_tone: self.tone,
+            prefix: None,
+        });
+
+        let text = self.buffer.get_text();
+        ProcessResult::update(text, self.buffer.len())
     }
 
     /// Apply stroke (d → đ) with delayed stroke logic
     /// Based on smart stroke handling:
     /// - Allow immediate stroke for short patterns (dd → đ, did → đi)
     /// - Validate syllable structure before applying to prevent invalid transforms
-    fn apply_stroke(&mut self) -> ProcessResult {
+    fn apply_stroke(&mut self, raw_key: char) -> ProcessResult {
         let chars: Vec<char> = self.buffer.iter().map(|bc| bc.ch).collect();
 
         // Check if buffer has any vowels
@@ -577,7 +1243,7 @@ impl Engine {
         });
 
         if let Some(i) = d_pos {
-            if let Some(bc) = self.buffer.get(i) {
+            if let Some(&bc) = self.buffer.get(i) {
                 let ch = bc.ch;
 
                 // Delayed stroke logic:
@@ -593,7 +1259,7 @@ impl Engine {
                         let text = self.buffer.get_text();
                         if !validation::is_valid_syllable(&text) {
                             // Invalid syllable - don't apply stroke
-                            return ProcessResult::passthrough();
+                            return self.handle_plain_key(raw_key);
                         }
                     }
                 }
@@ -601,22 +1267,45 @@ impl Engine {
                 let new_char = transform::toggle_stroke(ch);
                 self.buffer.replace(i, new_char);
 
-                self.last_transform = LastTransform {
-                    position: Some(i),
+                // Double-key undo (ddd → dd): stroke removed, raw key typed
+                if chars::to_lower(ch) == 'đ' {
+                    let old_len = self.buffer.len();
+                    if let Some(i) = self
+                        .history
+                        .iter()
+                        .rposition(|r| r.transform_type == TransformType::Stroke)
+                    {
+                        self.history[i].transform_type = TransformType::Undone;
+                    }
+                    self.buffer.push_simple(raw_key);
+
+                    let text = self.buffer.get_text();
+                    return ProcessResult::update(text, old_len);
+                }
+
+                self.history.push(TransformRecord {
                     transform_type: TransformType::Stroke,
-                    original: Some(ch),
-                };
+                    start: i,
+                    before: vec![bc],
+                    after_len: 1,
+                    end: self.buffer.len(),
+                    key: Some(raw_key),
+                    previous_tone: self.tone,
+                    prefix: None,
+                });
 
                 let text = self.buffer.get_text();
                 return ProcessResult::update(text, self.buffer.len());
             }
         }
 
-        ProcessResult::passthrough()
+        self.handle_plain_key(raw_key)
     }
 
     /// Remove all diacritics
-    fn remove_all_diacritics(&mut self) -> ProcessResult {
+    fn remove_all_diacritics(&mut self, raw_key: char) -> ProcessResult {
+        let before = self.buffer.as_slice().to_vec();
+        let previous_tone = self.tone;
         let mut changed = false;
 
         for i in 0..self.buffer.len() {
@@ -630,46 +1319,103 @@ impl Engine {
             }
         }
 
+        self.tone = ToneMark::None;
+
         if changed {
-            self.last_transform = LastTransform::default();
+            self.history.push(TransformRecord {
+                transform_type: TransformType::RemoveDiacritics,
+                start: 0,
+                after_len: before.len(),
+                end: before.len(),
+                before,
+                key: Some(raw_key),
+                previous_tone,
+                prefix: None,
+            });
             let text = self.buffer.get_text();
             ProcessResult::update(text, self.buffer.len())
         } else {
-            ProcessResult::passthrough()
+            self.handle_plain_key(raw_key)
         }
     }
 
     /// Undo last transform
-    fn undo_last_transform(&mut self) -> ProcessResult {
-        if let (Some(pos), Some(original)) =
-            (self.last_transform.position, self.last_transform.original)
+    ///
+    /// Pops one record off the word's history: the replaced characters come
+    /// back and the consumed key is typed where it was pressed. With
+    /// nothing left to undo the key is typed as a regular character.
+    fn undo_last_transform(&mut self, key: char) -> ProcessResult {
+        while self
+            .history
+            .last()
+            .is_some_and(|r| r.transform_type == TransformType::Undone)
         {
-            if pos < self.buffer.len() {
-                self.buffer.replace(pos, original);
-                self.last_transform = LastTransform::default();
+            self.history.pop();
+        }
+        let Some(record) = self.history.pop() else {
+            return self.handle_regular_char(key);
+        };
+        let old_len = self.buffer.len();
 
-                let text = self.buffer.get_text();
-                return ProcessResult::update(text, self.buffer.len());
+        if let Some(raw) = record.key {
+            self.buffer
+                .splice(record.end, 0, &[BufferChar::simple(raw)]);
+        }
+        self.buffer
+            .splice(record.start, record.after_len, &record.before);
+        self.tone = record.previous_tone;
+        self.place_tone();
+
+        let mut text = self.buffer.get_text();
+        if let Some(prefix) = record.prefix {
+            self.shortcut_prefix = Some(prefix);
+            text.insert(0, prefix);
+        }
+        ProcessResult::update(text, old_len)
+    }
+
+    /// Keys typed for the current word, recovered by undoing every
+    /// recorded transform on a copy of the buffer (expect, not ễpct)
+    ///
+    /// A key swallowed by a double-mark undo only counts if `swallowed`:
+    /// "aaa" typed on purpose stays "aa", while a lexicon still sees "boss".
+    fn typed_keys(&self, swallowed: bool) -> String {
+        let mut buffer = self.buffer.clone();
+        for record in self.history.iter().rev() {
+            let undone = record.transform_type == TransformType::Undone;
+            if let Some(raw) = record.key.filter(|_| swallowed || !undone) {
+                buffer.splice(record.end, 0, &[BufferChar::simple(raw)]);
             }
+            buffer.splice(record.start, record.after_len, &record.before);
         }
+        buffer.get_raw()
+    }
 
-        ProcessResult::passthrough()
+    /// Forget transforms that touched characters past `len`
+    fn drop_history_after(&mut self, len: usize) {
+        let keep = self
+            .history
+            .iter()
+            .position(|r| r.end > len)
+            .unwrap_or(self.history.len());
+        // Records under a shortcut refer to its trigger, which is gone too
+        let dropped_shortcut = self.history[keep..]
+            .iter()
+            .any(|r| r.transform_type == TransformType::Shortcut);
+        self.history
+            .truncate(if dropped_shortcut { 0 } else { keep });
     }
 
     /// Apply Quick Telex: expand double consonant to consonant pair
     /// e.g., "cc" → "ch", "gg" → "gh", "nn" → "nh", etc.
-    fn apply_quick_telex(&mut self, replacement: &str, _raw_key: char) -> ProcessResult {
+    fn apply_quick_telex(&mut self, replacement: &str, raw_key: char) -> ProcessResult {
         // Remove the first consonant (which was doubled)
-        if !self.buffer.is_empty() {
-            self.buffer.pop();
-        }
+        let old_len = self.buffer.len();
+        let start = old_len.saturating_sub(1);
+        let before: Vec<BufferChar> = self.buffer.pop().into_iter().collect();
 
         // Add the replacement characters (preserving case of original)
-        let was_upper = self
-            .buffer
-            .last()
-            .map(|bc| bc.ch.is_uppercase())
-            .unwrap_or(false);
+        let was_upper = before.first().is_some_and(|bc| bc.ch.is_uppercase());
 
         for (i, ch) in replacement.chars().enumerate() {
             let ch_to_push = if i == 0 && was_upper {
@@ -679,15 +1425,26 @@ impl Engine {
             };
             self.buffer.push_simple(ch_to_push);
         }
-
-        self.last_transform = LastTransform::default();
+        self.place_tone();
+
+        self.history.push(TransformRecord {
+            transform_type: TransformType::QuickTelex,
+            start,
+            before,
+            after_len: self.buffer.len() - start,
+            end: self.buffer.len(),
+            key: Some(raw_key),
+            previous_tone: self.tone,
+            prefix: None,
+        });
         let text = self.buffer.get_text();
-        ProcessResult::update(text, self.buffer.len())
+        ProcessResult::update(text, old_len)
     }
 
     /// Reset internal state
     fn reset_state(&mut self) {
-        self.last_transform = LastTransform::default();
+        self.history.clear();
+        self.tone = ToneMark::None;
         self.possible_foreign = false;
     }
 
@@ -698,14 +1455,30 @@ impl Engine {
         self.reset_state();
     }
 
+    /// Tell the engine what precedes the caret, as reported by a host's
+    /// surrounding text, so auto-capitalization and smart quotes follow
+    /// the document instead of the keys seen so far
+    ///
+    /// Ignored while a word is being composed.
+    pub fn set_text_before_cursor(&mut self, text: &str) {
+        if !self.buffer.is_empty() {
+            return;
+        }
+        self.last_committed_char = text.chars().last();
+        self.next_char_upper =
+            matches!(text.trim_end().chars().last(), None | Some('.' | '!' | '?'));
+    }
+
     /// Get current buffer text
     pub fn get_buffer(&self) -> String {
         self.buffer.get_text()
     }
 
-    /// Get raw buffer (without transforms)
+    /// Get raw buffer: every key typed for the current word, including
+    /// those swallowed by a double-mark undo (boss, not bos); a restore
+    /// types exactly these keys back
     pub fn get_raw_buffer(&self) -> String {
-        self.buffer.get_raw()
+        self.typed_keys(true)
     }
 
     /// Check if enabled
@@ -745,11 +1518,27 @@ impl Default for Engine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::convert::TextField;
+
+    fn options(
+        auto_capitalize: bool,
+        smart_quotes: bool,
+        spell_check: bool,
+        modern_tone: bool,
+    ) -> EngineOptions {
+        EngineOptions {
+            auto_capitalize,
+            smart_quotes,
+            spell_check,
+            modern_tone,
+            ..EngineOptions::default()
+        }
+    }
 
     #[test]
     fn test_telex_basic() {
         let mut engine = Engine::new();
-        engine.set_options(false, false, false);
+        engine.set_options(options(false, false, false, true));
         engine.set_method("telex");
 
         engine.process_key('v', false);
@@ -763,7 +1552,7 @@ mod tests {
     #[test]
     fn test_telex_tone() {
         let mut engine = Engine::new();
-        engine.set_options(false, false, false);
+        engine.set_options(options(false, false, false, true));
         engine.set_method("telex");
 
         engine.process_key('a', false);
@@ -775,7 +1564,7 @@ mod tests {
     #[test]
     fn test_telex_circumflex() {
         let mut engine = Engine::new();
-        engine.set_options(false, false, false);
+        engine.set_options(options(false, false, false, true));
         engine.set_method("telex");
 
         engine.process_key('a', false);
@@ -787,7 +1576,7 @@ mod tests {
     #[test]
     fn test_double_mark_undo() {
         let mut engine = Engine::new();
-        engine.set_options(false, false, false);
+        engine.set_options(options(false, false, false, true));
         engine.set_method("telex");
 
         engine.process_key('a', false);
@@ -800,7 +1589,7 @@ mod tests {
     #[test]
     fn test_vni_basic() {
         let mut engine = Engine::new();
-        engine.set_options(false, false, false);
+        engine.set_options(options(false, false, false, true));
         engine.set_method("vni");
 
         engine.process_key('a', false);
@@ -812,7 +1601,7 @@ mod tests {
     #[test]
     fn test_shortcut_expansion() {
         let mut engine = Engine::new();
-        engine.set_options(false, false, false); // Disable auto-cap
+        engine.set_options(options(false, false, false, true)); // Disable auto-cap
 
         engine.process_key('k', false);
         engine.process_key('o', false);
@@ -823,15 +1612,16 @@ mod tests {
         // Type space to trigger
         let result = engine.process_key(' ', false);
 
-        // Should expand "ko" to "không" + space
+        // Should expand "ko" to "không" + space; the k stays on screen
         // Buffer is cleared on boundary commit/update, so check result output
-        assert_eq!(result.output, "không ");
+        assert_eq!(result.output, "hông ");
+        assert_eq!(result.backspace, 1);
     }
 
     #[test]
     fn test_shortcut_with_prefix() {
         let mut engine = Engine::new();
-        engine.set_options(false, false, false);
+        engine.set_options(options(false, false, false, true));
         engine.add_shortcut("#vn", "Việt Nam");
 
         // 1. Type prefix '#'
@@ -858,10 +1648,37 @@ mod tests {
         assert_eq!(result.backspace, 3);
     }
 
+    #[test]
+    fn test_keys_after_immediate_shortcut() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+        let mut shortcuts = ShortcutTable::new();
+        shortcuts.enable();
+        shortcuts.add(crate::shortcut::Shortcut::immediate("vn", "Việt Nam"));
+        engine.set_shortcuts(shortcuts);
+
+        // The expansion keeps its tones and the next letter starts a word
+        let mut field = TextField::new();
+        for key in "vnb".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(field.text(), "Việt Namb");
+        assert_eq!(engine.get_buffer(), "b");
+
+        engine.clear();
+        let mut field = TextField::new();
+        for key in "vn".chars() {
+            field.type_key(&mut engine, key);
+        }
+        field.press_backspace(&mut engine);
+        field.type_key(&mut engine, 's');
+        assert_eq!(field.text(), "Việt Nas");
+    }
+
     #[test]
     fn test_auto_capitalize() {
         let mut engine = Engine::new();
-        engine.set_options(true, false, true);
+        engine.set_options(options(true, false, true, true));
 
         // 1. Start of text -> Capitalize
         let result = engine.process_key('h', false);
@@ -884,7 +1701,7 @@ mod tests {
     #[test]
     fn test_smart_quotes() {
         let mut engine = Engine::new();
-        engine.set_options(false, true, true);
+        engine.set_options(options(false, true, true, true));
 
         // 1. Open quote
         let result = engine.process_key('"', false);
@@ -896,4 +1713,710 @@ mod tests {
         let result = engine.process_key('"', false);
         assert!(result.output.ends_with("”"));
     }
+
+    #[test]
+    fn test_traditional_tone_style() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, false));
+
+        for key in "hoaf".chars() {
+            engine.process_key(key, false);
+        }
+
+        assert_eq!(engine.get_buffer(), "hòa");
+    }
+
+    #[test]
+    fn test_switch_tone_style_replaces_tone() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        for key in "thuyr".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "thuỷ");
+
+        let result = engine.set_options(options(false, false, false, false));
+        assert_eq!(result.action, Action::Update);
+        assert_eq!(result.output, "ủy");
+        assert_eq!(result.backspace, 2);
+        assert_eq!(engine.get_buffer(), "thủy");
+
+        // Unchanged style leaves the screen alone
+        let result = engine.set_options(options(false, false, false, false));
+        assert_eq!(result.action, Action::Passthrough);
+    }
+
+    #[test]
+    fn test_tone_follows_syllable() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, false));
+
+        for key in "hoaf".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "hòa");
+
+        // A final consonant moves the tone to the second vowel
+        let result = engine.process_key('n', false);
+        assert_eq!(engine.get_buffer(), "hoàn");
+        assert_eq!(result.output, "oàn");
+        assert_eq!(result.backspace, 2);
+    }
+
+    #[test]
+    fn test_tone_typed_before_vowel() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        // Tone typed early, then more vowels arrive
+        for key in "thusy".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "thuý");
+
+        // Horn added after the tone moves it onto ơ (open uo: only the o)
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+        engine.set_method("vni");
+        for key in "tuo27".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "tuờ");
+
+        // A following glide turns it into ươ
+        engine.process_key('i', false);
+        assert_eq!(engine.get_buffer(), "tười");
+    }
+
+    /// Key orderings that must all produce the same word
+    const KEY_ORDER_CORPUS: &[(&str, &str, &[&str])] = &[
+        (
+            "telex",
+            "người",
+            &["nguwowif", "nguowif", "nguoiwf", "nguoifw", "nguwoif"],
+        ),
+        ("telex", "tươi", &["tuwowi", "tuowi", "tuoiw", "tuwoi"]),
+        ("telex", "việt", &["vieetj", "vietej", "viejet", "vieejt"]),
+        (
+            "telex",
+            "quyển",
+            &["quyeenr", "quyenre", "quyener", "quyeren"],
+        ),
+        ("telex", "xoăn", &["xoawn", "xoanw"]),
+        ("telex", "mưa", &["muwa", "muaw"]),
+        ("telex", "thuở", &["thuowr", "thuorw"]),
+        ("telex", "huơ", &["huow"]),
+        ("telex", "khuở", &["khuowr", "khuorw"]),
+        ("telex", "hoàn", &["hoanf", "hoafn", "hofan"]),
+        (
+            "telex",
+            "được",
+            &["dduwowcj", "dduowcj", "dduocwj", "dduocjw"],
+        ),
+        (
+            "vni",
+            "người",
+            &["ngu7o7i2", "nguo7i2", "nguoi72", "nguoi27"],
+        ),
+        (
+            "vni",
+            "tiếng",
+            &["tie6ng1", "tieng61", "tieng16", "tie1ng6"],
+        ),
+        ("vni", "mưa", &["mu7a", "mua7"]),
+        ("vni", "thuở", &["thuo73", "thuo37"]),
+        ("vni", "huơ", &["huo7"]),
+    ];
+
+    #[test]
+    fn test_key_order_independence() {
+        for &(method, expected, orderings) in KEY_ORDER_CORPUS {
+            for keys in orderings {
+                let mut engine = Engine::new();
+                engine.set_options(options(false, false, false, true));
+                engine.set_method(method);
+                for key in keys.chars() {
+                    engine.process_key(key, false);
+                }
+                assert_eq!(engine.get_buffer(), expected, "{method}: {keys}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_backspace_keeps_word_editable() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        for key in "tieengs".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "tiếng");
+
+        let result = engine.process_backspace();
+        assert_eq!(result.action, Action::Passthrough);
+        assert_eq!(engine.get_buffer(), "tiến");
+        engine.process_backspace();
+        assert_eq!(engine.get_buffer(), "tiế");
+
+        // The word is still live: a new final keeps the tone
+        engine.process_key('t', false);
+        assert_eq!(engine.get_buffer(), "tiết");
+    }
+
+    #[test]
+    fn test_backspace_moves_tone() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, false));
+
+        for key in "hoanf".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "hoàn");
+
+        // Without the final the traditional style puts the tone back on o
+        let result = engine.process_backspace();
+        assert_eq!(result.action, Action::Update);
+        assert_eq!(result.output, "òa");
+        assert_eq!(result.backspace, 3);
+    }
+
+    #[test]
+    fn test_backspace_removes_tone_vowel() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        for key in "mas".chars() {
+            engine.process_key(key, false);
+        }
+        engine.process_backspace();
+        assert_eq!(engine.get_buffer(), "m");
+
+        // The tone went with the vowel
+        engine.process_key('a', false);
+        assert_eq!(engine.get_buffer(), "ma");
+
+        engine.process_backspace();
+        engine.process_backspace();
+        assert_eq!(engine.get_buffer(), "");
+        assert_eq!(engine.process_backspace().action, Action::Passthrough);
+    }
+
+    #[test]
+    fn test_multi_level_undo() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+        engine.set_undo_key(Some('`'));
+
+        for key in "dduwowcj".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "được");
+
+        // Tone first, then the horn, then the stroke
+        let result = engine.process_key('`', false);
+        assert_eq!(result.output, "ơcj");
+        assert_eq!(result.backspace, 2);
+        engine.process_key('`', false);
+        assert_eq!(engine.get_buffer(), "đươwcj");
+        engine.process_key('`', false);
+        assert_eq!(engine.get_buffer(), "đưowcj");
+        engine.process_key('`', false);
+        assert_eq!(engine.get_buffer(), "đuwowcj");
+        engine.process_key('`', false);
+        assert_eq!(engine.get_buffer(), "dduwowcj");
+
+        // Nothing left: the key is an ordinary boundary again
+        let result = engine.process_key('`', false);
+        assert_eq!(result.action, Action::Commit);
+    }
+
+    #[test]
+    fn test_undo_quick_telex_and_shortcut() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+        engine.set_method("vni");
+        engine.set_undo_key(Some('z'));
+
+        for key in "nhu7ng".chars() {
+            engine.process_key(key, false);
+        }
+        engine.process_key('z', false);
+        assert_eq!(engine.get_buffer(), "nhu7ng");
+
+        engine.clear();
+        engine.set_method("telex");
+        assert_eq!(engine.get_undo_key(), Some('z'));
+        for key in "cc".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "ch");
+        engine.process_key('z', false);
+        assert_eq!(engine.get_buffer(), "cc");
+
+        engine.clear();
+        let mut shortcuts = ShortcutTable::new();
+        shortcuts.enable();
+        shortcuts.add(crate::shortcut::Shortcut::immediate("vn", "Việt Nam"));
+        engine.set_shortcuts(shortcuts);
+        engine.process_key('v', false);
+        engine.process_key('n', false);
+        assert_eq!(engine.get_buffer(), "Việt Nam");
+        let result = engine.process_key('z', false);
+        assert_eq!(result.output, "vn");
+        assert_eq!(result.backspace, 8);
+    }
+
+    #[test]
+    fn test_backspace_drops_undo_history() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+        engine.set_undo_key(Some('`'));
+
+        for key in "toots".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "tốt");
+
+        // The tone key was pressed after "t", so deleting "t" forgets it
+        engine.process_backspace();
+        engine.process_key('`', false);
+        assert_eq!(engine.get_buffer(), "too");
+    }
+
+    #[test]
+    fn test_custom_method() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        let definition = r#"{
+            "name": "mini",
+            "rules": [
+                { "key": "1", "action": { "tone": "acute" } },
+                { "key": "q", "after_letter": true, "action": { "modifier": "circumflex" } }
+            ]
+        }"#;
+        engine.load_custom_method(definition).unwrap();
+        assert_eq!(engine.get_method(), "mini");
+
+        for key in "vietq1".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "viết");
+
+        // Built-ins are still there and the custom method can be re-selected
+        engine.set_method("telex");
+        engine.set_method("Mini");
+        assert_eq!(engine.get_method(), "mini");
+
+        let broken = r#"{ "name": "x", "rules": [
+            { "key": "1", "action": { "tone": "acute" } },
+            { "key": "1", "action": { "tone": "grave" } }
+        ] }"#;
+        assert!(engine.load_custom_method(broken).is_err());
+        assert_eq!(engine.get_method(), "mini");
+    }
+
+    #[test]
+    fn test_only_changed_suffix_is_retyped() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        let mut field = TextField::new();
+        let edits: Vec<(usize, String)> = "vieetj"
+            .chars()
+            .map(|key| {
+                let result = field.type_key(&mut engine, key);
+                (result.backspace, result.output)
+            })
+            .collect();
+
+        assert_eq!(edits[3], (1, "ê".to_string()));
+        assert_eq!(edits[4], (0, "t".to_string()));
+        assert_eq!(edits[5], (2, "ệt".to_string()));
+        assert_eq!(field.text(), "việt");
+    }
+
+    #[test]
+    fn test_double_key_undo_backspace() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        // Text before the word must survive every retype
+        for (keys, expected) in [
+            ("ass", "as"),
+            ("aaa", "aa"),
+            ("ddd", "dd"),
+            ("cc", "ch"),
+            ("t[", "tư"),
+            ("t[[", "t["),
+        ] {
+            engine.clear();
+            let mut field = TextField::with_text("x ");
+            for key in keys.chars() {
+                field.type_key(&mut engine, key);
+            }
+            assert_eq!(field.text(), format!("x {}", expected), "keys {:?}", keys);
+        }
+    }
+
+    #[test]
+    fn test_unused_transform_key_is_typed() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        // z with nothing to remove stays in the word
+        engine.process_key('f', false);
+        engine.process_key('z', false);
+        assert_eq!(engine.get_buffer(), "fz");
+
+        engine.clear();
+        engine.set_method("vni");
+        for key in "oxxd9".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "oxxd9");
+    }
+
+    #[test]
+    fn test_restore_uses_typed_keys() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        // x and the second e were consumed as tone and circumflex
+        let mut field = TextField::new();
+        for key in "expect ".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(field.text(), "expect ");
+
+        // The raw buffer is what a restore types back, swallowed keys too
+        let mut field = TextField::new();
+        for key in "boss".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(engine.get_raw_buffer(), "boss");
+        field.type_key(&mut engine, ' ');
+        assert_eq!(field.text(), "boss ");
+    }
+
+    #[test]
+    fn test_spell_check() {
+        let type_word = |spell_check: bool, keys: &str| {
+            let mut engine = Engine::new();
+            engine.set_options(options(false, false, spell_check, true));
+            let mut field = TextField::new();
+            let mut result = ProcessResult::passthrough();
+            for key in keys.chars() {
+                result = field.type_key(&mut engine, key);
+            }
+            (field.text().to_string(), result.misspelled)
+        };
+
+        // The rules accept "tàc", the dictionary does not: kept but reported
+        assert_eq!(type_word(false, "tafc "), ("tàc ".to_string(), false));
+        assert_eq!(type_word(true, "tafc "), ("tàc ".to_string(), true));
+        assert_eq!(type_word(true, "buyts "), ("buýt ".to_string(), false));
+        assert_eq!(type_word(true, "ddm "), ("đm ".to_string(), true));
+        assert_eq!(type_word(false, "ddm "), ("đm ".to_string(), false));
+        // Spellings the rules allow but no word uses are reported too
+        for (keys, word) in [
+            ("guowmr ", "gưởm "),
+            ("buowngr ", "bưởng "),
+            ("khuychs ", "khuých "),
+            ("thoetj ", "thoẹt "),
+        ] {
+            assert_eq!(type_word(true, keys), (word.to_string(), true));
+        }
+        // Plain ASCII is never reported
+        assert_eq!(type_word(true, "hello "), ("hello ".to_string(), false));
+    }
+
+    #[test]
+    fn test_lexicons() {
+        let type_text = |engine: &mut Engine, keys: &str| {
+            let mut field = TextField::new();
+            for key in keys.chars() {
+                field.type_key(engine, key);
+            }
+            field.text().to_string()
+        };
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, true, true));
+        // Neither word is in the bundled English lexicon
+        assert_eq!(type_text(&mut engine, "tox kubectl "), "tõ kubectl ");
+
+        engine.load_lexicon("# work\n!tox\nkubectl\n");
+        assert_eq!(type_text(&mut engine, "tox kubectl "), "tox kubectl ");
+
+        // Lexicons are saved with the configuration
+        let mut restored = Engine::from_config(&engine.config()).unwrap();
+        assert_eq!(type_text(&mut restored, "tox "), "tox ");
+        restored.clear_lexicons();
+        assert!(restored.config().lexicons.is_empty());
+        assert_eq!(type_text(&mut restored, "tox "), "tõ ");
+
+        // Without detectors only the spelling rules restore
+        engine.set_foreign_detectors(Vec::new());
+        engine.clear_lexicons();
+        assert_eq!(type_text(&mut engine, "mix "), "mĩ ");
+        engine.add_foreign_detector(Box::new(Lexicon::parse("!mix")));
+        assert_eq!(type_text(&mut engine, "mix "), "mix ");
+    }
+
+    #[test]
+    fn test_nfd_output() {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            output_encoding: OutputEncoding::Nfd,
+            ..options(false, false, false, true)
+        });
+
+        let mut field = TextField::new();
+        for key in "vieetj nguwowif tas".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(field.text(), OutputEncoding::Nfd.encode("việt người tá"));
+
+        // Backspace removes the whole decomposed "á"
+        assert_eq!(field.press_backspace(&mut engine).backspace, 2);
+        assert_eq!(field.text(), OutputEncoding::Nfd.encode("việt người t"));
+    }
+
+    #[test]
+    fn test_vni_windows_output() {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            output_encoding: OutputEncoding::VniWindows,
+            ..options(false, false, false, true)
+        });
+
+        let mut field = TextField::new();
+        for key in "vieet".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(field.text(), "vie\u{E2}t");
+
+        // Only the bytes after "vie" change
+        assert_eq!(field.type_key(&mut engine, 'j').backspace, 2);
+        assert_eq!(field.text(), "vie\u{E4}t");
+    }
+
+    #[test]
+    fn test_encoding_switch_retypes_word() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        let mut field = TextField::new();
+        for key in "vieet".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(field.text(), "viêt");
+
+        let result = engine.set_options(EngineOptions {
+            output_encoding: OutputEncoding::Nfd,
+            ..engine.get_options()
+        });
+        assert_eq!(result.backspace, 2);
+        field.apply(&result, None);
+        assert_eq!(field.text(), "vie\u{0302}t");
+
+        assert_eq!(field.type_key(&mut engine, 'j').backspace, 2);
+        assert_eq!(field.text(), "vie\u{0323}\u{0302}t");
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut engine = Engine::new();
+        engine.set_options(options(true, false, false, true));
+
+        let mut field = TextField::new();
+        for key in "xin. nguowf".chars() {
+            field.type_key(&mut engine, key);
+        }
+        let saved = EngineSnapshot::from_json(&engine.snapshot().to_json()).unwrap();
+        assert_eq!(saved, engine.snapshot());
+
+        // Another field is typed into, then focus comes back
+        engine.clear();
+        engine.process_key('a', false);
+        engine.restore_snapshot(&saved);
+
+        field.type_key(&mut engine, 'i');
+        assert_eq!(field.text(), "Xin. Người");
+        assert_eq!(engine.get_raw_buffer(), "Nguowfi");
+    }
+
+    #[test]
+    fn test_text_before_cursor() {
+        let mut engine = Engine::new();
+        engine.set_options(options(true, true, false, true));
+
+        // Caret moved into the middle of a sentence
+        let mut field = TextField::with_text("Xin chào ");
+        engine.set_text_before_cursor(field.text());
+        for key in "ban\"".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(field.text(), "Xin chào ban”");
+
+        let mut field = TextField::with_text("Xong. ");
+        engine.clear();
+        engine.set_text_before_cursor(field.text());
+        for key in "\"ok".chars() {
+            field.type_key(&mut engine, key);
+        }
+        assert_eq!(field.text(), "Xong. “Ok");
+
+        // Not while composing
+        engine.set_text_before_cursor("");
+        field.type_key(&mut engine, 'a');
+        assert_eq!(field.text(), "Xong. “Oka");
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, false));
+        engine
+            .load_custom_method(
+                r#"{ "name": "mini", "rules": [{ "key": "1", "action": { "tone": "acute" } }] }"#,
+            )
+            .unwrap();
+        engine.set_undo_key(Some('`'));
+        engine.add_shortcut("tphcm", "Thành phố Hồ Chí Minh");
+        engine.set_enabled(false);
+
+        let json = engine.config().to_json();
+        let restored = Engine::from_config(&EngineConfig::from_json(&json).unwrap()).unwrap();
+        assert_eq!(restored.config(), engine.config());
+        assert_eq!(restored.config().to_json(), json);
+        assert_eq!(restored.get_method(), "mini");
+        assert!(!restored.is_enabled());
+
+        let mut restored = restored;
+        restored.set_enabled(true);
+        for key in "ma1".chars() {
+            restored.process_key(key, false);
+        }
+        assert_eq!(restored.get_buffer(), "má");
+    }
+
+    #[test]
+    fn test_viqr_marks_and_boundaries() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, true, false, true));
+        engine.set_method("viqr");
+
+        for key in "Vie^.t".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "Việt");
+        assert_eq!(engine.process_key(' ', false).output, "Việt ");
+
+        // After a consonant "." ends the sentence
+        for key in "Nam".chars() {
+            engine.process_key(key, false);
+        }
+        let result = engine.process_key('.', false);
+        assert_eq!(result.action, Action::Commit);
+        assert_eq!(result.output, "Nam.");
+
+        // Escaped mark key is typed literally
+        for key in "ta\\.".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "ta.");
+        engine.clear();
+
+        // Marks win over smart quotes inside a word
+        engine.process_key('a', false);
+        engine.process_key('\'', false);
+        assert_eq!(engine.get_buffer(), "á");
+    }
+
+    #[test]
+    fn test_telex_variants() {
+        let mut engine = Engine::new();
+        engine.set_options(options(false, false, false, true));
+
+        // Brackets stay brackets
+        engine.set_method("simple-telex");
+        engine.process_key('a', false);
+        let result = engine.process_key('[', false);
+        assert_eq!(result.action, Action::Commit);
+        assert_eq!(result.output, "a[");
+
+        engine.set_method("telex-w");
+        for key in "tws".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "tứ");
+        engine.clear();
+
+        // Pressing w twice gives a plain w
+        let result = engine.process_key('w', false);
+        assert_eq!(result.output, "ư");
+        engine.process_key('w', false);
+        assert_eq!(engine.get_buffer(), "w");
+    }
+
+    #[test]
+    fn test_available_methods() {
+        let mut engine = Engine::new();
+        let methods = engine.available_methods();
+        assert_eq!(methods, methods::BUILTIN_METHODS);
+
+        engine
+            .load_custom_method(r#"{ "name": "mine", "rules": [] }"#)
+            .unwrap();
+        assert_eq!(engine.available_methods().last().unwrap(), "mine");
+    }
+
+    #[test]
+    fn test_options_merge_json() {
+        let current = options(false, true, false, false);
+        let merged = current
+            .merge_json(r#"{ "output_encoding": "nfd" }"#)
+            .unwrap();
+        assert_eq!(
+            merged,
+            EngineOptions {
+                output_encoding: OutputEncoding::Nfd,
+                ..current
+            }
+        );
+        assert_eq!(current.merge_json("{}").unwrap(), current);
+        assert!(current.merge_json(r#"{ "smart_quotes": 1 }"#).is_err());
+        assert!(current.merge_json("[]").is_err());
+    }
+
+    #[test]
+    fn test_feature_toggles() {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            auto_capitalize: false,
+            quick_telex: false,
+            bracket_shortcuts: false,
+            diacritic_removal: false,
+            ..EngineOptions::default()
+        });
+
+        for key in "account".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "account");
+        engine.clear();
+
+        // "[" is a plain boundary again
+        engine.process_key('a', false);
+        assert_eq!(engine.process_key('[', false).action, Action::Commit);
+
+        for key in "asz".chars() {
+            engine.process_key(key, false);
+        }
+        assert_eq!(engine.get_buffer(), "áz");
+        assert!(!engine.get_options().quick_telex);
+    }
 }
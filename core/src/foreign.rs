@@ -0,0 +1,148 @@
+//! Foreign Word Detection
+//!
+//! Telex borrows plain letters as tone and vowel keys, so English typed
+//! with Vietnamese input on comes out mangled: "mix" → "mĩ", "boss" →
+//! "bos". At a word boundary the engine asks its detectors whether the
+//! raw keys spell a foreign word and, if so, types the keys back.
+//!
+//! `Lexicon` is the stock detector: a word list stored as an FST, which is
+//! also a trie over keystrokes, so after every key the engine knows whether
+//! the word can still turn out foreign. The bundled English lexicon is
+//! compiled from `data/english.txt` by `build.rs`; users add their own.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+static ENGLISH: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/english.fst"));
+
+/// How sure a detector is that typed keys are a foreign word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// Restore unless the keys also type a Vietnamese word (bar → bả)
+    Likely,
+    /// Restore even over a Vietnamese word (mix → mĩ)
+    Certain,
+}
+
+/// Decides from raw keys whether a word is foreign
+pub trait ForeignWordDetector: Send + Sync {
+    /// Check if some foreign word starts with the typed keys
+    fn is_prefix(&self, keys: &str) -> bool;
+    /// Check if the typed keys spell a foreign word
+    fn detect(&self, keys: &str) -> Option<Confidence>;
+}
+
+/// Word list detector, matching keys case-insensitively
+#[derive(Clone)]
+pub struct Lexicon {
+    /// Lowercase word → `Confidence` as 0 (likely) or 1 (certain)
+    words: fst::Map<Cow<'static, [u8]>>,
+}
+
+impl Lexicon {
+    /// Build a lexicon from text, one word per line
+    ///
+    /// Blank lines and lines starting with `#` are skipped. A leading `!`
+    /// marks a word that wins over a Vietnamese word typed with the same
+    /// keys (`!chef` keeps "chef" from becoming "chè").
+    pub fn parse(text: &str) -> Self {
+        let mut words = BTreeMap::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (word, certain) = match line.strip_prefix('!') {
+                Some(word) => (word, 1),
+                None => (line, 0),
+            };
+            let entry = words.entry(word.to_lowercase()).or_insert(certain);
+            *entry = (*entry).max(certain);
+        }
+        let words = fst::Map::from_iter(words).expect("sorted unique keys");
+        Self {
+            words: fst::Map::new(Cow::Owned(words.into_fst().into_inner())).expect("valid FST"),
+        }
+    }
+
+    /// Bundled English lexicon
+    pub fn english() -> Self {
+        Self {
+            words: fst::Map::new(Cow::Borrowed(ENGLISH)).expect("English FST built by build.rs"),
+        }
+    }
+
+    /// Number of words
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+impl ForeignWordDetector for Lexicon {
+    fn is_prefix(&self, keys: &str) -> bool {
+        let fst = self.words.as_fst();
+        let mut node = fst.root();
+        for byte in keys.to_lowercase().bytes() {
+            match node.find_input(byte) {
+                Some(i) => node = fst.node(node.transition(i).addr),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    fn detect(&self, keys: &str) -> Option<Confidence> {
+        self.words
+            .get(keys.to_lowercase())
+            .map(|certain| match certain {
+                0 => Confidence::Likely,
+                _ => Confidence::Certain,
+            })
+    }
+}
+
+impl fmt::Debug for Lexicon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lexicon").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let lexicon = Lexicon::parse("# tools\nKubectl\n\n!chef\nchef\nnginx\n");
+        assert_eq!(lexicon.len(), 3);
+        assert_eq!(lexicon.detect("kubectl"), Some(Confidence::Likely));
+        assert_eq!(lexicon.detect("Chef"), Some(Confidence::Certain));
+        assert_eq!(lexicon.detect("chè"), None);
+        assert_eq!(lexicon.detect("# tools"), None);
+    }
+
+    #[test]
+    fn test_prefix() {
+        let lexicon = Lexicon::parse("nginx\nnode\n");
+        assert!(lexicon.is_prefix(""));
+        assert!(lexicon.is_prefix("NG"));
+        assert!(lexicon.is_prefix("node"));
+        assert!(!lexicon.is_prefix("nodes"));
+        assert!(!lexicon.is_prefix("nx"));
+    }
+
+    #[test]
+    fn test_english() {
+        let english = Lexicon::english();
+        for word in ["mix", "boss", "class", "address", "expect", "window"] {
+            assert!(english.detect(word).is_some(), "{}", word);
+        }
+        assert_eq!(english.detect("chef"), Some(Confidence::Certain));
+        assert_eq!(english.detect("vieetj"), None);
+        assert!(english.is_prefix("progra"));
+    }
+}
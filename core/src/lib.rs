@@ -8,6 +8,11 @@
 //! - Fast syllable validation
 //! - Smart diacritic placement
 //! - Zero-copy buffer management
+//! - NFC/NFD and legacy charset (TCVN3, VNI-Windows, VISCII, VPS) output
+//! - Independent input contexts sharing one configuration
+//! - Preedit (composition) events for IME frameworks
+//! - Dictionary spell check of committed syllables
+//! - English (and user lexicon) words restored from their raw keys
 //!
 #![allow(clippy::option_if_let_else)]
 
@@ -28,13 +33,26 @@
 
 pub mod buffer;
 pub mod chars;
+pub mod charset;
+pub mod config;
+pub mod context;
+pub mod convert;
+pub mod dictionary;
+pub mod encoding;
 pub mod engine;
+pub mod foreign;
 pub mod methods;
+pub mod preedit;
 pub mod shortcut;
+pub mod syllable;
 pub mod transform;
 pub mod validation;
 
-pub use engine::Engine;
+pub use config::EngineConfig;
+pub use context::{ContextId, ContextManager};
+pub use encoding::OutputEncoding;
+pub use engine::{Engine, EngineOptions, EngineSnapshot};
+pub use preedit::PreeditEvent;
 use wasm_bindgen::prelude::*;
 
 /// Main VietFlux IME instance exposed to JavaScript
@@ -53,32 +71,212 @@ impl VietFlux {
         }
     }
 
-    /// Set input method: "telex" or "vni"
+    /// Set input method: "telex", "simple-telex", "telex-w", "vni", "viqr"
+    /// or a loaded custom method
     #[wasm_bindgen]
     pub fn set_method(&mut self, method: &str) {
         self.engine.set_method(method);
     }
 
+    /// Load a custom input method from a JSON definition and switch to it
+    /// Throws with the list of problems if the definition is invalid.
+    #[wasm_bindgen]
+    pub fn load_custom_method(&mut self, json: &str) -> Result<(), JsValue> {
+        self.engine
+            .load_custom_method(json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Add a lexicon of foreign words, one per line, restored from the
+    /// typed keys at the word boundary (`!word` wins over Vietnamese)
+    #[wasm_bindgen]
+    pub fn load_lexicon(&mut self, text: &str) {
+        self.engine.load_lexicon(text);
+    }
+
+    /// Remove every lexicon added with `load_lexicon`
+    #[wasm_bindgen]
+    pub fn clear_lexicons(&mut self) {
+        self.engine.clear_lexicons();
+    }
+
+    /// Check a custom method definition without loading it
+    /// Returns JSON: [{ "rule": 1, "key": "w", "message": "..." }]
+    #[wasm_bindgen]
+    pub fn validate_method_definition(json: &str) -> Result<String, JsValue> {
+        let definition = methods::custom::MethodDefinition::from_json(json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(serde_json::to_string(&definition.validate()).unwrap_or_default())
+    }
+
+    /// Convert text between charsets ("unicode", "tcvn3", "vni-windows",
+    /// "viscii", "vps"); Unicode text is UTF-8
+    #[wasm_bindgen]
+    pub fn convert_charset(text: &[u8], from: &str, to: &str) -> Result<Vec<u8>, JsValue> {
+        let charset = |name: &str| {
+            charset::Charset::from_name(name)
+                .ok_or_else(|| JsValue::from_str(&format!("Unknown charset: {}", name)))
+        };
+        Ok(charset::convert(text, charset(from)?, charset(to)?))
+    }
+
+    /// Remove all diacritics from text (Việt Nam → Viet Nam)
+    #[wasm_bindgen]
+    pub fn strip_diacritics(text: &str) -> String {
+        convert::strip_diacritics(text)
+    }
+
+    /// Convert keystroke text typed with an input method to Vietnamese
+    /// ("Tieesng Vieetj" with "telex" → "Tiếng Việt")
+    #[wasm_bindgen]
+    pub fn keystrokes_to_text(keys: &str, method: &str) -> String {
+        convert::keystrokes_to_text(keys, method)
+    }
+
+    /// List input method names accepted by `set_method`
+    /// Returns JSON: ["telex", "simple-telex", ...]
+    #[wasm_bindgen]
+    pub fn available_methods(&self) -> String {
+        serde_json::to_string(&self.engine.available_methods()).unwrap_or_default()
+    }
+
     /// Get current input method name
     #[wasm_bindgen]
     pub fn get_method(&self) -> String {
         self.engine.get_method().to_string()
     }
 
+    /// Set engine options from JSON, e.g.
+    /// `{ "auto_capitalize": false, "output_encoding": "nfd" }`
+    /// (missing fields keep their current values)
+    /// Returns JSON like `process_key`, describing the edit needed if the
+    /// tone of the word being typed was re-placed.
+    #[wasm_bindgen]
+    pub fn set_options(&mut self, options: &str) -> Result<String, JsValue> {
+        let options = self
+            .engine
+            .get_options()
+            .merge_json(options)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let result = self.engine.set_options(options);
+        Ok(serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    /// Get engine options as JSON
+    #[wasm_bindgen]
+    pub fn get_options(&self) -> String {
+        serde_json::to_string(&self.engine.get_options()).unwrap_or_default()
+    }
+
+    /// Set the undo key (empty string disables it)
+    /// Each press undoes one more transformation of the word being typed.
+    #[wasm_bindgen]
+    pub fn set_undo_key(&mut self, key: &str) {
+        self.engine.set_undo_key(key.chars().next());
+    }
+
+    /// Get the undo key (empty string if disabled)
+    #[wasm_bindgen]
+    pub fn get_undo_key(&self) -> String {
+        self.engine
+            .get_undo_key()
+            .map(String::from)
+            .unwrap_or_default()
+    }
+
+    /// Replace the whole engine configuration from JSON (as returned by
+    /// `get_config`). Throws if the JSON or a custom method is invalid.
+    #[wasm_bindgen]
+    pub fn load_config(&mut self, json: &str) -> Result<(), JsValue> {
+        let config =
+            EngineConfig::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.engine =
+            Engine::from_config(&config).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(())
+    }
+
+    /// Get the whole engine configuration as JSON, for saving
+    #[wasm_bindgen]
+    pub fn get_config(&self) -> String {
+        self.engine.config().to_json()
+    }
+
+    /// Save the word being typed as JSON (e.g. when the text field loses
+    /// focus)
+    #[wasm_bindgen]
+    pub fn snapshot(&self) -> String {
+        self.engine.snapshot().to_json()
+    }
+
+    /// Continue a word saved by `snapshot`. Throws if the JSON is invalid.
+    #[wasm_bindgen]
+    pub fn restore_snapshot(&mut self, json: &str) -> Result<(), JsValue> {
+        let snapshot =
+            EngineSnapshot::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.engine.restore_snapshot(&snapshot);
+        Ok(())
+    }
+
     /// Process a key press and return the result
-    /// Returns JSON: { "action": "commit"|"update"|"passthrough", "output": "...", "backspace": 0 }
+    /// Returns JSON: { "action": "commit"|"update"|"passthrough", "output": "...", "backspace": 0,
+    /// "misspelled": false }
     #[wasm_bindgen]
     pub fn process_key(&mut self, key: char, shift: bool) -> String {
         let result = self.engine.process_key(key, shift);
         serde_json::to_string(&result).unwrap_or_default()
     }
 
+    /// Process a Backspace key press and return the result (same JSON as `process_key`)
+    #[wasm_bindgen]
+    pub fn process_backspace(&mut self) -> String {
+        let result = self.engine.process_backspace();
+        serde_json::to_string(&result).unwrap_or_default()
+    }
+
     /// Clear the input buffer (call on word boundary)
     #[wasm_bindgen]
     pub fn clear(&mut self) {
         self.engine.clear();
     }
 
+    /// Process a key press for composition events (e.g. driving a
+    /// `compositionupdate`-style UI) instead of backspace edits
+    /// Returns JSON: [{ "type": "preedit_update", "text": "việ", "cursor": 3 },
+    /// { "type": "commit", "text": "..." }, { "type": "cancel" }]
+    /// An empty list means the key was not used.
+    #[wasm_bindgen]
+    pub fn process_key_preedit(&mut self, key: char, shift: bool) -> String {
+        serde_json::to_string(&self.engine.process_key_preedit(key, shift)).unwrap_or_default()
+    }
+
+    /// Process a Backspace key press for composition events
+    /// Returns JSON like `process_key_preedit`
+    #[wasm_bindgen]
+    pub fn process_backspace_preedit(&mut self) -> String {
+        serde_json::to_string(&self.engine.process_backspace_preedit()).unwrap_or_default()
+    }
+
+    /// Commit the composition as shown (e.g. on blur)
+    /// Returns JSON like `process_key_preedit`
+    #[wasm_bindgen]
+    pub fn commit_preedit(&mut self) -> String {
+        serde_json::to_string(&self.engine.commit_preedit()).unwrap_or_default()
+    }
+
+    /// Drop the composition (e.g. on Escape)
+    /// Returns JSON like `process_key_preedit`
+    #[wasm_bindgen]
+    pub fn cancel_preedit(&mut self) -> String {
+        serde_json::to_string(&self.engine.cancel_preedit()).unwrap_or_default()
+    }
+
+    /// Text before the caret (e.g. from a textarea), so auto-capitalization
+    /// and smart quotes follow the document
+    #[wasm_bindgen]
+    pub fn set_text_before_cursor(&mut self, text: &str) {
+        self.engine.set_text_before_cursor(text);
+    }
+
     /// Get current buffer content
     #[wasm_bindgen]
     pub fn get_buffer(&self) -> String {
@@ -104,6 +302,77 @@ impl Default for VietFlux {
     }
 }
 
+/// Many text fields typed with one configuration, each with its own word
+/// in progress. Contexts are numbered by the page (e.g. one per text area).
+#[wasm_bindgen]
+pub struct VietFluxContexts {
+    contexts: ContextManager,
+}
+
+#[wasm_bindgen]
+impl VietFluxContexts {
+    /// Create contexts with the default configuration
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            contexts: ContextManager::new(EngineConfig::default())
+                .expect("default configuration is valid"),
+        }
+    }
+
+    /// Replace the configuration of all contexts from JSON (as returned by
+    /// `VietFlux.get_config`). Throws if the JSON or a custom method is
+    /// invalid.
+    #[wasm_bindgen]
+    pub fn load_config(&mut self, json: &str) -> Result<(), JsValue> {
+        let config =
+            EngineConfig::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.contexts
+            .set_config(config)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Get the shared configuration as JSON
+    #[wasm_bindgen]
+    pub fn get_config(&self) -> String {
+        self.contexts.config().to_json()
+    }
+
+    /// Process a key press in a context
+    /// Returns JSON like `VietFlux.process_key`
+    #[wasm_bindgen]
+    pub fn process_key(&mut self, context: u32, key: char, shift: bool) -> String {
+        let result = self.contexts.process_key(context.into(), key, shift);
+        serde_json::to_string(&result).unwrap_or_default()
+    }
+
+    /// Process a Backspace key press in a context
+    /// Returns JSON like `VietFlux.process_key`
+    #[wasm_bindgen]
+    pub fn process_backspace(&mut self, context: u32) -> String {
+        let result = self.contexts.process_backspace(context.into());
+        serde_json::to_string(&result).unwrap_or_default()
+    }
+
+    /// Get the word being typed in a context
+    #[wasm_bindgen]
+    pub fn get_buffer(&self, context: u32) -> String {
+        self.contexts.get_buffer(context.into())
+    }
+
+    /// Forget a context (text area removed or caret moved)
+    #[wasm_bindgen]
+    pub fn remove_context(&mut self, context: u32) {
+        self.contexts.remove(context.into());
+    }
+}
+
+impl Default for VietFluxContexts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Initialize panic hook for better error messages in WASM
 #[wasm_bindgen(start)]
 pub fn init() {
@@ -0,0 +1,415 @@
+//! Custom Input Methods
+//!
+//! Input methods built from a JSON definition instead of a hard-coded key
+//! table, so teams can ship their own layouts without recompiling:
+//!
+//! ```json
+//! {
+//!   "name": "my-telex",
+//!   "rules": [
+//!     { "key": "s", "action": { "tone": "acute" } },
+//!     { "key": "a", "after": "a", "action": { "modifier": "circumflex" } },
+//!     { "key": "w", "after_letter": true, "action": { "modifier": "horn" } },
+//!     { "key": "d", "after": "d", "action": "stroke" },
+//!     { "key": "n", "after": "n", "action": { "quick_telex": "nh" } },
+//!     { "key": "[", "action": { "insert_char": "ư" } }
+//!   ]
+//! }
+//! ```
+//!
+//! Rules are tried in order and the first match wins. Keys match case
+//! insensitively. `after` limits a rule to a previous character whose base
+//! letter is listed ("a" also matches â, ă, á...), `after_letter` to any
+//! previous letter.
+
+use super::{InputMethod, KeyAction};
+use crate::chars::{self, ToneMark, VowelMod};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Action a rule produces (mirrors `KeyAction`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Tone(ToneMark),
+    Modifier(VowelMod),
+    Stroke,
+    RemoveDiacritics,
+    Undo,
+    QuickTelex(String),
+    InsertChar(char),
+}
+
+impl RuleAction {
+    fn to_key_action(&self) -> KeyAction {
+        match self {
+            Self::Tone(tone) => KeyAction::Tone(*tone),
+            Self::Modifier(modifier) => KeyAction::Modifier(*modifier),
+            Self::Stroke => KeyAction::Stroke,
+            Self::RemoveDiacritics => KeyAction::RemoveDiacritics,
+            Self::Undo => KeyAction::Undo,
+            Self::QuickTelex(replacement) => KeyAction::QuickTelex(replacement.clone().into()),
+            Self::InsertChar(ch) => KeyAction::InsertChar(*ch),
+        }
+    }
+}
+
+/// One key mapping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Key that triggers the rule
+    pub key: char,
+    /// Only after a character with one of these base letters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    /// Only after any letter
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub after_letter: bool,
+    /// What the key does
+    pub action: RuleAction,
+}
+
+impl Rule {
+    fn context(&self) -> Context {
+        match &self.after {
+            Some(after) => Context::Chars(after.chars().map(chars::to_lower).collect()),
+            None if self.after_letter => Context::Letter,
+            None => Context::Any,
+        }
+    }
+
+    fn matches(&self, key: char, prev_char: Option<char>) -> bool {
+        if chars::to_lower(self.key) != chars::to_lower(key) {
+            return false;
+        }
+        match self.context() {
+            Context::Any => true,
+            Context::Letter => prev_char.is_some_and(|prev| prev.is_alphabetic()),
+            Context::Chars(after) => prev_char
+                .is_some_and(|prev| after.contains(&chars::get_base(chars::to_lower(prev)))),
+        }
+    }
+}
+
+/// Previous-character condition of a rule
+enum Context {
+    Any,
+    Letter,
+    Chars(Vec<char>),
+}
+
+impl Context {
+    /// Previous characters for which an earlier rule with context `self`
+    /// takes over a later rule with context `other`. None if it takes over
+    /// all of them. A narrower context before a wider one is the usual
+    /// specific-then-fallback layering and shares nothing.
+    fn overlap(&self, other: &Context) -> Option<Vec<char>> {
+        match (self, other) {
+            (Context::Any, _) => None,
+            (Context::Letter, Context::Letter) => None,
+            (Context::Letter, Context::Chars(b)) if b.iter().all(|c| c.is_alphabetic()) => None,
+            (Context::Letter, Context::Chars(b)) => {
+                Some(b.iter().copied().filter(|c| c.is_alphabetic()).collect())
+            }
+            (Context::Chars(a), Context::Chars(b)) if b.iter().all(|c| a.contains(c)) => None,
+            (Context::Chars(a), Context::Chars(b)) => {
+                Some(b.iter().copied().filter(|c| a.contains(c)).collect())
+            }
+            (Context::Chars(_) | Context::Letter, _) => Some(Vec::new()),
+        }
+    }
+}
+
+/// Problem found in a definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// Index of the offending rule
+    pub rule: usize,
+    /// Key of the offending rule
+    pub key: char,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rule {} ('{}'): {}", self.rule, self.key, self.message)
+    }
+}
+
+/// Error loading a definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionError {
+    /// Not valid JSON or not a method definition
+    Parse(String),
+    /// Name is empty or taken by a built-in method
+    InvalidName(String),
+    /// Rules that conflict
+    Conflicts(Vec<Conflict>),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "invalid method definition: {}", err),
+            Self::InvalidName(name) => write!(f, "invalid method name: {:?}", name),
+            Self::Conflicts(conflicts) => {
+                write!(f, "conflicting rules:")?;
+                for conflict in conflicts {
+                    write!(f, "\n  {}", conflict)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for DefinitionError {}
+
+/// Declarative input method definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MethodDefinition {
+    /// Method name used with `set_method`
+    pub name: String,
+    /// Key rules, first match wins
+    pub rules: Vec<Rule>,
+}
+
+impl MethodDefinition {
+    /// Parse a definition from JSON (without validating it)
+    pub fn from_json(json: &str) -> Result<Self, DefinitionError> {
+        serde_json::from_str(json).map_err(|e| DefinitionError::Parse(e.to_string()))
+    }
+
+    /// Report rules that can never fire, overlap with a different action,
+    /// or do nothing
+    pub fn validate(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        let mut report = |rule: usize, key: char, message: String| {
+            conflicts.push(Conflict { rule, key, message })
+        };
+
+        for (j, rule) in self.rules.iter().enumerate() {
+            match &rule.action {
+                RuleAction::Tone(ToneMark::None) => report(j, rule.key, "tone is none".into()),
+                RuleAction::Modifier(VowelMod::None) => {
+                    report(j, rule.key, "modifier is none".into())
+                }
+                RuleAction::QuickTelex(r) if r.is_empty() => {
+                    report(j, rule.key, "empty quick_telex replacement".into())
+                }
+                _ => {}
+            }
+            if rule.after.is_some() && rule.after_letter {
+                report(j, rule.key, "both after and after_letter are set".into());
+            }
+            if rule.after.as_deref() == Some("") {
+                report(j, rule.key, "after is empty, the rule never fires".into());
+            }
+
+            let context = rule.context();
+            for (i, earlier) in self.rules[..j].iter().enumerate() {
+                if chars::to_lower(earlier.key) != chars::to_lower(rule.key) {
+                    continue;
+                }
+                match earlier.context().overlap(&context) {
+                    None if earlier.action == rule.action => {
+                        report(j, rule.key, format!("duplicate of rule {}", i));
+                        break;
+                    }
+                    None => {
+                        report(
+                            j,
+                            rule.key,
+                            format!("never fires, rule {} matches first", i),
+                        );
+                        break;
+                    }
+                    Some(shared) if !shared.is_empty() && earlier.action != rule.action => {
+                        let shared: String = shared.into_iter().collect();
+                        report(
+                            j,
+                            rule.key,
+                            format!("after \"{}\" rule {} matches first", shared, i),
+                        );
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+/// Input method driven by a `MethodDefinition`
+#[derive(Debug, Clone)]
+pub struct CustomMethod {
+    definition: MethodDefinition,
+    /// Key that undoes the last transformation
+    undo_key: Option<char>,
+}
+
+impl CustomMethod {
+    /// Build a method from a definition, rejecting conflicting rules
+    pub fn new(mut definition: MethodDefinition) -> Result<Self, DefinitionError> {
+        definition.name = definition.name.trim().to_lowercase();
+        if definition.name.is_empty() || super::BUILTIN_METHODS.contains(&&*definition.name) {
+            return Err(DefinitionError::InvalidName(definition.name));
+        }
+
+        let conflicts = definition.validate();
+        if !conflicts.is_empty() {
+            return Err(DefinitionError::Conflicts(conflicts));
+        }
+
+        Ok(Self {
+            definition,
+            undo_key: None,
+        })
+    }
+
+    /// Parse and build a method from a JSON definition
+    pub fn from_json(json: &str) -> Result<Self, DefinitionError> {
+        Self::new(MethodDefinition::from_json(json)?)
+    }
+
+    /// Get the definition
+    pub fn definition(&self) -> &MethodDefinition {
+        &self.definition
+    }
+}
+
+impl InputMethod for CustomMethod {
+    fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    fn process(&self, key: char, prev_char: Option<char>) -> KeyAction {
+        if self.undo_key == Some(chars::to_lower(key)) {
+            return KeyAction::Undo;
+        }
+
+        self.definition
+            .rules
+            .iter()
+            .find(|rule| rule.matches(key, prev_char))
+            .map_or(KeyAction::None, |rule| rule.action.to_key_action())
+    }
+
+    fn is_modifier_key(&self, key: char) -> bool {
+        self.undo_key == Some(chars::to_lower(key))
+            || self.definition.rules.iter().any(|rule| {
+                chars::to_lower(rule.key) == chars::to_lower(key)
+                    && matches!(rule.context(), Context::Any)
+                    && matches!(
+                        rule.action,
+                        RuleAction::Tone(_) | RuleAction::RemoveDiacritics | RuleAction::Undo
+                    )
+            })
+    }
+
+    fn set_undo_key(&mut self, key: Option<char>) {
+        self.undo_key = key.map(chars::to_lower);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"{
+        "name": "My-Telex",
+        "rules": [
+            { "key": "s", "action": { "tone": "acute" } },
+            { "key": "a", "after": "a", "action": { "modifier": "circumflex" } },
+            { "key": "w", "after": "a", "action": { "modifier": "breve" } },
+            { "key": "w", "after_letter": true, "action": { "modifier": "horn" } },
+            { "key": "d", "after": "d", "action": "stroke" },
+            { "key": "n", "after": "n", "action": { "quick_telex": "nh" } },
+            { "key": "[", "action": { "insert_char": "ư" } }
+        ]
+    }"#;
+
+    #[test]
+    fn test_custom_method_rules() {
+        let method = CustomMethod::from_json(DEFINITION).unwrap();
+        assert_eq!(method.name(), "my-telex");
+        assert_eq!(method.process('S', None), KeyAction::Tone(ToneMark::Acute));
+        assert_eq!(
+            method.process('a', Some('â')),
+            KeyAction::Modifier(VowelMod::Circumflex)
+        );
+        assert_eq!(method.process('a', Some('b')), KeyAction::None);
+        assert_eq!(
+            method.process('w', Some('a')),
+            KeyAction::Modifier(VowelMod::Breve)
+        );
+        assert_eq!(
+            method.process('w', Some('t')),
+            KeyAction::Modifier(VowelMod::Horn)
+        );
+        assert_eq!(method.process('w', None), KeyAction::None);
+        assert_eq!(method.process('d', Some('D')), KeyAction::Stroke);
+        assert_eq!(
+            method.process('n', Some('n')),
+            KeyAction::QuickTelex("nh".into())
+        );
+        assert_eq!(method.process('[', None), KeyAction::InsertChar('ư'));
+        assert!(method.is_modifier_key('s'));
+        assert!(!method.is_modifier_key('a'));
+    }
+
+    #[test]
+    fn test_undo_key_ignores_case() {
+        let mut method = CustomMethod::from_json(DEFINITION).unwrap();
+        method.set_undo_key(Some('Q'));
+        assert_eq!(method.process('q', None), KeyAction::Undo);
+        assert_eq!(method.process('Q', None), KeyAction::Undo);
+        assert!(method.is_modifier_key('q'));
+        assert!(method.is_modifier_key('Q'));
+    }
+
+    #[test]
+    fn test_validator_reports_conflicts() {
+        let definition = MethodDefinition::from_json(
+            r#"{
+                "name": "broken",
+                "rules": [
+                    { "key": "w", "after_letter": true, "action": { "modifier": "horn" } },
+                    { "key": "w", "after": "a", "action": { "modifier": "breve" } },
+                    { "key": "s", "action": { "tone": "acute" } },
+                    { "key": "s", "action": { "tone": "acute" } },
+                    { "key": "6", "after": "a1", "action": { "modifier": "circumflex" } },
+                    { "key": "6", "after": "e1", "action": { "modifier": "horn" } },
+                    { "key": "x", "action": { "tone": "none" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let conflicts = definition.validate();
+        let rules: Vec<usize> = conflicts.iter().map(|c| c.rule).collect();
+        assert_eq!(rules, vec![1, 3, 5, 6]);
+        assert_eq!(conflicts[0].message, "never fires, rule 0 matches first");
+        assert_eq!(conflicts[1].message, "duplicate of rule 2");
+        assert_eq!(conflicts[2].message, "after \"1\" rule 4 matches first");
+
+        assert!(matches!(
+            CustomMethod::new(definition),
+            Err(DefinitionError::Conflicts(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        assert!(matches!(
+            CustomMethod::from_json("{ \"name\": \"x\" }"),
+            Err(DefinitionError::Parse(_))
+        ));
+        assert_eq!(
+            CustomMethod::from_json(r#"{ "name": "VNI", "rules": [] }"#).unwrap_err(),
+            DefinitionError::InvalidName("vni".to_string())
+        );
+    }
+}
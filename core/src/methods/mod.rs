@@ -1,14 +1,23 @@
 //! Input Methods Module
 //!
-//! Defines Telex and VNI input method implementations.
+//! Defines Telex, VNI and VIQR input method implementations, plus custom
+//! methods loaded from a definition.
 
+pub mod custom;
 pub mod telex;
+pub mod viqr;
 pub mod vni;
 
+pub use custom::CustomMethod;
 pub use telex::Telex;
+pub use viqr::Viqr;
 pub use vni::Vni;
 
 use crate::chars::{ToneMark, VowelMod};
+use std::borrow::Cow;
+
+/// Names of the built-in input methods
+pub const BUILTIN_METHODS: &[&str] = &["telex", "simple-telex", "telex-w", "vni", "viqr"];
 
 /// Key action result from input method
 #[derive(Debug, Clone, PartialEq)]
@@ -26,27 +35,38 @@ pub enum KeyAction {
     /// Undo last transformation
     Undo,
     /// Quick Telex: expand double consonant (cc→ch, gg→gh, nn→nh, etc.)
-    QuickTelex(&'static str),
+    QuickTelex(Cow<'static, str>),
     /// Insert a specific character directly (for quick shortcuts like [ → ư, ] → ơ)
     InsertChar(char),
+    /// Keep an escape character in the word instead of ending it (VIQR \)
+    Escape,
+    /// Type the key literally in place of the escape character before it
+    /// (VIQR \. → .); not a transform, so undo and restore leave it alone
+    Escaped,
 }
 
 /// Input method trait
 pub trait InputMethod: Send + Sync {
     /// Get method name
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     /// Process a key and return the action
     fn process(&self, key: char, prev_char: Option<char>) -> KeyAction;
 
     /// Check if key is a modifier key (not a regular character)
     fn is_modifier_key(&self, key: char) -> bool;
+
+    /// Set the key that undoes the last transformation (None disables it)
+    fn set_undo_key(&mut self, key: Option<char>);
 }
 
 /// Get input method by name
 pub fn get_method(name: &str) -> Box<dyn InputMethod> {
     match name.to_lowercase().as_str() {
-        "vni" => Box::new(Vni),
-        _ => Box::new(Telex),
+        "simple-telex" => Box::new(Telex::simple()),
+        "telex-w" => Box::new(Telex::with_standalone_w()),
+        "vni" => Box::new(Vni::default()),
+        "viqr" => Box::new(Viqr::default()),
+        _ => Box::new(Telex::default()),
     }
 }
@@ -5,21 +5,82 @@
 //! - Vowel modifiers: aa=â, ee=ê, oo=ô, aw=ă, ow=ơ, uw=ư
 //! - Consonant: dd=đ
 //! - Remove: z
+//! - Undo: configurable key (off by default)
+//!
+//! Variants:
+//! - `telex`: w also reaches back to earlier vowels (tuoiw), [ → ư, ] → ơ
+//! - `simple-telex`: w only right after a vowel, no bracket shortcuts
+//! - `telex-w`: like `telex`, plus w on its own types ư (UniKey style)
 
 use super::{InputMethod, KeyAction};
-use crate::chars::{ToneMark, VowelMod};
+use crate::chars::{self, ToneMark, VowelMod};
 
 /// Telex input method
-pub struct Telex;
+#[derive(Debug)]
+pub struct Telex {
+    /// Variant name
+    name: &'static str,
+    /// [ → ư, ] → ơ
+    bracket_shortcuts: bool,
+    /// w may apply to an earlier vowel (tuoiw → tươi)
+    retroactive_w: bool,
+    /// w not after a vowel types ư
+    standalone_w: bool,
+    /// Key that undoes the last transformation
+    undo_key: Option<char>,
+}
+
+impl Telex {
+    /// Standard Telex
+    pub fn new() -> Self {
+        Self {
+            name: "telex",
+            bracket_shortcuts: true,
+            retroactive_w: true,
+            standalone_w: false,
+            undo_key: None,
+        }
+    }
+
+    /// Telex for mixed code/English text: no bracket shortcuts and w only
+    /// modifies the vowel right before it
+    pub fn simple() -> Self {
+        Self {
+            name: "simple-telex",
+            bracket_shortcuts: false,
+            retroactive_w: false,
+            ..Self::new()
+        }
+    }
+
+    /// Telex where w on its own types ư (w → ư, tw → tư)
+    pub fn with_standalone_w() -> Self {
+        Self {
+            name: "telex-w",
+            standalone_w: true,
+            ..Self::new()
+        }
+    }
+}
+
+impl Default for Telex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl InputMethod for Telex {
-    fn name(&self) -> &'static str {
-        "telex"
+    fn name(&self) -> &str {
+        self.name
     }
 
     fn process(&self, key: char, prev_char: Option<char>) -> KeyAction {
         let key_lower = key.to_ascii_lowercase();
 
+        if self.undo_key == Some(key_lower) {
+            return KeyAction::Undo;
+        }
+
         match key_lower {
             // Tone marks
             's' => KeyAction::Tone(ToneMark::Acute), // sắc
@@ -29,26 +90,41 @@ impl InputMethod for Telex {
             'j' => KeyAction::Tone(ToneMark::Dot),   // nặng
 
             // Vowel modifiers (double key = circumflex)
-            'a' | 'e' | 'o' => {
-                if let Some(prev) = prev_char {
-                    if prev.to_ascii_lowercase() == key_lower {
-                        return KeyAction::Modifier(VowelMod::Circumflex);
+            // After any letter the key may reach back into the word
+            // (vietej → việt); the engine decides whether a vowel takes it.
+            'a' | 'e' | 'o' => match prev_char {
+                Some(prev) if prev.is_alphabetic() => KeyAction::Modifier(VowelMod::Circumflex),
+                _ => KeyAction::None,
+            },
+
+            // w = horn (ơ, ư) or breve (ă), also for earlier vowels (tuoiw)
+            'w' => {
+                let prev_base = prev_char.map(|prev| chars::get_base(chars::to_lower(prev)));
+                match prev_char {
+                    Some(_) if prev_base == Some('a') => KeyAction::Modifier(VowelMod::Breve), // aw = ă
+                    Some(prev)
+                        if chars::is_vowel(prev)
+                            && (self.retroactive_w || matches!(prev_base, Some('o' | 'u'))) =>
+                    {
+                        KeyAction::Modifier(VowelMod::Horn) // ow=ơ, uw=ư
+                    }
+                    Some(prev)
+                        if self.retroactive_w && !self.standalone_w && prev.is_alphabetic() =>
+                    {
+                        KeyAction::Modifier(VowelMod::Horn) // tuoiw: engine finds the vowel
                     }
+                    _ if self.standalone_w => {
+                        KeyAction::InsertChar(if key.is_uppercase() { 'Ư' } else { 'ư' })
+                    }
+                    _ => KeyAction::None,
                 }
-                KeyAction::None
             }
 
-            // w = horn (ơ, ư) or breve (ă)
-            'w' => prev_char.map_or(KeyAction::None, |prev| match prev.to_ascii_lowercase() {
-                'a' => KeyAction::Modifier(VowelMod::Breve), // aw = ă
-                'o' | 'u' => KeyAction::Modifier(VowelMod::Horn), // ow=ơ, uw=ư
-                _ => KeyAction::None,
-            }),
-
             // dd = đ
             'd' => {
                 if let Some(prev) = prev_char {
-                    if prev.eq_ignore_ascii_case(&'d') {
+                    // After đ the same key undoes the stroke
+                    if matches!(prev, 'd' | 'D' | 'đ' | 'Đ') {
                         return KeyAction::Stroke;
                     }
                 }
@@ -73,7 +149,7 @@ impl InputMethod for Telex {
                             'q' => "qu",
                             _ => unreachable!(),
                         };
-                        return KeyAction::QuickTelex(replacement);
+                        return KeyAction::QuickTelex(replacement.into());
                     }
                 }
                 KeyAction::None
@@ -81,8 +157,8 @@ impl InputMethod for Telex {
 
             // Quick key shortcuts (OpenKey style)
             // [ → ư, ] → ơ for faster typing without needing uw/ow
-            '[' => KeyAction::InsertChar('ư'),
-            ']' => KeyAction::InsertChar('ơ'),
+            '[' if self.bracket_shortcuts => KeyAction::InsertChar('ư'),
+            ']' if self.bracket_shortcuts => KeyAction::InsertChar('ơ'),
 
             _ => KeyAction::None,
         }
@@ -90,6 +166,11 @@ impl InputMethod for Telex {
 
     fn is_modifier_key(&self, key: char) -> bool {
         matches!(key.to_ascii_lowercase(), 's' | 'f' | 'r' | 'x' | 'j' | 'z')
+            || self.undo_key == Some(key.to_ascii_lowercase())
+    }
+
+    fn set_undo_key(&mut self, key: Option<char>) {
+        self.undo_key = key.map(|k| k.to_ascii_lowercase());
     }
 }
 
@@ -99,7 +180,7 @@ mod tests {
 
     #[test]
     fn test_telex_tones() {
-        let telex = Telex;
+        let telex = Telex::default();
         assert_eq!(telex.process('s', None), KeyAction::Tone(ToneMark::Acute));
         assert_eq!(telex.process('f', None), KeyAction::Tone(ToneMark::Grave));
         assert_eq!(telex.process('r', None), KeyAction::Tone(ToneMark::Hook));
@@ -109,7 +190,7 @@ mod tests {
 
     #[test]
     fn test_telex_circumflex() {
-        let telex = Telex;
+        let telex = Telex::default();
         assert_eq!(
             telex.process('a', Some('a')),
             KeyAction::Modifier(VowelMod::Circumflex)
@@ -126,7 +207,7 @@ mod tests {
 
     #[test]
     fn test_telex_horn_breve() {
-        let telex = Telex;
+        let telex = Telex::default();
         assert_eq!(
             telex.process('w', Some('a')),
             KeyAction::Modifier(VowelMod::Breve)
@@ -141,37 +222,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_telex_retroactive_keys() {
+        let telex = Telex::default();
+        // Modifier keys after other letters are resolved against the word
+        assert_eq!(
+            telex.process('e', Some('t')),
+            KeyAction::Modifier(VowelMod::Circumflex)
+        );
+        assert_eq!(
+            telex.process('w', Some('i')),
+            KeyAction::Modifier(VowelMod::Horn)
+        );
+        assert_eq!(
+            telex.process('w', Some('ă')),
+            KeyAction::Modifier(VowelMod::Breve)
+        );
+        assert_eq!(telex.process('a', None), KeyAction::None);
+        assert_eq!(telex.process('w', None), KeyAction::None);
+    }
+
     #[test]
     fn test_telex_stroke() {
-        let telex = Telex;
+        let telex = Telex::default();
         assert_eq!(telex.process('d', Some('d')), KeyAction::Stroke);
+        assert_eq!(telex.process('d', Some('đ')), KeyAction::Stroke);
     }
 
     #[test]
     fn test_telex_quick_telex() {
-        let telex = Telex;
+        let telex = Telex::default();
         // cc → ch
-        assert_eq!(telex.process('c', Some('c')), KeyAction::QuickTelex("ch"));
+        assert_eq!(
+            telex.process('c', Some('c')),
+            KeyAction::QuickTelex("ch".into())
+        );
         // gg → gh
-        assert_eq!(telex.process('g', Some('g')), KeyAction::QuickTelex("gh"));
+        assert_eq!(
+            telex.process('g', Some('g')),
+            KeyAction::QuickTelex("gh".into())
+        );
         // nn → nh
-        assert_eq!(telex.process('n', Some('n')), KeyAction::QuickTelex("nh"));
+        assert_eq!(
+            telex.process('n', Some('n')),
+            KeyAction::QuickTelex("nh".into())
+        );
         // pp → ph
-        assert_eq!(telex.process('p', Some('p')), KeyAction::QuickTelex("ph"));
+        assert_eq!(
+            telex.process('p', Some('p')),
+            KeyAction::QuickTelex("ph".into())
+        );
         // tt → th
-        assert_eq!(telex.process('t', Some('t')), KeyAction::QuickTelex("th"));
+        assert_eq!(
+            telex.process('t', Some('t')),
+            KeyAction::QuickTelex("th".into())
+        );
         // qq → qu
-        assert_eq!(telex.process('q', Some('q')), KeyAction::QuickTelex("qu"));
+        assert_eq!(
+            telex.process('q', Some('q')),
+            KeyAction::QuickTelex("qu".into())
+        );
         // kk → kh
-        assert_eq!(telex.process('k', Some('k')), KeyAction::QuickTelex("kh"));
+        assert_eq!(
+            telex.process('k', Some('k')),
+            KeyAction::QuickTelex("kh".into())
+        );
     }
 
     #[test]
     fn test_telex_bracket_shortcuts() {
-        let telex = Telex;
+        let telex = Telex::default();
         // [ → ư (quick shortcut from OpenKey)
         assert_eq!(telex.process('[', None), KeyAction::InsertChar('ư'));
         // ] → ơ (quick shortcut from OpenKey)
         assert_eq!(telex.process(']', None), KeyAction::InsertChar('ơ'));
     }
+
+    #[test]
+    fn test_telex_undo_key() {
+        let mut telex = Telex::default();
+        assert_eq!(telex.process('`', Some('a')), KeyAction::None);
+
+        telex.set_undo_key(Some('`'));
+        assert_eq!(telex.process('`', Some('a')), KeyAction::Undo);
+        assert!(telex.is_modifier_key('`'));
+    }
+
+    #[test]
+    fn test_simple_telex() {
+        let telex = Telex::simple();
+        assert_eq!(telex.name(), "simple-telex");
+        assert_eq!(telex.process('[', None), KeyAction::None);
+        assert_eq!(telex.process(']', Some('a')), KeyAction::None);
+        assert_eq!(
+            telex.process('w', Some('o')),
+            KeyAction::Modifier(VowelMod::Horn)
+        );
+        // No reaching back over other letters
+        assert_eq!(telex.process('w', Some('i')), KeyAction::None);
+        assert_eq!(telex.process('w', Some('t')), KeyAction::None);
+    }
+
+    #[test]
+    fn test_telex_standalone_w() {
+        let telex = Telex::with_standalone_w();
+        assert_eq!(telex.name(), "telex-w");
+        assert_eq!(telex.process('w', None), KeyAction::InsertChar('ư'));
+        assert_eq!(telex.process('W', Some('T')), KeyAction::InsertChar('Ư'));
+        assert_eq!(
+            telex.process('w', Some('i')),
+            KeyAction::Modifier(VowelMod::Horn)
+        );
+        assert_eq!(
+            telex.process('w', Some('a')),
+            KeyAction::Modifier(VowelMod::Breve)
+        );
+    }
 }
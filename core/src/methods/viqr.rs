@@ -0,0 +1,142 @@
+//! VIQR Input Method
+//!
+//! Key mappings based on the VIQR convention (RFC 1456):
+//! - Tone marks: '=sắc, `=huyền, ?=hỏi, ~=ngã, .=nặng
+//! - Vowel modifiers: ^=circumflex, (=breve, +=horn
+//! - Consonant: dd=đ
+//! - Escape: \ before a mark key types it literally (ta\. → ta.)
+//!
+//! Mark keys are also punctuation, so they only count as marks right
+//! after a vowel they can apply to; elsewhere they stay word boundaries.
+
+use super::{InputMethod, KeyAction};
+use crate::chars::{self, ToneMark, VowelMod};
+
+/// VIQR input method
+#[derive(Debug, Default)]
+pub struct Viqr {
+    /// Key that undoes the last transformation
+    undo_key: Option<char>,
+}
+
+/// Keys that VIQR uses as marks
+fn is_mark_key(key: char) -> bool {
+    matches!(key, '\'' | '`' | '?' | '~' | '.' | '^' | '(' | '+')
+}
+
+impl InputMethod for Viqr {
+    fn name(&self) -> &str {
+        "viqr"
+    }
+
+    fn process(&self, key: char, prev_char: Option<char>) -> KeyAction {
+        if self.undo_key == Some(key) {
+            return KeyAction::Undo;
+        }
+
+        let Some(prev) = prev_char else {
+            return KeyAction::None;
+        };
+
+        // Escaped mark key replaces the backslash
+        if prev == '\\' && is_mark_key(key) {
+            return KeyAction::Escaped;
+        }
+
+        let prev_base = chars::get_base(chars::to_lower(prev));
+        let after_vowel = chars::is_vowel(prev);
+
+        match key {
+            // Tone marks
+            '\'' if after_vowel => KeyAction::Tone(ToneMark::Acute), // sắc
+            '`' if after_vowel => KeyAction::Tone(ToneMark::Grave),  // huyền
+            '?' if after_vowel => KeyAction::Tone(ToneMark::Hook),   // hỏi
+            '~' if after_vowel => KeyAction::Tone(ToneMark::Tilde),  // ngã
+            '.' if after_vowel => KeyAction::Tone(ToneMark::Dot),    // nặng
+
+            // Vowel modifiers
+            '^' if matches!(prev_base, 'a' | 'e' | 'o') => {
+                KeyAction::Modifier(VowelMod::Circumflex) // â, ê, ô
+            }
+            '(' if prev_base == 'a' => KeyAction::Modifier(VowelMod::Breve), // ă
+            '+' if matches!(prev_base, 'o' | 'u') => KeyAction::Modifier(VowelMod::Horn), // ơ, ư
+
+            // dd = đ
+            'd' | 'D' if prev.eq_ignore_ascii_case(&'d') => KeyAction::Stroke,
+
+            // Escape inside a word
+            '\\' => KeyAction::Escape,
+
+            _ => KeyAction::None,
+        }
+    }
+
+    fn is_modifier_key(&self, key: char) -> bool {
+        is_mark_key(key) || self.undo_key == Some(key)
+    }
+
+    fn set_undo_key(&mut self, key: Option<char>) {
+        self.undo_key = key;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viqr_tones() {
+        let viqr = Viqr::default();
+        assert_eq!(
+            viqr.process('\'', Some('a')),
+            KeyAction::Tone(ToneMark::Acute)
+        );
+        assert_eq!(
+            viqr.process('`', Some('a')),
+            KeyAction::Tone(ToneMark::Grave)
+        );
+        assert_eq!(
+            viqr.process('?', Some('a')),
+            KeyAction::Tone(ToneMark::Hook)
+        );
+        assert_eq!(
+            viqr.process('~', Some('a')),
+            KeyAction::Tone(ToneMark::Tilde)
+        );
+        assert_eq!(viqr.process('.', Some('ê')), KeyAction::Tone(ToneMark::Dot));
+    }
+
+    #[test]
+    fn test_viqr_marks_need_a_vowel() {
+        let viqr = Viqr::default();
+        assert_eq!(viqr.process('.', Some('m')), KeyAction::None);
+        assert_eq!(viqr.process('?', None), KeyAction::None);
+        assert_eq!(viqr.process('^', Some('u')), KeyAction::None);
+        assert_eq!(viqr.process('(', Some('o')), KeyAction::None);
+    }
+
+    #[test]
+    fn test_viqr_modifiers() {
+        let viqr = Viqr::default();
+        assert_eq!(
+            viqr.process('^', Some('e')),
+            KeyAction::Modifier(VowelMod::Circumflex)
+        );
+        assert_eq!(
+            viqr.process('(', Some('A')),
+            KeyAction::Modifier(VowelMod::Breve)
+        );
+        assert_eq!(
+            viqr.process('+', Some('o')),
+            KeyAction::Modifier(VowelMod::Horn)
+        );
+        assert_eq!(viqr.process('d', Some('d')), KeyAction::Stroke);
+    }
+
+    #[test]
+    fn test_viqr_escape() {
+        let viqr = Viqr::default();
+        assert_eq!(viqr.process('\\', Some('a')), KeyAction::Escape);
+        assert_eq!(viqr.process('.', Some('\\')), KeyAction::Escaped);
+    }
+}
@@ -5,19 +5,28 @@
 //! - Vowel modifiers: 6=circumflex, 7=horn, 8=breve
 //! - Consonant: 9=đ (after d)
 //! - Remove: 0
+//! - Undo: configurable key (off by default)
 
 use super::{InputMethod, KeyAction};
 use crate::chars::{ToneMark, VowelMod};
 
 /// VNI input method
-pub struct Vni;
+#[derive(Debug, Default)]
+pub struct Vni {
+    /// Key that undoes the last transformation
+    undo_key: Option<char>,
+}
 
 impl InputMethod for Vni {
-    fn name(&self) -> &'static str {
+    fn name(&self) -> &str {
         "vni"
     }
 
     fn process(&self, key: char, prev_char: Option<char>) -> KeyAction {
+        if self.undo_key == Some(key) {
+            return KeyAction::Undo;
+        }
+
         match key {
             // Tone marks
             '1' => KeyAction::Tone(ToneMark::Acute), // sắc
@@ -34,7 +43,8 @@ impl InputMethod for Vni {
             // Stroke (đ)
             '9' => {
                 if let Some(prev) = prev_char {
-                    if prev.eq_ignore_ascii_case(&'d') {
+                    // After đ the same key undoes the stroke
+                    if matches!(prev, 'd' | 'D' | 'đ' | 'Đ') {
                         return KeyAction::Stroke;
                     }
                 }
@@ -49,7 +59,11 @@ impl InputMethod for Vni {
     }
 
     fn is_modifier_key(&self, key: char) -> bool {
-        matches!(key, '1'..='9' | '0')
+        matches!(key, '1'..='9' | '0') || self.undo_key == Some(key)
+    }
+
+    fn set_undo_key(&mut self, key: Option<char>) {
+        self.undo_key = key;
     }
 }
 
@@ -59,7 +73,7 @@ mod tests {
 
     #[test]
     fn test_vni_tones() {
-        let vni = Vni;
+        let vni = Vni::default();
         assert_eq!(vni.process('1', None), KeyAction::Tone(ToneMark::Acute));
         assert_eq!(vni.process('2', None), KeyAction::Tone(ToneMark::Grave));
         assert_eq!(vni.process('3', None), KeyAction::Tone(ToneMark::Hook));
@@ -69,7 +83,7 @@ mod tests {
 
     #[test]
     fn test_vni_modifiers() {
-        let vni = Vni;
+        let vni = Vni::default();
         assert_eq!(
             vni.process('6', None),
             KeyAction::Modifier(VowelMod::Circumflex)
@@ -80,8 +94,20 @@ mod tests {
 
     #[test]
     fn test_vni_stroke() {
-        let vni = Vni;
+        let vni = Vni::default();
         assert_eq!(vni.process('9', Some('d')), KeyAction::Stroke);
+        assert_eq!(vni.process('9', Some('Đ')), KeyAction::Stroke);
         assert_eq!(vni.process('9', Some('a')), KeyAction::None);
     }
+
+    #[test]
+    fn test_vni_undo_key() {
+        let mut vni = Vni::default();
+        vni.set_undo_key(Some('`'));
+        assert_eq!(vni.process('`', Some('a')), KeyAction::Undo);
+        assert_eq!(
+            vni.process('1', Some('a')),
+            KeyAction::Tone(ToneMark::Acute)
+        );
+    }
 }
@@ -0,0 +1,150 @@
+//! Preedit (Composition) Events
+//!
+//! `ProcessResult` edits text the host has already inserted: delete
+//! `backspace` characters, then type `output`. IME frameworks (IBus,
+//! Fcitx, TSF, IMK, browser composition events) instead show the word
+//! being typed as an underlined preedit string and only insert text when
+//! it is committed. This module turns engine results into that protocol:
+//! - `PreeditUpdate`: show `text` as the composition, caret at `cursor`
+//! - `Commit`: insert `text` and end the composition
+//! - `Cancel`: drop the composition without inserting anything
+
+use crate::convert::TextField;
+use crate::engine::{Action, ProcessResult};
+use serde::{Deserialize, Serialize};
+
+/// Change to the composition shown by the host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreeditEvent {
+    /// Show `text` as the composition; `cursor` is in characters
+    PreeditUpdate { text: String, cursor: usize },
+    /// Insert `text` into the document, ending the composition
+    Commit { text: String },
+    /// Clear the composition
+    Cancel,
+}
+
+/// Events for one key, given the composition before it, the engine
+/// result and the word the engine is composing afterwards
+///
+/// An empty list means the key was not used and the host should handle
+/// it normally.
+pub fn events(
+    before: &str,
+    result: &ProcessResult,
+    key: Option<char>,
+    after: &str,
+) -> Vec<PreeditEvent> {
+    if result.action == Action::Passthrough && before.is_empty() && after.is_empty() {
+        return Vec::new();
+    }
+
+    // Replay the edit on the composition alone: whatever is left in front
+    // of the new word has left the composition and is committed
+    let mut field = TextField::with_text(before);
+    field.apply(result, key);
+    let text = field.into_text();
+    let committed = text.strip_suffix(after).unwrap_or(&text);
+
+    let mut events = Vec::new();
+    if !committed.is_empty() {
+        events.push(PreeditEvent::Commit {
+            text: committed.to_string(),
+        });
+    }
+    if !after.is_empty() {
+        events.push(PreeditEvent::PreeditUpdate {
+            text: after.to_string(),
+            cursor: after.chars().count(),
+        });
+    } else if committed.is_empty() {
+        events.push(PreeditEvent::Cancel);
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Engine, EngineOptions};
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            auto_capitalize: false,
+            ..EngineOptions::default()
+        });
+        engine
+    }
+
+    fn update(text: &str) -> PreeditEvent {
+        PreeditEvent::PreeditUpdate {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+        }
+    }
+
+    fn commit(text: &str) -> PreeditEvent {
+        PreeditEvent::Commit {
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_word_is_composed_then_committed() {
+        let mut engine = engine();
+        let events: Vec<_> = "vieetj "
+            .chars()
+            .map(|key| engine.process_key_preedit(key, false))
+            .collect();
+
+        assert_eq!(events[0], vec![update("v")]);
+        assert_eq!(events[3], vec![update("viê")]);
+        assert_eq!(events[5], vec![update("việt")]);
+        assert_eq!(events[6], vec![commit("việt ")]);
+
+        // A space with nothing composed is left to the host
+        assert!(engine.process_key_preedit(' ', false).is_empty());
+    }
+
+    #[test]
+    fn test_restore_commits_raw_keys() {
+        let mut engine = engine();
+        for key in "expect".chars() {
+            engine.process_key_preedit(key, false);
+        }
+        assert_eq!(
+            engine.process_key_preedit(' ', false),
+            vec![commit("expect ")]
+        );
+    }
+
+    #[test]
+    fn test_backspace_and_cancel() {
+        let mut engine = engine();
+        for key in "tas".chars() {
+            engine.process_key_preedit(key, false);
+        }
+        assert_eq!(engine.process_backspace_preedit(), vec![update("t")]);
+        assert_eq!(
+            engine.process_backspace_preedit(),
+            vec![PreeditEvent::Cancel]
+        );
+        assert!(engine.process_backspace_preedit().is_empty());
+
+        engine.process_key_preedit('a', false);
+        assert_eq!(engine.cancel_preedit(), vec![PreeditEvent::Cancel]);
+        assert_eq!(engine.get_buffer(), "");
+    }
+
+    #[test]
+    fn test_commit_on_focus_out() {
+        let mut engine = engine();
+        for key in "ddaay".chars() {
+            engine.process_key_preedit(key, false);
+        }
+        assert_eq!(engine.commit_preedit(), vec![commit("đây")]);
+        assert!(engine.commit_preedit().is_empty());
+    }
+}
@@ -3,8 +3,8 @@
 //! Allows users to define shortcuts like "vn" → "Việt Nam"
 //! Shortcuts can be enabled/disabled and added/removed at runtime.
 
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 
 /// Maximum replacement length
 pub const MAX_REPLACEMENT_LEN: usize = 63;
@@ -20,7 +20,7 @@ pub enum TriggerCondition {
 }
 
 /// A single shortcut entry
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Shortcut {
     pub trigger: String,
     pub replacement: String,
@@ -58,13 +58,43 @@ pub struct ShortcutMatch {
 }
 
 /// Shortcut table with on/off functionality
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(from = "SavedTable")]
 pub struct ShortcutTable {
     enabled: bool,
+    #[serde(serialize_with = "serialize_sorted")]
     shortcuts: HashMap<String, Shortcut>,
+    /// Lookup order, rebuilt from `shortcuts` rather than saved
+    #[serde(skip)]
     sorted_triggers: Vec<String>,
 }
 
+/// Shortcut table as saved in a config; a table that only lists
+/// shortcuts is switched on
+#[derive(Deserialize)]
+struct SavedTable {
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    #[serde(default)]
+    shortcuts: HashMap<String, Shortcut>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl From<SavedTable> for ShortcutTable {
+    fn from(saved: SavedTable) -> Self {
+        let mut table = Self {
+            enabled: saved.enabled,
+            shortcuts: saved.shortcuts,
+            sorted_triggers: Vec::new(),
+        };
+        table.rebuild_sorted();
+        table
+    }
+}
+
 impl ShortcutTable {
     /// Create empty table (disabled by default)
     pub fn new() -> Self {
@@ -160,7 +190,7 @@ impl ShortcutTable {
     fn rebuild_sorted(&mut self) {
         self.sorted_triggers = self.shortcuts.keys().cloned().collect();
         self.sorted_triggers
-            .sort_by_key(|s| std::cmp::Reverse(s.len()));
+            .sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
     }
 
     pub fn len(&self) -> usize {
@@ -171,6 +201,17 @@ impl ShortcutTable {
     }
 }
 
+/// Write shortcuts in trigger order so saved configs are stable
+fn serialize_sorted<S: Serializer>(
+    shortcuts: &HashMap<String, Shortcut>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    shortcuts
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,360 @@
+//! Syllable Model
+//!
+//! Splits the buffer into initial consonant, vowel nucleus and final
+//! consonant so vowel modifiers land on the right nucleus vowel no matter
+//! where in the key sequence they were typed:
+//! - tuoiw → tươi, nguoiwf → người
+//! - vietej → việt, xoanw → xoăn
+
+use crate::chars::{self, VowelMod};
+use crate::transform;
+use crate::validation;
+use std::ops::Range;
+
+/// Character index ranges of the parts of a syllable
+#[derive(Debug, Clone, PartialEq)]
+pub struct Syllable {
+    /// Initial consonant (phụ âm đầu)
+    pub initial: Range<usize>,
+    /// Vowel nucleus (vần)
+    pub nucleus: Range<usize>,
+    /// Final consonant (phụ âm cuối)
+    pub final_c: Range<usize>,
+}
+
+impl Syllable {
+    /// Parse buffer characters into initial/nucleus/final
+    pub fn parse(chars: &[char]) -> Option<Self> {
+        let lower: String = chars.iter().map(|&c| chars::to_lower(c)).collect();
+        let (initial, vowel, _) = validation::parse_syllable(&lower)?;
+
+        let initial_len = initial.chars().count();
+        let nucleus_end = initial_len + vowel.chars().count();
+
+        Some(Self {
+            initial: 0..initial_len,
+            nucleus: initial_len..nucleus_end,
+            final_c: nucleus_end..chars.len(),
+        })
+    }
+}
+
+/// Character replacements that apply or remove a vowel modifier
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifierEdit {
+    /// (index, new character) pairs
+    pub changes: Vec<(usize, char)>,
+    /// Whether this removes a modifier (double-mark undo)
+    pub undo: bool,
+}
+
+/// Decide which nucleus vowels a modifier key changes
+///
+/// Letter keys (Telex `aa`, `w`) may also be plain letters, so they only
+/// reach back into the nucleus when the result is a valid vowel pattern;
+/// otherwise they only affect the vowel right before them. Telex `w` can
+/// produce either horn or breve, whichever fits the nucleus.
+pub fn resolve_modifier(chars: &[char], modifier: VowelMod, raw_key: char) -> Option<ModifierEdit> {
+    let letter_key = raw_key.is_alphabetic();
+    let key_base = chars::to_lower(raw_key);
+    // Telex aa/ee/oo: the key names the vowel it puts a circumflex on
+    let vowel_key = matches!(key_base, 'a' | 'e' | 'o').then_some(key_base);
+
+    let Some(syllable) = Syllable::parse(chars) else {
+        return if letter_key {
+            resolve_adjacent(chars, modifier, vowel_key)
+        } else {
+            resolve_anywhere(chars, modifier)
+        };
+    };
+    let nucleus = syllable.nucleus;
+
+    let kinds: &[VowelMod] = match modifier {
+        VowelMod::Horn if letter_key => &[VowelMod::Horn, VowelMod::Breve],
+        VowelMod::Breve if letter_key => &[VowelMod::Breve, VowelMod::Horn],
+        VowelMod::None => return None,
+        _ => std::slice::from_ref(&modifier),
+    };
+
+    // Double-mark undo: the key was already applied to this nucleus
+    for &kind in kinds {
+        let applied: Vec<usize> = nucleus
+            .clone()
+            .filter(|&i| transform::get_modifier(chars[i]) == kind)
+            .filter(|&i| {
+                kind != VowelMod::Circumflex || vowel_key.is_none_or(|k| base(chars[i]) == k)
+            })
+            .collect();
+        if !applied.is_empty() {
+            return Some(ModifierEdit {
+                changes: applied
+                    .into_iter()
+                    .map(|i| (i, transform::remove_modifier(chars[i]).0))
+                    .collect(),
+                undo: true,
+            });
+        }
+    }
+
+    for &kind in kinds {
+        for targets in candidates(chars, nucleus.clone(), kind, vowel_key) {
+            if let Some(changes) = apply_to(chars, &targets, kind) {
+                if is_valid_after(chars, nucleus.clone(), &changes) {
+                    return Some(ModifierEdit {
+                        changes,
+                        undo: false,
+                    });
+                }
+            }
+        }
+    }
+
+    // No placement gives a valid nucleus
+    if letter_key {
+        resolve_adjacent(chars, modifier, vowel_key)
+    } else {
+        candidates(chars, nucleus, modifier, None)
+            .into_iter()
+            .find_map(|targets| apply_to(chars, &targets, modifier))
+            .map(|changes| ModifierEdit {
+                changes,
+                undo: false,
+            })
+    }
+}
+
+/// Complete a half-typed ươ: "ư" followed by a plain "o" takes the horn
+/// too, and so does the "u" of an open "uơ" once a glide or final follows
+/// (tuơ + i → tươi; the u of "qu" is part of the initial)
+pub fn complete_uo_horn(chars: &[char]) -> Option<(usize, char)> {
+    (1..chars.len()).find_map(|i| {
+        let (u, o) = (chars[i - 1], chars[i]);
+        let is_u = base(u) == 'u';
+        let is_o = base(o) == 'o';
+        if is_u
+            && is_o
+            && transform::get_modifier(u) == VowelMod::Horn
+            && transform::get_modifier(o) == VowelMod::None
+        {
+            return transform::apply_modifier(o, VowelMod::Horn).map(|c| (i, c));
+        }
+        let after_q = i >= 2 && chars::to_lower(chars[i - 2]) == 'q';
+        if is_u
+            && is_o
+            && !after_q
+            && i + 1 < chars.len()
+            && transform::get_modifier(u) == VowelMod::None
+            && transform::get_modifier(o) == VowelMod::Horn
+        {
+            return transform::apply_modifier(u, VowelMod::Horn).map(|c| (i - 1, c));
+        }
+        None
+    })
+}
+
+/// Lowercase base vowel of a character
+fn base(c: char) -> char {
+    chars::get_base(chars::to_lower(c))
+}
+
+/// Target groups for a modifier, most likely first
+fn candidates(
+    chars: &[char],
+    nucleus: Range<usize>,
+    modifier: VowelMod,
+    key_base: Option<char>,
+) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    match modifier {
+        VowelMod::Horn => {
+            // uo → ươ as a pair before a glide or final (người, được);
+            // an open uo only takes it on the o (thuở, huơ)
+            for i in nucleus.start..nucleus.end.saturating_sub(1) {
+                if base(chars[i]) == 'u' && base(chars[i + 1]) == 'o' {
+                    if i + 2 < chars.len() {
+                        result.push(vec![i, i + 1]);
+                    } else {
+                        result.push(vec![i + 1]);
+                    }
+                }
+            }
+            for i in nucleus {
+                if matches!(base(chars[i]), 'o' | 'u') {
+                    result.push(vec![i]);
+                }
+            }
+        }
+        VowelMod::Breve => {
+            for i in nucleus.rev() {
+                if base(chars[i]) == 'a' {
+                    result.push(vec![i]);
+                }
+            }
+        }
+        VowelMod::Circumflex => {
+            for i in nucleus.rev() {
+                let b = base(chars[i]);
+                let matches_key = match key_base {
+                    Some(key) => b == key,
+                    None => matches!(b, 'a' | 'e' | 'o'),
+                };
+                if matches_key {
+                    result.push(vec![i]);
+                }
+            }
+        }
+        VowelMod::None => {}
+    }
+    result
+}
+
+/// Apply a modifier to every target, keeping tones and case
+fn apply_to(chars: &[char], targets: &[usize], modifier: VowelMod) -> Option<Vec<(usize, char)>> {
+    targets
+        .iter()
+        .map(|&i| transform::apply_modifier(chars[i], modifier).map(|c| (i, c)))
+        .collect()
+}
+
+/// Check the nucleus is still a valid vowel pattern after the changes
+fn is_valid_after(chars: &[char], nucleus: Range<usize>, changes: &[(usize, char)]) -> bool {
+    let vowel: String = nucleus
+        .map(|i| {
+            changes
+                .iter()
+                .find(|&&(idx, _)| idx == i)
+                .map_or(chars[i], |&(_, c)| c)
+        })
+        .collect();
+    validation::is_valid_nucleus(&vowel)
+}
+
+/// Letter keys outside a parsable syllable only touch the previous vowel
+fn resolve_adjacent(
+    chars: &[char],
+    modifier: VowelMod,
+    vowel_key: Option<char>,
+) -> Option<ModifierEdit> {
+    let last = chars.len().checked_sub(1)?;
+    let prev = chars[last];
+    let fits = match modifier {
+        VowelMod::Circumflex => match vowel_key {
+            Some(key) => base(prev) == key,
+            None => matches!(base(prev), 'a' | 'e' | 'o'),
+        },
+        VowelMod::Horn => matches!(base(prev), 'o' | 'u'),
+        VowelMod::Breve => base(prev) == 'a',
+        VowelMod::None => false,
+    };
+    if !fits {
+        return None;
+    }
+
+    if transform::should_undo_modifier(prev, modifier) {
+        return Some(ModifierEdit {
+            changes: vec![(last, transform::remove_modifier(prev).0)],
+            undo: true,
+        });
+    }
+    transform::apply_modifier(prev, modifier).map(|c| ModifierEdit {
+        changes: vec![(last, c)],
+        undo: false,
+    })
+}
+
+/// Non-letter keys (VNI digits) outside a parsable syllable use the
+/// whole-buffer search
+fn resolve_anywhere(chars: &[char], modifier: VowelMod) -> Option<ModifierEdit> {
+    if modifier == VowelMod::Horn {
+        let mut compound = chars.to_vec();
+        let uo = (0..chars.len().saturating_sub(1))
+            .find(|&i| base(chars[i]) == 'u' && base(chars[i + 1]) == 'o');
+        if let Some(pos) = uo {
+            if transform::apply_uo_compound(&mut compound, pos).success {
+                return Some(ModifierEdit {
+                    changes: vec![(pos, compound[pos]), (pos + 1, compound[pos + 1])],
+                    undo: false,
+                });
+            }
+        }
+    }
+
+    let pos = transform::find_modifier_position(chars, modifier)?;
+    let old = chars[pos];
+    if transform::should_undo_modifier(old, modifier) {
+        return Some(ModifierEdit {
+            changes: vec![(pos, transform::remove_modifier(old).0)],
+            undo: true,
+        });
+    }
+    transform::apply_modifier(old, modifier).map(|c| ModifierEdit {
+        changes: vec![(pos, c)],
+        undo: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(word: &str, modifier: VowelMod, key: char) -> String {
+        let mut chars: Vec<char> = word.chars().collect();
+        let edit = resolve_modifier(&chars, modifier, key).expect("modifier should apply");
+        for (i, c) in edit.changes {
+            chars[i] = c;
+        }
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn test_parse_syllable_ranges() {
+        let chars: Vec<char> = "nguoi".chars().collect();
+        let syllable = Syllable::parse(&chars).unwrap();
+        assert_eq!(syllable.initial, 0..2);
+        assert_eq!(syllable.nucleus, 2..5);
+        assert_eq!(syllable.final_c, 5..5);
+
+        let chars: Vec<char> = "Tiếng".chars().collect();
+        let syllable = Syllable::parse(&chars).unwrap();
+        assert_eq!(syllable.nucleus, 1..3);
+        assert_eq!(syllable.final_c, 3..5);
+    }
+
+    #[test]
+    fn test_retroactive_horn() {
+        assert_eq!(resolve("tuoi", VowelMod::Horn, 'w'), "tươi");
+        assert_eq!(resolve("nguoi", VowelMod::Horn, 'w'), "ngươi");
+        assert_eq!(resolve("muan", VowelMod::Horn, '7'), "mưan");
+    }
+
+    #[test]
+    fn test_w_picks_breve_or_horn() {
+        assert_eq!(resolve("xoan", VowelMod::Horn, 'w'), "xoăn");
+        assert_eq!(resolve("mua", VowelMod::Breve, 'w'), "mưa");
+    }
+
+    #[test]
+    fn test_retroactive_circumflex() {
+        assert_eq!(resolve("viet", VowelMod::Circumflex, 'e'), "viêt");
+        assert_eq!(resolve("nguyen", VowelMod::Circumflex, '6'), "nguyên");
+        // "oâ" is not a valid nucleus, so "a" stays a plain letter
+        let chars: Vec<char> = "hoan".chars().collect();
+        assert!(resolve_modifier(&chars, VowelMod::Circumflex, 'a').is_none());
+    }
+
+    #[test]
+    fn test_modifier_undo() {
+        let chars: Vec<char> = "tươi".chars().collect();
+        let edit = resolve_modifier(&chars, VowelMod::Horn, 'w').unwrap();
+        assert!(edit.undo);
+        assert_eq!(edit.changes, vec![(1, 'u'), (2, 'o')]);
+    }
+
+    #[test]
+    fn test_complete_uo_horn() {
+        let chars: Vec<char> = "ngưo".chars().collect();
+        assert_eq!(complete_uo_horn(&chars), Some((3, 'ơ')));
+        let chars: Vec<char> = "thuơ".chars().collect();
+        assert_eq!(complete_uo_horn(&chars), None);
+    }
+}
@@ -64,11 +64,11 @@ pub fn apply_modifier(ch: char, modifier: VowelMod) -> Option<char> {
 /// Remove all diacritics from a character
 #[allow(clippy::option_if_let_else)]
 pub fn remove_diacritics(ch: char) -> char {
-    let lower = ch.to_ascii_lowercase();
+    let lower = chars::to_lower(ch);
 
     if let Some(&(base, _, _)) = REVERSE_MAP.get(&lower) {
         if ch.is_uppercase() {
-            base.to_ascii_uppercase()
+            chars::to_upper(base)
         } else {
             base
         }
@@ -95,7 +95,7 @@ pub fn toggle_stroke(ch: char) -> char {
 
 /// Get the current tone of a character
 pub fn get_tone(ch: char) -> ToneMark {
-    let lower = ch.to_ascii_lowercase();
+    let lower = chars::to_lower(ch);
     REVERSE_MAP
         .get(&lower)
         .map_or(ToneMark::None, |&(_, _, tone)| tone)
@@ -103,7 +103,7 @@ pub fn get_tone(ch: char) -> ToneMark {
 
 /// Get the current modifier of a character
 pub fn get_modifier(ch: char) -> VowelMod {
-    let lower = ch.to_ascii_lowercase();
+    let lower = chars::to_lower(ch);
     REVERSE_MAP
         .get(&lower)
         .map_or(VowelMod::None, |&(_, modifier, _)| modifier)
@@ -143,6 +143,8 @@ pub fn find_tone_position(chars: &[char], vowel_indices: &[usize]) -> Option<usi
 ///
 /// - `modern_style = true`: hoà, khoẻ, thuỷ (tone on second vowel for oa/oe/uy)
 /// - `modern_style = false`: hòa, khỏe, thủy (tone on first vowel for oa/oe/uy)
+///
+/// With a final consonant both styles agree: hoàn, huỳnh.
 pub fn find_tone_position_styled(
     chars: &[char],
     vowel_indices: &[usize],
@@ -161,13 +163,13 @@ pub fn find_tone_position_styled(
     if vowel_indices.len() > 1 {
         if crate::validation::has_gi_initial(&word) {
             // Skip 'i' if it's the first vowel
-            let first_vowel = chars[vowel_indices[0]].to_ascii_lowercase();
+            let first_vowel = chars::to_lower(chars[vowel_indices[0]]);
             if chars::get_base(first_vowel) == 'i' {
                 effective_vowels = &vowel_indices[1..];
             }
         } else if crate::validation::has_qu_initial(&word) {
             // Skip 'u' if it's the first vowel
-            let first_vowel = chars[vowel_indices[0]].to_ascii_lowercase();
+            let first_vowel = chars::to_lower(chars[vowel_indices[0]]);
             if chars::get_base(first_vowel) == 'u' {
                 effective_vowels = &vowel_indices[1..];
             }
@@ -193,8 +195,8 @@ pub fn find_tone_position_styled(
             let first_idx = vowel_indices[i];
             let second_idx = vowel_indices[i + 1];
 
-            let first = chars[first_idx].to_ascii_lowercase();
-            let second = chars[second_idx].to_ascii_lowercase();
+            let first = chars::to_lower(chars[first_idx]);
+            let second = chars::to_lower(chars[second_idx]);
 
             let first_base = chars::get_base(first);
             let second_base = chars::get_base(second);
@@ -226,8 +228,8 @@ pub fn find_tone_position_styled(
 
         // Rule 4: Two vowels
         2 => {
-            let first = chars[vowel_indices[0]].to_ascii_lowercase();
-            let second = chars[vowel_indices[1]].to_ascii_lowercase();
+            let first = chars::to_lower(chars[vowel_indices[0]]);
+            let second = chars::to_lower(chars[vowel_indices[1]]);
 
             let first_base = chars::get_base(first);
             let second_base = chars::get_base(second);
@@ -240,9 +242,15 @@ pub fn find_tone_position_styled(
                 ('u', 'y'), // thuy → thuỳ (modern) / thủy (traditional)
             ];
 
+            // A final consonant always pulls the tone to the second vowel
+            // (hoàn, khoẻn, huỳnh), whatever the style
+            let has_final = chars
+                .get(vowel_indices[1] + 1)
+                .is_some_and(|&c| chars::is_consonant(c));
+
             for (f, s) in style_patterns {
                 if first_base == f && second_base == s {
-                    return if modern_style {
+                    return if modern_style || has_final {
                         Some(vowel_indices[1]) // Modern: second vowel
                     } else {
                         Some(vowel_indices[0]) // Traditional: first vowel
@@ -306,8 +314,8 @@ pub fn apply_uo_compound(chars: &mut [char], start_idx: usize) -> TransformResul
         return TransformResult::none();
     }
 
-    let first = chars[start_idx].to_ascii_lowercase();
-    let second = chars[start_idx + 1].to_ascii_lowercase();
+    let first = chars::to_lower(chars[start_idx]);
+    let second = chars::to_lower(chars[start_idx + 1]);
 
     let first_base = chars::get_base(first);
     let second_base = chars::get_base(second);
@@ -323,12 +331,12 @@ pub fn apply_uo_compound(chars: &mut [char], start_idx: usize) -> TransformResul
             if let Some(new_second) = apply_modifier_with_tone('o', VowelMod::Horn, second_tone) {
                 // Preserve case
                 chars[start_idx] = if chars[start_idx].is_uppercase() {
-                    new_first.to_ascii_uppercase()
+                    chars::to_upper(new_first)
                 } else {
                     new_first
                 };
                 chars[start_idx + 1] = if chars[start_idx + 1].is_uppercase() {
-                    new_second.to_ascii_uppercase()
+                    chars::to_upper(new_second)
                 } else {
                     new_second
                 };
@@ -375,7 +383,7 @@ pub fn remove_tone(ch: char) -> (char, bool) {
         if modifier != VowelMod::None {
             if let Some(with_mod) = apply_modifier(without_tone, modifier) {
                 let result = if ch.is_uppercase() {
-                    with_mod.to_ascii_uppercase()
+                    chars::to_upper(with_mod)
                 } else {
                     with_mod
                 };
@@ -384,7 +392,7 @@ pub fn remove_tone(ch: char) -> (char, bool) {
         }
 
         let result = if ch.is_uppercase() {
-            without_tone.to_ascii_uppercase()
+            chars::to_upper(without_tone)
         } else {
             without_tone
         };
@@ -406,7 +414,7 @@ pub fn remove_modifier(ch: char) -> (char, bool) {
 
     if let Some(without_mod) = CHAR_MAP.get(&(base, VowelMod::None, tone)).copied() {
         let result = if ch.is_uppercase() {
-            without_mod.to_ascii_uppercase()
+            chars::to_upper(without_mod)
         } else {
             without_mod
         };
@@ -434,7 +442,7 @@ pub fn find_modifier_position(chars: &[char], modifier: VowelMod) -> Option<usiz
 
     // Find last matching vowel (right to left)
     for i in (0..chars.len()).rev() {
-        let c = chars[i].to_ascii_lowercase();
+        let c = chars::to_lower(chars[i]);
         let base = chars::get_base(c);
         if valid_bases.contains(&base) {
             return Some(i);
@@ -505,6 +513,19 @@ mod tests {
         assert_eq!(find_tone_position(&chars, &vowels), Some(1));
     }
 
+    #[test]
+    fn test_tone_position_style_with_final() {
+        // Traditional style only differs for open syllables
+        let chars: Vec<char> = "hoa".chars().collect();
+        let vowels = find_vowel_indices(&chars);
+        assert_eq!(find_tone_position_styled(&chars, &vowels, false), Some(1));
+
+        let chars: Vec<char> = "hoan".chars().collect();
+        let vowels = find_vowel_indices(&chars);
+        assert_eq!(find_tone_position_styled(&chars, &vowels, false), Some(2));
+        assert_eq!(find_tone_position_styled(&chars, &vowels, true), Some(2));
+    }
+
     #[test]
     fn test_should_undo() {
         assert!(should_undo_tone('á', ToneMark::Acute));
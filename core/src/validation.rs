@@ -1,9 +1,10 @@
 //! Vietnamese Syllable Validation
 //!
 //! Validates if a string is a valid Vietnamese syllable.
-//! Based on Vietnamese phonology rules with Foreign Word Detection.
+//! Based on Vietnamese phonology rules; English words typed with Telex are
+//! recognized from their raw keys by `foreign`.
 
-use crate::chars::{self, VowelMod, REVERSE_MAP};
+use crate::chars;
 
 // ============================================================
 // PHONOLOGY CONSTANTS
@@ -45,16 +46,6 @@ const FOREIGN_CLUSTERS: &[&str] = &[
     "ght", "wh", "wr",
 ];
 
-/// Invalid vowel patterns (impossible in Vietnamese)
-#[allow(dead_code)]
-const INVALID_VOWEL_PATTERNS: &[&str] = &[
-    // These vowel combinations don't exist in Vietnamese
-    "eư", "oư", "iư", // ư cannot follow e, o, i directly
-    "ưe", "ưo", "ưy", // Invalid ư combinations
-    "ou", "yo", // English-like patterns
-    "ea", "ei", // English diphthongs (NOT "ie" - that's valid: tiếp, tiếng)
-];
-
 // ============================================================
 // VALIDATION RESULT
 // ============================================================
@@ -142,195 +133,6 @@ pub fn is_valid_syllable(s: &str) -> bool {
     validate(s).is_valid()
 }
 
-/// Check if pattern looks like a foreign/English word
-/// Based on 8 English auto-restore patterns
-pub fn is_foreign_word_pattern(buffer: &str, modifier_key: Option<char>) -> bool {
-    let lower = buffer.to_lowercase();
-    let chars: Vec<char> = lower.chars().collect();
-
-    // Skip check if buffer has Vietnamese diacritics (horn, circumflex, breve)
-    // User is typing Vietnamese intentionally
-    if has_vietnamese_diacritics(&lower) {
-        return false;
-    }
-
-    // Skip check for valid typing sequences like "dodo" (typing đô)
-    if is_valid_typing_sequence(&lower) {
-        return false;
-    }
-
-    // ============================================================
-    // 8 ENGLISH AUTO-RESTORE PATTERNS
-    // ============================================================
-
-    // PATTERN 1: MODIFIER + CONSONANT (not sonorant)
-    // "text" → x+t, "expect" → x+p → English
-    // Exception: Modifier + sonorant (m,n,ng,nh) → Vietnamese "làm"
-    if let Some(mod_key) = modifier_key {
-        if is_tone_modifier(mod_key) && chars.len() >= 2 {
-            let last = chars[chars.len() - 1];
-            // Allow sonorants (m, n) as they're valid Vietnamese endings
-            if !matches!(last, 'm' | 'n') && crate::chars::is_consonant(last) {
-                // Check if last char would be added after a vowel (vietnamese) or consonant (english)
-                if chars.len() >= 2 {
-                    let second_last = chars[chars.len() - 2];
-                    if crate::chars::is_consonant(second_last) {
-                        return true; // Consonant cluster after modifier = English
-                    }
-                }
-            }
-        }
-    }
-
-    // PATTERN 2: W AT START + CONSONANT
-    // "water", "window", "world" → English
-    // Exception: "ưng", "ưn" (w + sonorant final) → Vietnamese
-    if lower.starts_with('w') && chars.len() > 1 {
-        let second = chars[1];
-        if crate::chars::is_consonant(second) && second != 'h' {
-            return true;
-        }
-    }
-
-    // PATTERN 3: EI VOWEL PAIR
-    // "their", "weird" → English (Vietnamese doesn't have "ei" diphthong)
-    if lower.contains("ei") {
-        return true;
-    }
-
-    // PATTERN 4: P INITIAL + AI PATTERN
-    // "pair", "paint" → English (P initial is rare in pure Vietnamese)
-    if lower.starts_with('p') && !lower.starts_with("ph") && lower.contains("ai") {
-        return true;
-    }
-
-    // PATTERN 5: W AS FINAL
-    // "raw", "law", "saw" → English (W cannot be final in Vietnamese)
-    if lower.ends_with('w') {
-        return true;
-    }
-
-    // PATTERN 6: F INITIAL
-    // "fix", "file", "focus" → English (Vietnamese uses PH for /f/)
-    if lower.starts_with('f') {
-        return true;
-    }
-
-    // PATTERN 7: MODIFIER + K ENDING
-    // "risk", "disk", "task" → English
-    // Exception: Ethnic minority with breve (Đắk, Lắk) - but those have diacritics so skip
-    if modifier_key.map(is_tone_modifier).unwrap_or(false) && lower.ends_with('k') {
-        // Check if NOT ethnic minority pattern (starts with đ, l, b)
-        if !matches!(chars.first(), Some('đ') | Some('l') | Some('b')) {
-            return true;
-        }
-    }
-
-    // PATTERN 8: DOUBLE VOWEL + CONSONANT (English "looks", "took")
-    // Different from Telex "aa" → "â" which creates circumflex
-    if lower.contains("oo") && chars.len() > 2 {
-        // If followed by consonant (not another vowel), likely English
-        if let Some(pos) = lower.find("oo") {
-            if pos + 2 < chars.len() {
-                let after = chars[pos + 2];
-                if crate::chars::is_consonant(after) {
-                    return true;
-                }
-            }
-        }
-    }
-
-    // ============================================================
-    // EXISTING CHECKS (kept from before)
-    // ============================================================
-
-    // Check for invalid vowel patterns (eư, oư, etc)
-    for pattern in INVALID_VOWEL_PATTERNS {
-        if lower.contains(pattern) {
-            return true;
-        }
-    }
-
-    // Check for foreign consonant clusters (not at start)
-    for cluster in FOREIGN_CLUSTERS {
-        if lower.contains(cluster) {
-            // Allow "tr" at the start (Vietnamese "tr")
-            if *cluster == "tr" && lower.starts_with("tr") {
-                continue;
-            }
-            // Check if cluster is found
-            if let Some(pos) = lower.find(cluster) {
-                if pos == 0 {
-                    return true;
-                }
-
-                // Check if cluster is after a vowel (foreign pattern)
-                let prev_char = lower.chars().nth(pos - 1);
-                if prev_char.map(crate::chars::is_vowel).unwrap_or(false) {
-                    return true;
-                }
-            }
-        }
-    }
-
-    // English prefix patterns: de+s (describe, design)
-    if lower.starts_with("de") && modifier_key == Some('s') && lower.len() > 3 {
-        return true;
-    }
-
-    // pr- prefix not valid in Vietnamese
-    if lower.starts_with("pr") && lower.len() > 3 {
-        return true;
-    }
-
-    // -tion, -sion endings (clearly English)
-    if lower.ends_with("tion") || lower.ends_with("sion") {
-        return true;
-    }
-
-    // PATTERN 9: YO combination (yoga, yolo, yo-yo)
-    // Vietnamese doesn't have "yo" diphthong
-    if lower.contains("yo") {
-        return true;
-    }
-
-    // PATTERN 10: OU combination outside of specific Vietnamese patterns
-    // "your", "our", "out", "about" → English
-    // Exception: "tou" in some loan words, "sou" patterns
-    if lower.contains("ou") {
-        // Check if it's in a clearly English context
-        let has_english_context = lower.starts_with("you")
-            || lower.starts_with("our")
-            || lower.starts_with("out")
-            || lower.ends_with("ous")
-            || lower.ends_with("ould")
-            || lower.ends_with("ound")
-            || lower.ends_with("ount");
-        if has_english_context {
-            return true;
-        }
-    }
-
-    // PATTERN 11: J initial (just, join, job)
-    // Vietnamese doesn't use J (uses GI instead)
-    if lower.starts_with('j') {
-        return true;
-    }
-
-    // PATTERN 12: Z at start or middle (zero, amazing)
-    // Vietnamese doesn't use Z
-    if lower.contains('z') {
-        return true;
-    }
-
-    false
-}
-
-/// Check if character is a tone modifier key (Telex)
-fn is_tone_modifier(c: char) -> bool {
-    matches!(c, 's' | 'f' | 'r' | 'x' | 'j')
-}
-
 // ============================================================
 // HELPER FUNCTIONS
 // ============================================================
@@ -340,50 +142,6 @@ fn has_vowel(chars: &[char]) -> bool {
     chars.iter().any(|&c| crate::chars::is_vowel(c))
 }
 
-/// Check if string has Vietnamese diacritics (horn, circumflex, breve)
-fn has_vietnamese_diacritics(s: &str) -> bool {
-    for c in s.chars() {
-        if let Some(&(_, modifier, tone)) = REVERSE_MAP.get(&c) {
-            if modifier != VowelMod::None || tone != crate::chars::ToneMark::None {
-                return true;
-            }
-        }
-        // Direct check for Vietnamese special chars
-        if matches!(c, 'ư' | 'ơ' | 'â' | 'ê' | 'ô' | 'ă' | 'đ') {
-            return true;
-        }
-    }
-    false
-}
-
-/// Check if this is a valid Vietnamese typing sequence
-/// e.g., "dodo" could be typing "đô" (d→o→d→o where second d triggers stroke)
-fn is_valid_typing_sequence(s: &str) -> bool {
-    let lower = s.to_lowercase();
-
-    // Patterns that look foreign but are valid Vietnamese typing sequences:
-
-    // "dodo", "dodi" - typing đô, đồ, etc. with dd for đ
-    if lower.starts_with("do") && lower.chars().nth(2) == Some('d') {
-        return true;
-    }
-
-    // "soso", "lolo" - typing có repeated consonants for emphasis
-    // But these could also be valid Vietnamese with tones added later
-    let chars: Vec<char> = lower.chars().collect();
-    if chars.len() >= 4 {
-        // Check if it's a simple repetition pattern like "xoxo"
-        if chars[0] == chars[2] && chars[1] == chars[3] {
-            // If first char is consonant and second is vowel, could be valid
-            if !crate::chars::is_vowel(chars[0]) && crate::chars::is_vowel(chars[1]) {
-                return true;
-            }
-        }
-    }
-
-    false
-}
-
 /// Check for foreign word patterns
 fn is_foreign_pattern(s: &str) -> bool {
     // Consonant clusters that don't exist in Vietnamese
@@ -400,7 +158,8 @@ fn is_foreign_pattern(s: &str) -> bool {
 }
 
 /// Parse syllable into (initial, vowel, final) components
-fn parse_syllable(s: &str) -> Option<(&str, &str, &str)> {
+/// Expects lowercase input.
+pub fn parse_syllable(s: &str) -> Option<(&str, &str, &str)> {
     // Try longest initial first
     let mut initials: Vec<&&str> = VALID_INITIALS.iter().collect();
     initials.sort_by_key(|b| std::cmp::Reverse(b.len()));
@@ -477,6 +236,12 @@ fn normalize_vowel(vowel: &str) -> String {
         .collect()
 }
 
+/// Check if a vowel nucleus (tones ignored) is a valid Vietnamese pattern
+pub fn is_valid_nucleus(vowel: &str) -> bool {
+    let lower = vowel.to_lowercase();
+    is_valid_vowel_pattern(&normalize_vowel(&lower)) && !is_breve_followed_by_vowel(&lower)
+}
+
 /// Check if vowel pattern is valid
 fn is_valid_vowel_pattern(vowel_base: &str) -> bool {
     // Single vowels are always valid
@@ -552,11 +317,6 @@ pub fn is_potentially_valid(s: &str) -> bool {
 
     let lower = s.to_lowercase();
 
-    // Check if it's clearly a foreign word
-    if is_foreign_word_pattern(&lower, None) {
-        return false;
-    }
-
     // Check if any valid syllable could start with this
     for initial in VALID_INITIALS {
         if initial.starts_with(&lower) || lower.starts_with(initial) {
@@ -648,15 +408,6 @@ mod tests {
         assert!(!is_valid_syllable("mmm"));
     }
 
-    #[test]
-    fn test_foreign_word_detection() {
-        assert!(is_foreign_word_pattern("programming", None));
-        assert!(is_foreign_word_pattern("describe", Some('s')));
-        assert!(is_foreign_word_pattern("spectrum", None));
-        assert!(!is_foreign_word_pattern("việt", None)); // Has horn
-        assert!(!is_foreign_word_pattern("xin", None)); // Valid Vietnamese
-    }
-
     #[test]
     fn test_spelling_rules() {
         // c vs k
@@ -717,56 +468,4 @@ mod tests {
         // oă pattern is valid (o before ă)
         assert!(is_valid_syllable("xoăn")); // Valid: curly
     }
-
-    // ============================================================
-    // 8 ENGLISH AUTO-RESTORE PATTERN TESTS
-    // ============================================================
-
-    #[test]
-    fn test_english_pattern_w_initial() {
-        // PATTERN 2: W at start + consonant
-        assert!(is_foreign_word_pattern("wn", None)); // window start
-        assert!(is_foreign_word_pattern("wr", None)); // write
-        assert!(is_foreign_word_pattern("wl", None)); // world
-    }
-
-    #[test]
-    fn test_english_pattern_ei_pair() {
-        // PATTERN 3: EI vowel pair (not in Vietnamese)
-        assert!(is_foreign_word_pattern("their", None));
-        assert!(is_foreign_word_pattern("weird", None));
-        assert!(is_foreign_word_pattern("receive", None));
-    }
-
-    #[test]
-    fn test_english_pattern_f_initial() {
-        // PATTERN 6: F initial (Vietnamese uses PH)
-        assert!(is_foreign_word_pattern("fix", None));
-        assert!(is_foreign_word_pattern("file", None));
-        assert!(is_foreign_word_pattern("focus", None));
-    }
-
-    #[test]
-    fn test_english_pattern_w_final() {
-        // PATTERN 5: W as final
-        assert!(is_foreign_word_pattern("raw", None));
-        assert!(is_foreign_word_pattern("law", None));
-        assert!(is_foreign_word_pattern("saw", None));
-    }
-
-    #[test]
-    fn test_english_pattern_p_ai() {
-        // PATTERN 4: P initial + AI pattern
-        assert!(is_foreign_word_pattern("pair", None));
-        assert!(is_foreign_word_pattern("paint", None));
-        // But "phai" without diacritics could still trigger
-    }
-
-    #[test]
-    fn test_english_pattern_double_oo() {
-        // PATTERN 8: Double vowel oo + consonant
-        assert!(is_foreign_word_pattern("look", None));
-        assert!(is_foreign_word_pattern("took", None));
-        assert!(is_foreign_word_pattern("book", None));
-    }
 }
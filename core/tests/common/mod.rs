@@ -0,0 +1,61 @@
+//! Helpers shared by the integration tests
+
+use vietflux_core::engine::{Action, ProcessResult};
+use vietflux_core::Engine;
+
+/// Simulated host text field
+///
+/// Applies engine results the way the keyboard hooks do: Update and
+/// Restore delete `backspace` characters and type the output; Passthrough
+/// and Commit let the key itself through. Backspace counts are code
+/// points, matching `OutputEncoding`.
+#[derive(Debug, Default, Clone)]
+pub struct TextField {
+    text: String,
+}
+
+impl TextField {
+    /// Field that already holds some text before the cursor
+    pub fn with_text(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+        }
+    }
+
+    /// Current contents
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Press a key with the engine attached
+    pub fn type_key(&mut self, engine: &mut Engine, key: char) -> ProcessResult {
+        let result = engine.process_key(key, key.is_uppercase());
+        self.apply(&result, Some(key));
+        result
+    }
+
+    /// Press Backspace with the engine attached
+    pub fn press_backspace(&mut self, engine: &mut Engine) -> ProcessResult {
+        let result = engine.process_backspace();
+        self.apply(&result, None);
+        result
+    }
+
+    /// Apply a result for `key` (None for Backspace)
+    fn apply(&mut self, result: &ProcessResult, key: Option<char>) {
+        match result.action {
+            Action::Update | Action::Restore => {
+                for _ in 0..result.backspace {
+                    self.text.pop();
+                }
+                self.text.push_str(&result.output);
+            }
+            Action::Commit | Action::Passthrough => match key {
+                Some(k) => self.text.push(k),
+                None => {
+                    self.text.pop();
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Golden corpus
+//!
+//! Replays the keystroke cases in `tests/data` through `Engine` on a
+//! simulated text field (backspaces, then output) and compares what the
+//! user would see with the expected text.
+//!
+//! Corpus format, one case per line: `keys => expected`, where trailing
+//! spaces belong to the expected text (`boss  => boss `)
+//! - `{BS}` in keys presses Backspace
+//! - `#` starts a comment line
+//! - `@method NAME` switches the input method for the following cases
+//! - `@options JSON` sets engine options for the following cases (missing
+//!   fields take their defaults; auto-capitalize and smart quotes start off)
+
+mod common;
+
+use common::TextField;
+use vietflux_core::{Engine, EngineOptions};
+
+/// Text already in the field, so deleting too much shows up as a mismatch
+const SENTINEL: &str = "»";
+
+/// One keystroke case
+struct Case {
+    line: usize,
+    method: String,
+    options: EngineOptions,
+    keys: String,
+    expected: String,
+}
+
+/// Options cases run with unless `@options` says otherwise
+fn base_options() -> EngineOptions {
+    EngineOptions {
+        auto_capitalize: false,
+        smart_quotes: false,
+        ..EngineOptions::default()
+    }
+}
+
+fn parse_corpus(text: &str, method: &str) -> Vec<Case> {
+    let mut method = method.to_string();
+    let mut options = base_options();
+    let mut cases = Vec::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.trim_start().trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("@method ") {
+            method = name.trim().to_string();
+            continue;
+        }
+        if let Some(json) = line.strip_prefix("@options ") {
+            options = serde_json::from_str(json)
+                .unwrap_or_else(|e| panic!("line {}: bad options: {}", i + 1, e));
+            continue;
+        }
+        let (keys, expected) = line
+            .split_once(" =>")
+            .unwrap_or_else(|| panic!("line {}: expected `keys => text`", i + 1));
+        let expected = expected.strip_prefix(' ').unwrap_or(expected);
+        cases.push(Case {
+            line: i + 1,
+            method: method.clone(),
+            options,
+            keys: keys.to_string(),
+            expected: expected.to_string(),
+        });
+    }
+    cases
+}
+
+/// Type a key sequence into a fresh engine and return the field contents,
+/// starting with `SENTINEL` unless the engine deleted past the word
+fn replay(method: &str, options: EngineOptions, keys: &str) -> String {
+    let mut engine = Engine::new();
+    engine.set_method(method);
+    engine.set_options(options);
+
+    let mut field = TextField::with_text(SENTINEL);
+    let mut rest = keys;
+    while let Some(key) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("{BS}") {
+            field.press_backspace(&mut engine);
+            rest = after;
+        } else {
+            field.type_key(&mut engine, key);
+            rest = &rest[key.len_utf8()..];
+        }
+    }
+    field.text().to_string()
+}
+
+/// Run every case in a corpus file, reporting all mismatches at once
+fn check_corpus(name: &str, text: &str, method: &str) {
+    let cases = parse_corpus(text, method);
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|case| {
+            let actual = replay(&case.method, case.options, &case.keys);
+            let expected = format!("{}{}", SENTINEL, case.expected);
+            (actual != expected).then(|| {
+                format!(
+                    "{}:{}: {:?} → {:?}, expected {:?}",
+                    name, case.line, case.keys, actual, expected
+                )
+            })
+        })
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_telex_corpus() {
+    check_corpus("telex.txt", include_str!("data/telex.txt"), "telex");
+}
+
+#[test]
+fn test_vni_corpus() {
+    check_corpus("vni.txt", include_str!("data/vni.txt"), "vni");
+}
+
+#[test]
+fn test_viqr_corpus() {
+    check_corpus("viqr.txt", include_str!("data/viqr.txt"), "viqr");
+}
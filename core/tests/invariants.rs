@@ -0,0 +1,128 @@
+//! Engine invariants
+//!
+//! Random key streams for Telex and VNI, checked after every key against
+//! a simulated text field:
+//! - a backspace count never reaches past what the engine emitted
+//! - after a Restore the field ends with the raw buffer plus the boundary
+//!   key
+//! - the field ends with the active word as `get_buffer` reports it
+
+mod common;
+
+use common::TextField;
+use proptest::prelude::*;
+use vietflux_core::convert::BACKSPACE;
+use vietflux_core::engine::Action;
+use vietflux_core::{Engine, EngineOptions, OutputEncoding};
+
+/// Text already in the field; the engine must never delete it
+const SENTINEL: &str = "»";
+
+const TELEX_KEYS: &str = "aeiouydwsfrxjzcnghtqpkmlvb[]AEODW .,#'\"!";
+const VNI_KEYS: &str = "aeiouydcnghtqpkmlvb0123456789AEOD .,#'\"!";
+
+fn key_stream(alphabet: &'static str) -> impl Strategy<Value = Vec<char>> {
+    let keys: Vec<char> = alphabet.chars().chain([BACKSPACE]).collect();
+    prop::collection::vec(prop::sample::select(keys), 0..24)
+}
+
+fn engine_options() -> impl Strategy<Value = EngineOptions> {
+    (
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        prop::sample::select(vec![
+            OutputEncoding::Nfc,
+            OutputEncoding::Nfd,
+            OutputEncoding::VniWindows,
+        ]),
+    )
+        .prop_map(
+            |(
+                auto_capitalize,
+                smart_quotes,
+                modern_tone,
+                quick_telex,
+                bracket_shortcuts,
+                output_encoding,
+            )| EngineOptions {
+                auto_capitalize,
+                smart_quotes,
+                modern_tone,
+                quick_telex,
+                bracket_shortcuts,
+                output_encoding,
+                ..EngineOptions::default()
+            },
+        )
+}
+
+/// Type `keys` and check the invariants after each one
+fn check_invariants(method: &str, options: EngineOptions, keys: &[char]) {
+    let mut engine = Engine::new();
+    engine.set_method(method);
+    engine.set_options(options);
+    let encoding = options.output_encoding;
+
+    let mut field = TextField::with_text(SENTINEL);
+    for (i, &key) in keys.iter().enumerate() {
+        // Backspace on an empty field would delete the sentinel itself
+        if key == BACKSPACE && field.text() == SENTINEL {
+            continue;
+        }
+        let raw = engine.get_raw_buffer();
+        let typed = field.text().chars().count() - SENTINEL.chars().count();
+
+        let result = if key == BACKSPACE {
+            field.press_backspace(&mut engine)
+        } else {
+            field.type_key(&mut engine, key)
+        };
+        let step: String = keys[..=i].iter().collect();
+
+        if matches!(result.action, Action::Update | Action::Restore) {
+            assert!(
+                result.backspace <= typed,
+                "{:?}: backspace {} with {} typed",
+                step,
+                result.backspace,
+                typed
+            );
+        }
+        if result.action == Action::Restore {
+            let expected = encoding.encode(&format!("{}{}", raw, key));
+            assert!(
+                field.text().ends_with(&expected),
+                "{:?}: field {:?}, restored {:?}",
+                step,
+                field.text(),
+                expected
+            );
+        }
+        assert!(
+            field
+                .text()
+                .ends_with(&encoding.encode(&engine.get_buffer())),
+            "{:?}: field {:?}, buffer {:?}",
+            step,
+            field.text(),
+            engine.get_buffer()
+        );
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn telex_invariants(options in engine_options(), keys in key_stream(TELEX_KEYS)) {
+        check_invariants("telex", options, &keys);
+    }
+
+    #[test]
+    fn vni_invariants(options in engine_options(), keys in key_stream(VNI_KEYS)) {
+        check_invariants("vni", options, &keys);
+    }
+}
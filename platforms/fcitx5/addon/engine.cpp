@@ -0,0 +1,130 @@
+/*
+ * VietFlux input method for Fcitx5
+ */
+
+#include "engine.h"
+
+#include <fcitx-utils/key.h>
+#include <fcitx-utils/utf8.h>
+#include <fcitx/inputcontext.h>
+#include <fcitx/inputcontextmanager.h>
+#include <fcitx/inputpanel.h>
+#include <fcitx/text.h>
+
+#include <string>
+
+namespace fcitx {
+
+VietFluxEngine::VietFluxEngine(Instance *instance)
+    : instance_(instance),
+      factory_([](InputContext &) { return new VietFluxState; }) {
+    instance_->inputContextManager().registerProperty("vietfluxState",
+                                                      &factory_);
+}
+
+VietFluxContext *VietFluxEngine::context(InputContext *ic) {
+    return ic->propertyFor(&factory_)->context();
+}
+
+// Pick up settings saved by the app since the last time
+void VietFluxEngine::activate(const InputMethodEntry &,
+                              InputContextEvent &event) {
+    vietflux_reload_config(context(event.inputContext()));
+}
+
+void VietFluxEngine::deactivate(const InputMethodEntry &,
+                                InputContextEvent &event) {
+    commit(event.inputContext());
+}
+
+// Focus out and caret moves: keep the word as it was shown
+void VietFluxEngine::reset(const InputMethodEntry &, InputContextEvent &event) {
+    commit(event.inputContext());
+}
+
+void VietFluxEngine::keyEvent(const InputMethodEntry &, KeyEvent &keyEvent) {
+    if (keyEvent.isRelease()) {
+        return;
+    }
+    auto *ic = keyEvent.inputContext();
+    auto *state = context(ic);
+    const Key &key = keyEvent.rawKey();
+
+    // Shortcuts, Enter, arrows...: finish the word, then let the key
+    // through to the application
+    if (key.states().testAny(
+            KeyStates{KeyState::Ctrl, KeyState::Alt, KeyState::Super})) {
+        commit(ic);
+        return;
+    }
+
+    bool used = false;
+    uint32_t unicode = Key::keySymToUnicode(key.sym());
+    if (key.check(FcitxKey_BackSpace)) {
+        used = vietflux_process_backspace(state);
+    } else if (unicode >= 0x20 && unicode != 0x7f) {
+        syncSurroundingText(ic);
+        used = vietflux_process_key(state, unicode,
+                                    key.states().test(KeyState::Shift));
+    } else {
+        commit(ic);
+        return;
+    }
+
+    updateUI(ic);
+    if (used) {
+        keyEvent.filterAndAccept();
+    }
+}
+
+// Before a new word starts, tell the engine what precedes the caret
+void VietFluxEngine::syncSurroundingText(InputContext *ic) {
+    auto *state = context(ic);
+    if (*vietflux_preedit_text(state) != '\0' ||
+        !ic->capabilityFlags().test(CapabilityFlag::SurroundingText)) {
+        return;
+    }
+    const auto &surrounding = ic->surroundingText();
+    if (!surrounding.isValid()) {
+        return;
+    }
+    const auto &text = surrounding.text();
+    auto cursor = surrounding.cursor();
+    if (cursor > utf8::length(text)) {
+        return;
+    }
+    auto bytes = utf8::ncharByteLength(text.begin(), cursor);
+    vietflux_set_text_before_cursor(state, text.substr(0, bytes).c_str());
+}
+
+void VietFluxEngine::commit(InputContext *ic) {
+    vietflux_commit(context(ic));
+    updateUI(ic);
+}
+
+void VietFluxEngine::updateUI(InputContext *ic) {
+    auto *state = context(ic);
+
+    std::string committed = vietflux_commit_text(state);
+    if (!committed.empty()) {
+        ic->commitString(committed);
+    }
+
+    Text preedit;
+    std::string composing = vietflux_preedit_text(state);
+    if (!composing.empty()) {
+        preedit.append(composing, TextFormatFlag::Underline);
+        preedit.setCursor(vietflux_preedit_cursor(state));
+    }
+    if (ic->capabilityFlags().test(CapabilityFlag::Preedit)) {
+        ic->inputPanel().setClientPreedit(preedit);
+    } else {
+        ic->inputPanel().setPreedit(preedit);
+    }
+    ic->updatePreedit();
+    ic->updateUserInterface(UserInterfaceComponent::InputPanel);
+}
+
+} // namespace fcitx
+
+FCITX_ADDON_FACTORY(fcitx::VietFluxEngineFactory);
@@ -0,0 +1,67 @@
+/*
+ * VietFlux input method for Fcitx5
+ *
+ * Thin addon over the Rust shim (include/vietflux.h): every input context
+ * gets its own engine, the word being typed is shown as preedit and the
+ * surrounding text tells the engine where the caret is in the sentence.
+ */
+
+#ifndef FCITX5_VIETFLUX_ENGINE_H
+#define FCITX5_VIETFLUX_ENGINE_H
+
+#include <fcitx/addonfactory.h>
+#include <fcitx/addonmanager.h>
+#include <fcitx/inputcontextproperty.h>
+#include <fcitx/inputmethodengine.h>
+#include <fcitx/instance.h>
+
+#include "vietflux.h"
+
+namespace fcitx {
+
+class VietFluxState : public InputContextProperty {
+public:
+    VietFluxState() : context_(vietflux_context_new()) {}
+    ~VietFluxState() { vietflux_context_free(context_); }
+
+    VietFluxState(const VietFluxState &) = delete;
+    VietFluxState &operator=(const VietFluxState &) = delete;
+
+    VietFluxContext *context() const { return context_; }
+
+private:
+    VietFluxContext *context_;
+};
+
+class VietFluxEngine : public InputMethodEngineV2 {
+public:
+    explicit VietFluxEngine(Instance *instance);
+
+    void activate(const InputMethodEntry &entry,
+                  InputContextEvent &event) override;
+    void deactivate(const InputMethodEntry &entry,
+                    InputContextEvent &event) override;
+    void keyEvent(const InputMethodEntry &entry, KeyEvent &keyEvent) override;
+    void reset(const InputMethodEntry &entry,
+               InputContextEvent &event) override;
+
+private:
+    VietFluxContext *context(InputContext *ic);
+    void syncSurroundingText(InputContext *ic);
+    void commit(InputContext *ic);
+    void updateUI(InputContext *ic);
+
+    Instance *instance_;
+    FactoryFor<VietFluxState> factory_;
+};
+
+class VietFluxEngineFactory : public AddonFactory {
+public:
+    AddonInstance *create(AddonManager *manager) override {
+        return new VietFluxEngine(manager->instance());
+    }
+};
+
+} // namespace fcitx
+
+#endif // FCITX5_VIETFLUX_ENGINE_H
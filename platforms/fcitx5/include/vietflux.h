@@ -0,0 +1,48 @@
+/*
+ * VietFlux C ABI (implemented in src/lib.rs)
+ *
+ * One VietFluxContext per input context. After each call the context
+ * holds the text to commit and the preedit to show; both strings are
+ * UTF-8 and stay valid until the next call on the same context.
+ */
+
+#ifndef VIETFLUX_H
+#define VIETFLUX_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct VietFluxContext VietFluxContext;
+
+/* Create a context configured from the VietFlux app's saved settings */
+VietFluxContext *vietflux_context_new(void);
+void vietflux_context_free(VietFluxContext *context);
+
+/* Reload the app's settings; false if the file is invalid (old ones kept) */
+bool vietflux_reload_config(VietFluxContext *context);
+
+/* Surrounding text up to the caret; ignored while composing */
+void vietflux_set_text_before_cursor(VietFluxContext *context, const char *text);
+
+/* Returns true if the key was used and must not reach the application */
+bool vietflux_process_key(VietFluxContext *context, uint32_t unicode, bool shift);
+bool vietflux_process_backspace(VietFluxContext *context);
+
+/* Commit the word being composed as shown */
+void vietflux_commit(VietFluxContext *context);
+
+const char *vietflux_commit_text(const VietFluxContext *context);
+const char *vietflux_preedit_text(const VietFluxContext *context);
+/* Caret in the preedit, in bytes */
+size_t vietflux_preedit_cursor(const VietFluxContext *context);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* VIETFLUX_H */
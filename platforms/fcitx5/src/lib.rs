@@ -0,0 +1,276 @@
+//! VietFlux Fcitx5 Shim
+//!
+//! C ABI over `vietflux_core::Engine` for the Fcitx5 addon in `addon/`
+//! (declared in `include/vietflux.h`). Each Fcitx input context owns one
+//! `VietFluxContext`. After every call the context holds:
+//! - the text to commit (empty if none)
+//! - the preedit to show, with its caret as a UTF-8 byte offset as
+//!   `fcitx::Text::setCursor` expects
+//!
+//! Both strings stay valid until the next call on the same context.
+
+use std::ffi::{c_char, CStr, CString};
+use vietflux_core::config::{config_path, load_config};
+use vietflux_core::{Engine, PreeditEvent};
+
+/// The VietFlux app's config file, relative to the user's config directory
+const APP_CONFIG: &str = "dev.vietflux.app/config.json";
+
+/// Engine state of one Fcitx input context
+pub struct VietFluxContext {
+    engine: Engine,
+    commit: CString,
+    preedit: CString,
+    cursor: usize,
+}
+
+impl VietFluxContext {
+    fn new() -> Self {
+        let mut context = Self {
+            engine: Engine::new(),
+            commit: CString::default(),
+            preedit: CString::default(),
+            cursor: 0,
+        };
+        context.reload_config();
+        context
+    }
+
+    /// Apply the app's saved configuration; false if it could not be used
+    fn reload_config(&mut self) -> bool {
+        let config = match config_path(APP_CONFIG).map_or(Ok(None), |path| load_config(&path)) {
+            Ok(Some(config)) => config,
+            Ok(None) => return true,
+            Err(_) => return false,
+        };
+        match Engine::from_config(&config) {
+            Ok(engine) => {
+                self.engine = engine;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Record the outcome of one call; returns whether anything changed
+    fn apply(&mut self, events: Vec<PreeditEvent>) -> bool {
+        let mut commit = String::new();
+        let mut preedit = None;
+        let used = !events.is_empty();
+        for event in events {
+            match event {
+                PreeditEvent::Commit { text } => {
+                    commit.push_str(&text);
+                    preedit = Some((String::new(), 0));
+                }
+                PreeditEvent::PreeditUpdate { text, cursor } => {
+                    let bytes = text.chars().take(cursor).map(char::len_utf8).sum();
+                    preedit = Some((text, bytes));
+                }
+                PreeditEvent::Cancel => preedit = Some((String::new(), 0)),
+            }
+        }
+        self.commit = to_c_string(commit);
+        if let Some((text, cursor)) = preedit {
+            self.preedit = to_c_string(text);
+            self.cursor = cursor;
+        }
+        used
+    }
+}
+
+fn to_c_string(text: String) -> CString {
+    CString::new(text).unwrap_or_default()
+}
+
+/// Create a context configured from the app's saved settings
+#[no_mangle]
+pub extern "C" fn vietflux_context_new() -> *mut VietFluxContext {
+    Box::into_raw(Box::new(VietFluxContext::new()))
+}
+
+/// Free a context
+///
+/// # Safety
+/// `context` must come from `vietflux_context_new` and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_context_free(context: *mut VietFluxContext) {
+    if !context.is_null() {
+        drop(Box::from_raw(context));
+    }
+}
+
+/// Reload the app's saved settings, keeping the old ones if the file is
+/// invalid; returns false in that case
+///
+/// # Safety
+/// `context` must be a live context.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_reload_config(context: *mut VietFluxContext) -> bool {
+    (*context).reload_config()
+}
+
+/// Pass the surrounding text up to the caret (UTF-8)
+///
+/// Ignored while a word is being composed.
+///
+/// # Safety
+/// `context` must be a live context and `text` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_set_text_before_cursor(
+    context: *mut VietFluxContext,
+    text: *const c_char,
+) {
+    if let Ok(text) = CStr::from_ptr(text).to_str() {
+        (*context).engine.set_text_before_cursor(text);
+    }
+}
+
+/// Process a key producing `unicode`; returns true if it was used and must
+/// not reach the application
+///
+/// # Safety
+/// `context` must be a live context.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_process_key(
+    context: *mut VietFluxContext,
+    unicode: u32,
+    shift: bool,
+) -> bool {
+    let context = &mut *context;
+    let Some(key) = char::from_u32(unicode) else {
+        return context.apply(Vec::new());
+    };
+    let events = context.engine.process_key_preedit(key, shift);
+    context.apply(events)
+}
+
+/// Process Backspace; returns true if it edited the preedit
+///
+/// # Safety
+/// `context` must be a live context.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_process_backspace(context: *mut VietFluxContext) -> bool {
+    let context = &mut *context;
+    let events = context.engine.process_backspace_preedit();
+    context.apply(events)
+}
+
+/// Commit the word being composed as shown (focus lost, caret moved, a
+/// key the engine does not handle)
+///
+/// # Safety
+/// `context` must be a live context.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_commit(context: *mut VietFluxContext) {
+    let context = &mut *context;
+    let events = context.engine.commit_preedit();
+    context.apply(events);
+}
+
+/// Text to commit after the last call, empty if none
+///
+/// # Safety
+/// `context` must be a live context.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_commit_text(context: *const VietFluxContext) -> *const c_char {
+    (*context).commit.as_ptr()
+}
+
+/// Preedit to show, empty if none
+///
+/// # Safety
+/// `context` must be a live context.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_preedit_text(context: *const VietFluxContext) -> *const c_char {
+    (*context).preedit.as_ptr()
+}
+
+/// Caret in the preedit, in bytes
+///
+/// # Safety
+/// `context` must be a live context.
+#[no_mangle]
+pub unsafe extern "C" fn vietflux_preedit_cursor(context: *const VietFluxContext) -> usize {
+    (*context).cursor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vietflux_core::EngineConfig;
+
+    struct Context(*mut VietFluxContext);
+
+    impl Context {
+        fn new() -> Self {
+            let context = vietflux_context_new();
+            let mut config = EngineConfig::default();
+            config.options.auto_capitalize = false;
+            unsafe { (*context).engine = Engine::from_config(&config).unwrap() };
+            Self(context)
+        }
+
+        fn type_text(&self, text: &str) -> bool {
+            text.chars()
+                .all(|ch| unsafe { vietflux_process_key(self.0, ch as u32, ch.is_uppercase()) })
+        }
+
+        fn commit(&self) -> &str {
+            unsafe { CStr::from_ptr(vietflux_commit_text(self.0)) }
+                .to_str()
+                .unwrap()
+        }
+
+        fn preedit(&self) -> (&str, usize) {
+            let text = unsafe { CStr::from_ptr(vietflux_preedit_text(self.0)) };
+            let cursor = unsafe { vietflux_preedit_cursor(self.0) };
+            (text.to_str().unwrap(), cursor)
+        }
+    }
+
+    impl Drop for Context {
+        fn drop(&mut self) {
+            unsafe { vietflux_context_free(self.0) }
+        }
+    }
+
+    #[test]
+    fn test_preedit_and_commit() {
+        let context = Context::new();
+        assert!(context.type_text("vieetj"));
+        assert_eq!(context.preedit(), ("việt", "việt".len()));
+        assert_eq!(context.commit(), "");
+
+        assert!(context.type_text(" "));
+        assert_eq!(context.commit(), "việt ");
+        assert_eq!(context.preedit(), ("", 0));
+
+        // Nothing composed: the application gets the key
+        assert!(!context.type_text(" "));
+        assert_eq!(context.commit(), "");
+
+        assert!(context.type_text("ddi"));
+        assert!(unsafe { vietflux_process_backspace(context.0) });
+        assert_eq!(context.preedit(), ("đ", 2));
+        unsafe { vietflux_commit(context.0) };
+        assert_eq!(context.commit(), "đ");
+        assert_eq!(context.preedit(), ("", 0));
+    }
+
+    #[test]
+    fn test_text_before_cursor() {
+        let context = Context::new();
+        unsafe {
+            (*context.0)
+                .engine
+                .set_options(vietflux_core::EngineOptions {
+                    auto_capitalize: true,
+                    ..Default::default()
+                });
+            vietflux_set_text_before_cursor(context.0, c"Xin chào ".as_ptr());
+        }
+        assert!(context.type_text("ban"));
+        assert_eq!(context.preedit().0, "ban");
+    }
+}
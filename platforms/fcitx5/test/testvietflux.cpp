@@ -0,0 +1,132 @@
+/*
+ * Load the addon in a headless Fcitx5 instance (testfrontend, testim,
+ * testui) and type through it.
+ */
+
+#include "testdir.h"
+#include "testfrontend_public.h"
+
+#include <fcitx-utils/eventdispatcher.h>
+#include <fcitx-utils/key.h>
+#include <fcitx-utils/log.h>
+#include <fcitx-utils/testing.h>
+#include <fcitx/addonmanager.h>
+#include <fcitx/inputcontext.h>
+#include <fcitx/inputcontextmanager.h>
+#include <fcitx/inputmethodgroup.h>
+#include <fcitx/inputmethodmanager.h>
+#include <fcitx/inputpanel.h>
+#include <fcitx/instance.h>
+
+#include <cstdlib>
+#include <filesystem>
+#include <fstream>
+#include <string>
+
+using namespace fcitx;
+
+// The addon reads the settings saved by the VietFlux app
+void writeAppConfig() {
+    std::filesystem::path home = TESTING_BINARY_DIR "/test/config";
+    std::filesystem::create_directories(home / "dev.vietflux.app");
+    std::ofstream(home / "dev.vietflux.app" / "config.json")
+        << R"({ "method": "vni", "options": { "auto_capitalize": true } })";
+    setenv("XDG_CONFIG_HOME", home.c_str(), 1);
+}
+
+void scheduleEvent(EventDispatcher *dispatcher, Instance *instance) {
+    dispatcher->schedule([instance]() {
+        auto *vietflux = instance->addonManager().addon("vietflux", true);
+        FCITX_ASSERT(vietflux);
+
+        auto group = instance->inputMethodManager().currentGroup();
+        group.inputMethodList().clear();
+        group.inputMethodList().push_back(InputMethodGroupItem("keyboard-us"));
+        group.inputMethodList().push_back(InputMethodGroupItem("vietflux"));
+        group.setDefaultInputMethod("");
+        instance->inputMethodManager().setGroup(group);
+
+        auto *testfrontend = instance->addonManager().addon("testfrontend");
+        auto uuid =
+            testfrontend->call<ITestFrontend::createInputContext>("testapp");
+        auto *ic = instance->inputContextManager().findByUUID(uuid);
+        FCITX_ASSERT(ic);
+        FCITX_ASSERT(testfrontend->call<ITestFrontend::sendKeyEvent>(
+            uuid, Key("Control+space"), false));
+        FCITX_ASSERT(instance->inputMethod(ic) == "vietflux");
+
+        auto press = [testfrontend, uuid](const Key &key) {
+            return testfrontend->call<ITestFrontend::sendKeyEvent>(uuid, key,
+                                                                   false);
+        };
+        auto type = [&press](const std::string &keys) {
+            for (char key : keys) {
+                FCITX_ASSERT(press(Key(static_cast<KeySym>(key))));
+            }
+        };
+        auto preedit = [ic]() {
+            auto &panel = ic->inputPanel();
+            return ic->capabilityFlags().test(CapabilityFlag::Preedit)
+                       ? panel.clientPreedit().toString()
+                       : panel.preedit().toString();
+        };
+
+        // VNI from the app's config, composed as preedit
+        type("vie65t");
+        FCITX_ASSERT(preedit() == "Việt") << preedit();
+        testfrontend->call<ITestFrontend::pushCommitExpectation>("Việt ");
+        type(" ");
+        FCITX_ASSERT(preedit().empty());
+
+        // Nothing composed: the key goes to the application
+        FCITX_ASSERT(!press(Key(FcitxKey_space)));
+
+        // Surrounding text puts the caret after a full stop
+        ic->setCapabilityFlags(CapabilityFlag::SurroundingText);
+        ic->surroundingText().setText("Xong. ", 6, 6);
+        ic->updateSurroundingText();
+        testfrontend->call<ITestFrontend::pushCommitExpectation>("Ok ");
+        type("ok ");
+
+        // Keys without text commit the word and pass through
+        ic->surroundingText().setText("Xong. Ok ", 9, 9);
+        ic->updateSurroundingText();
+        type("d9");
+        testfrontend->call<ITestFrontend::pushCommitExpectation>("đ");
+        FCITX_ASSERT(!press(Key(FcitxKey_Return)));
+
+        // Backspace edits the preedit
+        type("ba1");
+        FCITX_ASSERT(press(Key(FcitxKey_BackSpace)));
+        FCITX_ASSERT(preedit() == "b") << preedit();
+
+        // Losing focus keeps the word
+        testfrontend->call<ITestFrontend::pushCommitExpectation>("b");
+        ic->focusOut();
+        FCITX_ASSERT(preedit().empty());
+
+        testfrontend->call<ITestFrontend::destroyInputContext>(uuid);
+    });
+    dispatcher->schedule([dispatcher, instance]() {
+        dispatcher->detach();
+        instance->exit();
+    });
+}
+
+int main() {
+    setupTestingEnvironment(TESTING_BINARY_DIR, {"addon"}, {"test"});
+    writeAppConfig();
+
+    char arg0[] = "testvietflux";
+    char arg1[] = "--disable=all";
+    char arg2[] = "--enable=testim,testfrontend,testui,vietflux";
+    char *argv[] = {arg0, arg1, arg2};
+    fcitx::Log::setLogRule("default=5,vietflux=5");
+    Instance instance(FCITX_ARRAY_SIZE(argv), argv);
+    instance.addonManager().registerDefaultLoader(nullptr);
+    EventDispatcher dispatcher;
+    dispatcher.attach(&instance.eventLoop());
+    scheduleEvent(&dispatcher, &instance);
+    instance.exec();
+    return 0;
+}
@@ -0,0 +1,23 @@
+//! Settings shared with the VietFlux app
+//!
+//! The desktop app saves its engine configuration under its bundle
+//! identifier in the user's config directory; the IBus engine reads the
+//! same file (or `$VIETFLUX_CONFIG`) so both type the same way.
+
+use vietflux_core::config::{config_path, load_config};
+use vietflux_core::EngineConfig;
+
+/// The app's config file, relative to the user's config directory
+const APP_CONFIG: &str = "dev.vietflux.app/config.json";
+
+/// Load the saved configuration, falling back to defaults when the file
+/// is missing or invalid
+pub fn load() -> EngineConfig {
+    config_path(APP_CONFIG)
+        .map_or(Ok(None), |path| load_config(&path))
+        .unwrap_or_else(|e| {
+            eprintln!("Ignoring config: {}", e);
+            None
+        })
+        .unwrap_or_default()
+}
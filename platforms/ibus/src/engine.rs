@@ -0,0 +1,280 @@
+//! IBus engine objects
+//!
+//! ibus-daemon asks the factory for an engine per input context and then
+//! forwards key events to it. Every engine is one context of a shared
+//! `ContextManager`, and the word being typed is shown as an underlined
+//! preedit string until it is committed.
+
+use crate::{config, keysym, text};
+use std::sync::{Arc, Mutex};
+use vietflux_core::{ContextId, ContextManager, PreeditEvent};
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedObjectPath, Value};
+use zbus::{fdo, interface, ObjectServer};
+
+/// Bus name of the component (see vietflux.xml)
+pub const BUS_NAME: &str = "org.freedesktop.IBus.VietFlux";
+/// Object path of the engine factory
+pub const FACTORY_PATH: &str = "/org/freedesktop/IBus/Factory";
+/// Engine name registered in vietflux.xml
+pub const ENGINE_NAME: &str = "vietflux";
+
+/// IBUS_ENGINE_PREEDIT_COMMIT: the client commits the preedit when it
+/// loses focus, so the word is not lost
+const PREEDIT_COMMIT: u32 = 1;
+
+type Contexts = Arc<Mutex<ContextManager>>;
+
+/// org.freedesktop.IBus.Factory
+pub struct Factory {
+    contexts: Contexts,
+    last_id: ContextId,
+}
+
+impl Factory {
+    pub fn new(contexts: ContextManager) -> Self {
+        Self {
+            contexts: Arc::new(Mutex::new(contexts)),
+            last_id: 0,
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.IBus.Factory")]
+impl Factory {
+    /// Create an engine object and return its path
+    async fn create_engine(
+        &mut self,
+        name: &str,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> fdo::Result<OwnedObjectPath> {
+        if name != ENGINE_NAME {
+            return Err(fdo::Error::InvalidArgs(format!("Unknown engine: {}", name)));
+        }
+        self.last_id += 1;
+        let id = self.last_id;
+        let path = OwnedObjectPath::try_from(format!("/org/freedesktop/IBus/Engine/{}", id))
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+
+        let contexts = Arc::clone(&self.contexts);
+        server
+            .at(&path, Service::new(Arc::clone(&contexts), id))
+            .await?;
+        server.at(&path, InputEngine::new(contexts, id)).await?;
+        Ok(path)
+    }
+}
+
+/// org.freedesktop.IBus.Service: lets ibus-daemon destroy an engine
+pub struct Service {
+    contexts: Contexts,
+    id: ContextId,
+}
+
+impl Service {
+    fn new(contexts: Contexts, id: ContextId) -> Self {
+        Self { contexts, id }
+    }
+}
+
+#[interface(name = "org.freedesktop.IBus.Service")]
+impl Service {
+    async fn destroy(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> fdo::Result<()> {
+        self.contexts.lock().unwrap().remove(self.id);
+        if let Some(path) = header.path() {
+            server.remove::<InputEngine, _>(path).await?;
+            server.remove::<Service, _>(path).await?;
+        }
+        Ok(())
+    }
+}
+
+/// org.freedesktop.IBus.Engine for one input context
+pub struct InputEngine {
+    contexts: Contexts,
+    id: ContextId,
+    content_type: (u32, u32),
+}
+
+impl InputEngine {
+    fn new(contexts: Contexts, id: ContextId) -> Self {
+        Self {
+            contexts,
+            id,
+            content_type: (0, 0),
+        }
+    }
+
+    /// Run a key through the context; returns the preedit events and
+    /// whether the key was used
+    fn handle_key(&self, keyval: u32, state: u32) -> (Vec<PreeditEvent>, bool) {
+        if state & keysym::RELEASE_MASK != 0 {
+            return (Vec::new(), false);
+        }
+        let mut contexts = self.contexts.lock().unwrap();
+
+        // Shortcuts, Enter, arrows...: finish the word, then let the key
+        // through to the application
+        if state & keysym::SHORTCUT_MASK != 0 {
+            return (contexts.commit_preedit(self.id), false);
+        }
+        let events = if keyval == keysym::BACKSPACE {
+            contexts.process_backspace_preedit(self.id)
+        } else if let Some(ch) = keysym::to_char(keyval) {
+            let shift = state & keysym::SHIFT_MASK != 0;
+            contexts.process_key_preedit(self.id, ch, shift)
+        } else {
+            return (contexts.commit_preedit(self.id), false);
+        };
+        let used = !events.is_empty();
+        (events, used)
+    }
+
+    /// Drop the word being typed without signals; with PREEDIT_COMMIT the
+    /// client has already committed what it showed
+    fn forget_word(&self) {
+        self.contexts.lock().unwrap().commit_preedit(self.id);
+    }
+
+    async fn emit(emitter: &SignalEmitter<'_>, events: Vec<PreeditEvent>) -> zbus::Result<()> {
+        for event in events {
+            match event {
+                PreeditEvent::PreeditUpdate { text, cursor } => {
+                    let cursor = cursor as u32;
+                    Self::update_preedit_text(
+                        emitter,
+                        text::underlined(&text),
+                        cursor,
+                        true,
+                        PREEDIT_COMMIT,
+                    )
+                    .await?;
+                }
+                PreeditEvent::Commit { text } => {
+                    Self::hide_preedit(emitter).await?;
+                    Self::commit_text(emitter, text::plain(&text)).await?;
+                }
+                PreeditEvent::Cancel => Self::hide_preedit(emitter).await?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn hide_preedit(emitter: &SignalEmitter<'_>) -> zbus::Result<()> {
+        Self::update_preedit_text(emitter, text::plain(""), 0, false, PREEDIT_COMMIT).await
+    }
+}
+
+#[interface(name = "org.freedesktop.IBus.Engine")]
+impl InputEngine {
+    /// Returns true if the key was used and must not reach the application
+    async fn process_key_event(
+        &self,
+        keyval: u32,
+        _keycode: u32,
+        state: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<bool> {
+        let (events, used) = self.handle_key(keyval, state);
+        Self::emit(&emitter, events).await?;
+        Ok(used)
+    }
+
+    fn focus_in(&self) {}
+
+    fn focus_out(&self) {
+        self.forget_word();
+    }
+
+    fn focus_in_id(&self, _object_path: &str, _client: &str) {}
+
+    fn focus_out_id(&self, _object_path: &str) {
+        self.forget_word();
+    }
+
+    fn reset(&self) {
+        self.forget_word();
+    }
+
+    /// Pick up settings saved by the app since the last time
+    fn enable(&self) {
+        if let Err(e) = self.contexts.lock().unwrap().set_config(config::load()) {
+            eprintln!("Ignoring config: {}", e);
+        }
+    }
+
+    fn disable(&self) {
+        self.forget_word();
+    }
+
+    fn set_capabilities(&self, _caps: u32) {}
+
+    fn set_cursor_location(&self, _x: i32, _y: i32, _w: i32, _h: i32) {}
+
+    /// Capitalization and smart quotes follow the text before the caret
+    fn set_surrounding_text(&self, text: Value<'_>, cursor_pos: u32, _anchor_pos: u32) {
+        let Some(text) = text::text_of(&text) else {
+            return;
+        };
+        let before: String = text.chars().take(cursor_pos as usize).collect();
+        self.contexts
+            .lock()
+            .unwrap()
+            .set_text_before_cursor(self.id, &before);
+    }
+
+    fn page_up(&self) {}
+
+    fn page_down(&self) {}
+
+    fn cursor_up(&self) {}
+
+    fn cursor_down(&self) {}
+
+    fn candidate_clicked(&self, _index: u32, _button: u32, _state: u32) {}
+
+    fn property_activate(&self, _name: &str, _state: u32) {}
+
+    fn property_show(&self, _name: &str) {}
+
+    fn property_hide(&self, _name: &str) {}
+
+    /// Focus changes arrive as FocusIn/FocusOut rather than the Id variants
+    #[zbus(property)]
+    fn focus_id(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn active_surrounding_text(&self) -> bool {
+        true
+    }
+
+    /// Input purpose and hints of the focused field
+    #[zbus(property)]
+    fn content_type(&self) -> (u32, u32) {
+        self.content_type
+    }
+
+    #[zbus(property)]
+    fn set_content_type(&mut self, content_type: (u32, u32)) {
+        self.content_type = content_type;
+    }
+
+    #[zbus(signal)]
+    async fn commit_text(emitter: &SignalEmitter<'_>, text: Value<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn update_preedit_text(
+        emitter: &SignalEmitter<'_>,
+        text: Value<'_>,
+        cursor_pos: u32,
+        visible: bool,
+        mode: u32,
+    ) -> zbus::Result<()>;
+}
@@ -0,0 +1,45 @@
+//! X11 keysyms and modifier masks as IBus sends them
+
+/// Backspace key
+pub const BACKSPACE: u32 = 0xff08;
+
+/// Shift modifier
+pub const SHIFT_MASK: u32 = 1 << 0;
+/// Ctrl modifier
+pub const CONTROL_MASK: u32 = 1 << 2;
+/// Alt modifier
+pub const MOD1_MASK: u32 = 1 << 3;
+/// Super (Windows) modifier
+pub const SUPER_MASK: u32 = 1 << 26;
+/// Set on key release events
+pub const RELEASE_MASK: u32 = 1 << 30;
+
+/// Modifiers that turn a key into a shortcut rather than text
+pub const SHORTCUT_MASK: u32 = CONTROL_MASK | MOD1_MASK | SUPER_MASK;
+
+/// Character typed by a keysym, if it types one
+///
+/// Covers printable ASCII and Latin-1, which map to keysyms one to one,
+/// and the Unicode keysym range (0x0100_0000 + code point).
+pub fn to_char(keyval: u32) -> Option<char> {
+    match keyval {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keyval),
+        0x0100_0100..=0x0110_ffff => char::from_u32(keyval - 0x0100_0000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_char() {
+        assert_eq!(to_char(0x61), Some('a'));
+        assert_eq!(to_char(0x41), Some('A'));
+        assert_eq!(to_char(0x20), Some(' '));
+        assert_eq!(to_char(0x0100_1ea1), Some('ạ'));
+        assert_eq!(to_char(BACKSPACE), None);
+        assert_eq!(to_char(0xff0d), None); // Return
+    }
+}
@@ -0,0 +1,30 @@
+//! VietFlux IBus Engine
+//!
+//! D-Bus service implementing the IBus engine interface on top of
+//! `vietflux_core`. ibus-daemon starts `ibus-engine-vietflux --ibus` as
+//! listed in `vietflux.xml`; the process claims `BUS_NAME` on the IBus bus
+//! and serves an engine factory at `FACTORY_PATH`.
+//!
+//! Words are composed as preedit text, so applications see no backspace
+//! retyping.
+
+pub mod config;
+pub mod engine;
+pub mod keysym;
+pub mod text;
+
+pub use engine::{BUS_NAME, ENGINE_NAME, FACTORY_PATH};
+
+use vietflux_core::ContextManager;
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+
+/// Serve the engine factory on a connection
+///
+/// The builder chooses the bus (the IBus bus for the real engine, a
+/// peer-to-peer socket in tests).
+pub fn serve(builder: Builder<'_>, contexts: ContextManager) -> zbus::Result<Connection> {
+    builder
+        .serve_at(FACTORY_PATH, engine::Factory::new(contexts))?
+        .build()
+}
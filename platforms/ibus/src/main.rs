@@ -0,0 +1,38 @@
+//! ibus-engine-vietflux - IBus engine process
+//!
+//! Started by ibus-daemon (`--ibus`, see vietflux.xml). Connects to the
+//! IBus bus at `$IBUS_ADDRESS` or the address reported by `ibus address`
+//! and serves until the daemon goes away.
+
+use std::process::ExitCode;
+use vietflux_core::{ContextManager, EngineConfig};
+use zbus::blocking::connection::Builder;
+use zbus::blocking::MessageIterator;
+
+fn main() -> ExitCode {
+    let contexts = ContextManager::new(vietflux_ibus::config::load()).unwrap_or_else(|e| {
+        eprintln!("ibus-engine-vietflux: ignoring config: {}", e);
+        ContextManager::new(EngineConfig::default()).expect("default configuration is valid")
+    });
+
+    // ibus-daemon exports its address to the engines it starts
+    let builder = match std::env::var("IBUS_ADDRESS") {
+        Ok(address) if !address.is_empty() => Builder::address(address.as_str()),
+        _ => Builder::ibus(),
+    };
+    let connection = builder
+        .and_then(|builder| builder.name(vietflux_ibus::BUS_NAME))
+        .and_then(|builder| vietflux_ibus::serve(builder, contexts));
+    let connection = match connection {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("ibus-engine-vietflux: cannot connect to IBus: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // The object server answers calls in the background; the stream ends
+    // when the bus connection closes
+    for _ in MessageIterator::from(&connection) {}
+    ExitCode::SUCCESS
+}
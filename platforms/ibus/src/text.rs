@@ -0,0 +1,92 @@
+//! IBusText values
+//!
+//! IBus passes text as a serialized GObject: the struct
+//! `("IBusText", attachments, text, attributes)` inside a variant, where
+//! the attributes are an `IBusAttrList` of `IBusAttribute` structs.
+
+use std::collections::HashMap;
+use zbus::zvariant::{Array, Signature, StructureBuilder, Value};
+
+/// IBusAttribute type for underlines
+const ATTR_TYPE_UNDERLINE: u32 = 1;
+/// Single underline
+const ATTR_UNDERLINE_SINGLE: u32 = 1;
+
+/// Serialized object header: type name and (empty) attachments
+fn object(name: &str) -> StructureBuilder<'static> {
+    StructureBuilder::new()
+        .add_field(name.to_string())
+        .add_field(HashMap::<String, Value<'static>>::new())
+}
+
+/// Plain text, as used for commits
+pub fn plain(text: &str) -> Value<'static> {
+    build(text, Vec::new())
+}
+
+/// Text underlined from start to end, as used for the preedit
+pub fn underlined(text: &str) -> Value<'static> {
+    let len = text.chars().count() as u32;
+    let underline = object("IBusAttribute")
+        .add_field(ATTR_TYPE_UNDERLINE)
+        .add_field(ATTR_UNDERLINE_SINGLE)
+        .add_field(0u32)
+        .add_field(len)
+        .build()
+        .expect("attribute has fields");
+    build(text, vec![variant(underline.into())])
+}
+
+fn build(text: &str, attributes: Vec<Value<'static>>) -> Value<'static> {
+    let mut list = Array::new(&Signature::Variant);
+    for attribute in attributes {
+        list.append(attribute).expect("attributes are variants");
+    }
+    let attr_list = object("IBusAttrList")
+        .add_field(list)
+        .build()
+        .expect("attribute list has fields");
+    let text = object("IBusText")
+        .add_field(text.to_string())
+        .append_field(variant(attr_list.into()))
+        .build()
+        .expect("text has fields");
+    Value::from(text)
+}
+
+/// Wrap a serialized object in a variant
+fn variant(value: Value<'static>) -> Value<'static> {
+    Value::Value(Box::new(value))
+}
+
+/// String inside an IBusText value
+pub fn text_of(value: &Value<'_>) -> Option<String> {
+    let value = match value {
+        Value::Value(inner) => inner,
+        other => other,
+    };
+    let Value::Structure(text) = value else {
+        return None;
+    };
+    match text.fields().get(2)? {
+        Value::Str(s) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ibus_text_signature() {
+        let value = underlined("việt");
+        assert_eq!(
+            value.value_signature().to_string(),
+            "(sa{sv}sv)",
+            "IBusText must serialize as (sa{{sv}}sv)"
+        );
+        assert_eq!(text_of(&value).as_deref(), Some("việt"));
+        assert_eq!(text_of(&plain("")).as_deref(), Some(""));
+    }
+}
@@ -0,0 +1,252 @@
+//! Drive the engine over D-Bus the way ibus-daemon does
+//!
+//! The engine is served on one end of a peer-to-peer socket and the test
+//! plays the daemon on the other: create an engine through the factory,
+//! send ProcessKeyEvent calls and check the signals that come back.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+use vietflux_core::{ContextManager, EngineConfig};
+use vietflux_ibus::{keysym, text, ENGINE_NAME, FACTORY_PATH};
+use zbus::blocking::connection::Builder;
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::message::Type;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::Guid;
+
+const FACTORY: &str = "org.freedesktop.IBus.Factory";
+const ENGINE: &str = "org.freedesktop.IBus.Engine";
+const SERVICE: &str = "org.freedesktop.IBus.Service";
+
+#[derive(Debug, PartialEq)]
+enum Signal {
+    Preedit {
+        text: String,
+        cursor: u32,
+        visible: bool,
+    },
+    Commit(String),
+}
+
+fn preedit(text: &str) -> Signal {
+    Signal::Preedit {
+        text: text.to_string(),
+        cursor: text.chars().count() as u32,
+        visible: true,
+    }
+}
+
+fn hidden() -> Signal {
+    Signal::Preedit {
+        text: String::new(),
+        cursor: 0,
+        visible: false,
+    }
+}
+
+fn commit(text: &str) -> Signal {
+    Signal::Commit(text.to_string())
+}
+
+/// The daemon's side of the connection
+struct Daemon {
+    _server: Connection,
+    client: Connection,
+    messages: MessageIterator,
+}
+
+impl Daemon {
+    fn start() -> Self {
+        let mut config = EngineConfig::default();
+        config.options.auto_capitalize = false;
+        Self::start_with(config)
+    }
+
+    fn start_with(config: EngineConfig) -> Self {
+        let (server, client) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || {
+            let builder = Builder::async_io_unix_stream(server)
+                .server(Guid::generate())
+                .unwrap()
+                .p2p();
+            vietflux_ibus::serve(builder, ContextManager::new(config).unwrap()).unwrap()
+        });
+        let client = Builder::async_io_unix_stream(client).p2p().build().unwrap();
+        let messages = MessageIterator::from(&client);
+        Self {
+            _server: server.join().unwrap(),
+            client,
+            messages,
+        }
+    }
+
+    /// Call a method; returns the reply and the signals emitted before it
+    fn call<B>(
+        &mut self,
+        path: &str,
+        interface: &str,
+        method: &str,
+        body: &B,
+    ) -> (zbus::Result<zbus::Message>, Vec<Signal>)
+    where
+        B: zbus::export::serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        let reply = self
+            .client
+            .call_method(None::<&str>, path, Some(interface), method, body);
+        let serial = match &reply {
+            Ok(reply) => reply.header().reply_serial(),
+            Err(zbus::Error::MethodError(_, _, reply)) => reply.header().reply_serial(),
+            Err(e) => panic!("{} failed: {}", method, e),
+        };
+
+        let mut signals = Vec::new();
+        for message in &mut self.messages {
+            let message = message.unwrap();
+            let header = message.header();
+            if header.message_type() != Type::Signal {
+                if header.reply_serial() == serial {
+                    break;
+                }
+                continue;
+            }
+            let body = message.body();
+            match header.member().unwrap().as_str() {
+                "UpdatePreeditText" => {
+                    let (value, cursor, visible, _mode): (OwnedValue, u32, bool, u32) =
+                        body.deserialize().unwrap();
+                    signals.push(Signal::Preedit {
+                        text: text::text_of(&value).unwrap(),
+                        cursor,
+                        visible,
+                    });
+                }
+                "CommitText" => {
+                    let value: OwnedValue = body.deserialize().unwrap();
+                    signals.push(Signal::Commit(text::text_of(&value).unwrap()));
+                }
+                other => panic!("unexpected signal {}", other),
+            }
+        }
+        (reply, signals)
+    }
+
+    fn create_engine(&mut self) -> String {
+        let (reply, _) = self.call(FACTORY_PATH, FACTORY, "CreateEngine", &(ENGINE_NAME,));
+        let path: OwnedObjectPath = reply.unwrap().body().deserialize().unwrap();
+        path.to_string()
+    }
+
+    /// Press a key; returns whether the engine used it and its signals
+    fn press(&mut self, engine: &str, keyval: u32, state: u32) -> (bool, Vec<Signal>) {
+        let (reply, signals) = self.call(engine, ENGINE, "ProcessKeyEvent", &(keyval, 0u32, state));
+        (reply.unwrap().body().deserialize().unwrap(), signals)
+    }
+
+    /// Type text, returning the signals of all the keys
+    fn type_text(&mut self, engine: &str, text: &str) -> Vec<Signal> {
+        let mut signals = Vec::new();
+        for ch in text.chars() {
+            let state = if ch.is_uppercase() {
+                keysym::SHIFT_MASK
+            } else {
+                0
+            };
+            let (used, mut key_signals) = self.press(engine, ch as u32, state);
+            assert!(used, "{:?} should be used", ch);
+            signals.append(&mut key_signals);
+        }
+        signals
+    }
+}
+
+#[test]
+fn test_word_is_composed_as_preedit() {
+    let mut daemon = Daemon::start();
+    let engine = daemon.create_engine();
+
+    let signals = daemon.type_text(&engine, "vieetj");
+    assert_eq!(signals.last(), Some(&preedit("việt")));
+
+    // Space commits the word with the space
+    let signals = daemon.type_text(&engine, " ");
+    assert_eq!(signals, vec![hidden(), commit("việt ")]);
+
+    // Nothing composed: the key goes to the application
+    let (used, signals) = daemon.press(&engine, ' ' as u32, 0);
+    assert!(!used);
+    assert!(signals.is_empty());
+}
+
+#[test]
+fn test_backspace_and_non_text_keys() {
+    let mut daemon = Daemon::start();
+    let engine = daemon.create_engine();
+
+    daemon.type_text(&engine, "Dd");
+    let (used, signals) = daemon.press(&engine, keysym::BACKSPACE, 0);
+    assert!(used);
+    assert_eq!(signals, vec![hidden()]);
+    let (used, _) = daemon.press(&engine, keysym::BACKSPACE, 0);
+    assert!(!used, "nothing left to delete in the preedit");
+
+    // Key releases are ignored
+    daemon.type_text(&engine, "as");
+    let (used, signals) = daemon.press(&engine, 's' as u32, keysym::RELEASE_MASK);
+    assert!(!used);
+    assert!(signals.is_empty());
+
+    // Ctrl+key and keys without text commit the word and pass through
+    let (used, signals) = daemon.press(&engine, 'c' as u32, keysym::CONTROL_MASK);
+    assert!(!used);
+    assert_eq!(signals, vec![hidden(), commit("á")]);
+    daemon.type_text(&engine, "ow");
+    let (used, signals) = daemon.press(&engine, 0xff0d, 0);
+    assert!(!used);
+    assert_eq!(signals, vec![hidden(), commit("ơ")]);
+}
+
+#[test]
+fn test_engines_are_independent() {
+    let mut daemon = Daemon::start();
+    let first = daemon.create_engine();
+    let second = daemon.create_engine();
+    assert_ne!(first, second);
+
+    daemon.type_text(&first, "tieng");
+    let signals = daemon.type_text(&second, "vieet");
+    assert_eq!(signals.last(), Some(&preedit("viêt")));
+    let signals = daemon.type_text(&first, "s");
+    assert_eq!(signals.last(), Some(&preedit("tiéng")));
+
+    // Destroyed engines are gone from the bus
+    let (reply, _) = daemon.call(&second, SERVICE, "Destroy", &());
+    reply.unwrap();
+    let (reply, _) = daemon.call(&second, ENGINE, "ProcessKeyEvent", &(0x61u32, 0u32, 0u32));
+    assert!(reply.is_err());
+
+    let (reply, _) = daemon.call(FACTORY_PATH, FACTORY, "CreateEngine", &("pinyin",));
+    assert!(reply.is_err(), "only the vietflux engine is provided");
+}
+
+#[test]
+fn test_surrounding_text_sets_capitalization() {
+    let mut daemon = Daemon::start_with(EngineConfig::default());
+    let first = daemon.create_engine();
+    let second = daemon.create_engine();
+
+    let text = |s: &str| (text::plain(s), s.chars().count() as u32, 0u32);
+    daemon
+        .call(&first, ENGINE, "SetSurroundingText", &text("Xin chào "))
+        .0
+        .unwrap();
+    daemon
+        .call(&second, ENGINE, "SetSurroundingText", &text("Xong. "))
+        .0
+        .unwrap();
+
+    let signals = daemon.type_text(&first, "b");
+    assert_eq!(signals.last(), Some(&preedit("b")));
+    let signals = daemon.type_text(&second, "b");
+    assert_eq!(signals.last(), Some(&preedit("B")));
+}
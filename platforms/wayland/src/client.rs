@@ -0,0 +1,269 @@
+//! Wayland connection
+//!
+//! Binds `zwp_input_method_manager_v2` and `zwp_virtual_keyboard_manager_v1`
+//! on the first seat, grabs the keyboard while a text field is focused and
+//! replays keys the engine does not use through a virtual keyboard with
+//! the compositor's own keymap.
+//!
+//! Keys the engine uses do not auto-repeat; forwarded keys repeat in the
+//! application as usual.
+
+use crate::ime::{Ime, Update};
+use crate::keymap::Keymap;
+use std::collections::HashSet;
+use std::fmt;
+use std::os::fd::AsFd;
+use vietflux_core::Engine;
+use wayland_client::globals::{registry_queue_init, BindError, GlobalError, GlobalListContents};
+use wayland_client::protocol::wl_keyboard::KeyState;
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{
+    delegate_noop, ConnectError, Connection, Dispatch, DispatchError, QueueHandle, WEnum,
+};
+use wayland_protocols_misc::zwp_input_method_v2::client::{
+    zwp_input_method_keyboard_grab_v2::{self, ZwpInputMethodKeyboardGrabV2},
+    zwp_input_method_manager_v2::ZwpInputMethodManagerV2,
+    zwp_input_method_v2::{self, ZwpInputMethodV2},
+};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::{
+    zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
+    zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
+};
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// No Wayland display to connect to
+    Connect(ConnectError),
+    /// The compositor lacks a required global
+    Global(String),
+    /// The connection failed
+    Dispatch(String),
+    /// Another input method is already running on the seat
+    Unavailable,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(err) => write!(f, "cannot connect to Wayland: {}", err),
+            Self::Global(err) => write!(f, "compositor not supported: {}", err),
+            Self::Dispatch(err) => write!(f, "Wayland connection failed: {}", err),
+            Self::Unavailable => write!(f, "another input method is already running"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<GlobalError> for ClientError {
+    fn from(err: GlobalError) -> Self {
+        Self::Global(err.to_string())
+    }
+}
+
+impl From<BindError> for ClientError {
+    fn from(err: BindError) -> Self {
+        Self::Global(err.to_string())
+    }
+}
+
+impl From<DispatchError> for ClientError {
+    fn from(err: DispatchError) -> Self {
+        Self::Dispatch(err.to_string())
+    }
+}
+
+/// input-method-v2 state received since the last `done`
+#[derive(Default)]
+struct Pending {
+    active: Option<bool>,
+    surrounding_text: Option<(String, u32)>,
+}
+
+struct App {
+    ime: Ime,
+    input_method: ZwpInputMethodV2,
+    virtual_keyboard: ZwpVirtualKeyboardV1,
+    keymap: Option<Keymap>,
+    pending: Pending,
+    /// Number of `done` events, echoed in `commit`
+    serial: u32,
+    /// Keys whose press was forwarded, so their release is too
+    forwarded: HashSet<u32>,
+    unavailable: bool,
+}
+
+/// Connect to the compositor and serve until it goes away
+pub fn run() -> Result<(), ClientError> {
+    let connection = Connection::connect_to_env().map_err(ClientError::Connect)?;
+    let (globals, mut queue) = registry_queue_init::<App>(&connection)?;
+    let qh = queue.handle();
+
+    let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=9, ())?;
+    let input_methods: ZwpInputMethodManagerV2 = globals.bind(&qh, 1..=1, ())?;
+    let virtual_keyboards: ZwpVirtualKeyboardManagerV1 = globals.bind(&qh, 1..=1, ())?;
+
+    let input_method = input_methods.get_input_method(&seat, &qh, ());
+    let virtual_keyboard = virtual_keyboards.create_virtual_keyboard(&seat, &qh, ());
+    let _grab = input_method.grab_keyboard(&qh, ());
+
+    let mut app = App {
+        ime: Ime::new(Engine::new()),
+        input_method,
+        virtual_keyboard,
+        keymap: None,
+        pending: Pending::default(),
+        serial: 0,
+        forwarded: HashSet::new(),
+        unavailable: false,
+    };
+    loop {
+        queue.blocking_dispatch(&mut app)?;
+        if app.unavailable {
+            return Err(ClientError::Unavailable);
+        }
+    }
+}
+
+impl App {
+    /// Apply the state collected since the last `done`
+    fn done(&mut self) {
+        self.serial = self.serial.wrapping_add(1);
+        match self.pending.active.take() {
+            Some(true) => self.ime.activate(),
+            Some(false) => self.ime.deactivate(),
+            None => {}
+        }
+        if let Some((text, cursor)) = self.pending.surrounding_text.take() {
+            if self.ime.is_active() {
+                self.ime.set_surrounding_text(&text, cursor as usize);
+            }
+        }
+    }
+
+    fn key(&mut self, time: u32, key: u32, state: WEnum<KeyState>) {
+        if state != WEnum::Value(KeyState::Pressed) {
+            if self.forwarded.remove(&key) {
+                self.virtual_keyboard
+                    .key(time, key, KeyState::Released.into());
+            }
+            return;
+        }
+
+        let outcome = self.keymap.as_ref().map(|keymap| {
+            let (keysym, unicode) = keymap.key(key);
+            self.ime.key(keysym, unicode, keymap.modifiers())
+        });
+        let forward = match outcome {
+            Some(outcome) => {
+                if let Some(update) = outcome.update {
+                    self.send(update);
+                }
+                outcome.forward
+            }
+            None => true,
+        };
+        if forward {
+            self.forwarded.insert(key);
+            self.virtual_keyboard
+                .key(time, key, KeyState::Pressed.into());
+        }
+    }
+
+    fn send(&self, update: Update) {
+        if !update.commit.is_empty() {
+            self.input_method.commit_string(update.commit);
+        }
+        let cursor = update.cursor as i32;
+        self.input_method
+            .set_preedit_string(update.preedit, cursor, cursor);
+        self.input_method.commit(self.serial);
+    }
+}
+
+impl Dispatch<ZwpInputMethodV2, ()> for App {
+    fn event(
+        app: &mut Self,
+        _: &ZwpInputMethodV2,
+        event: zwp_input_method_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            // Activation resets all state
+            zwp_input_method_v2::Event::Activate => {
+                app.pending = Pending {
+                    active: Some(true),
+                    surrounding_text: None,
+                };
+            }
+            zwp_input_method_v2::Event::Deactivate => app.pending.active = Some(false),
+            zwp_input_method_v2::Event::SurroundingText { text, cursor, .. } => {
+                app.pending.surrounding_text = Some((text, cursor));
+            }
+            zwp_input_method_v2::Event::Done => app.done(),
+            zwp_input_method_v2::Event::Unavailable => app.unavailable = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwpInputMethodKeyboardGrabV2, ()> for App {
+    fn event(
+        app: &mut Self,
+        _: &ZwpInputMethodKeyboardGrabV2,
+        event: zwp_input_method_keyboard_grab_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwp_input_method_keyboard_grab_v2::Event::Keymap { format, fd, size } => {
+                // Forwarded keys must mean the same to the application
+                app.virtual_keyboard.keymap(format.into(), fd.as_fd(), size);
+                app.keymap = match Keymap::from_fd(fd, size) {
+                    Ok(keymap) => Some(keymap),
+                    Err(e) => {
+                        eprintln!("vietflux-wayland: {}; forwarding all keys", e);
+                        None
+                    }
+                };
+            }
+            zwp_input_method_keyboard_grab_v2::Event::Key {
+                time, key, state, ..
+            } => app.key(time, key, state),
+            zwp_input_method_keyboard_grab_v2::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                if let Some(keymap) = &mut app.keymap {
+                    keymap.update_modifiers(mods_depressed, mods_latched, mods_locked, group);
+                }
+                app.virtual_keyboard
+                    .modifiers(mods_depressed, mods_latched, mods_locked, group);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for App {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(App: ignore wl_seat::WlSeat);
+delegate_noop!(App: ZwpInputMethodManagerV2);
+delegate_noop!(App: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(App: ZwpVirtualKeyboardV1);
@@ -0,0 +1,230 @@
+//! Input method state, independent of the Wayland connection
+//!
+//! Turns key presses into the double-buffered input-method-v2 state:
+//! text to commit plus the preedit to show, applied together with one
+//! `commit` request. Keys the engine does not use are forwarded to the
+//! application through the virtual keyboard.
+
+use vietflux_core::config::{config_path, load_config};
+use vietflux_core::{Engine, PreeditEvent};
+
+/// The VietFlux app's config file, relative to the user's config directory
+const APP_CONFIG: &str = "dev.vietflux.app/config.json";
+
+/// BackSpace keysym
+pub const KEY_BACKSPACE: u32 = 0xff08;
+
+/// Modifiers active for a key press
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+/// State to send to the compositor after an event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Update {
+    /// Text to commit, empty if none
+    pub commit: String,
+    /// Preedit to show, empty to hide it
+    pub preedit: String,
+    /// Caret in the preedit, in bytes
+    pub cursor: usize,
+}
+
+/// Outcome of a key press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOutcome {
+    /// State to send before anything is forwarded
+    pub update: Option<Update>,
+    /// Whether the application must get the key
+    pub forward: bool,
+}
+
+/// VietFlux as a Wayland input method
+pub struct Ime {
+    engine: Engine,
+    active: bool,
+}
+
+impl Ime {
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            engine,
+            active: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// A text field got focus: start fresh with the app's latest settings
+    pub fn activate(&mut self) {
+        match config_path(APP_CONFIG).map_or(Ok(None), |path| load_config(&path)) {
+            Ok(Some(config)) => match Engine::from_config(&config) {
+                Ok(engine) => self.engine = engine,
+                Err(e) => eprintln!("Ignoring config: {}", e),
+            },
+            Ok(None) => {}
+            Err(e) => eprintln!("Ignoring config: {}", e),
+        }
+        self.engine.clear();
+        self.active = true;
+    }
+
+    /// The text field went away; the compositor drops the preedit
+    pub fn deactivate(&mut self) {
+        self.engine.clear();
+        self.active = false;
+    }
+
+    /// Surrounding text of the focused field; `cursor` is in bytes
+    pub fn set_surrounding_text(&mut self, text: &str, cursor: usize) {
+        if let Some(before) = text.get(..cursor) {
+            self.engine.set_text_before_cursor(before);
+        }
+    }
+
+    /// Handle a key press; `unicode` is the character it types, 0 if none
+    pub fn key(&mut self, keysym: u32, unicode: u32, modifiers: Modifiers) -> KeyOutcome {
+        if !self.active {
+            return KeyOutcome {
+                update: None,
+                forward: true,
+            };
+        }
+
+        // Shortcuts, Enter, arrows...: finish the word, then forward
+        if modifiers.ctrl || modifiers.alt || modifiers.logo {
+            return self.commit_and_forward();
+        }
+        let events = if keysym == KEY_BACKSPACE {
+            self.engine.process_backspace_preedit()
+        } else {
+            match char::from_u32(unicode).filter(|ch| !ch.is_control()) {
+                Some(ch) => self.engine.process_key_preedit(ch, modifiers.shift),
+                None => return self.commit_and_forward(),
+            }
+        };
+
+        if events.is_empty() {
+            return KeyOutcome {
+                update: None,
+                forward: true,
+            };
+        }
+        KeyOutcome {
+            update: Some(apply(events)),
+            forward: false,
+        }
+    }
+
+    fn commit_and_forward(&mut self) -> KeyOutcome {
+        let events = self.engine.commit_preedit();
+        KeyOutcome {
+            update: (!events.is_empty()).then(|| apply(events)),
+            forward: true,
+        }
+    }
+}
+
+/// Fold preedit events into the state to send
+fn apply(events: Vec<PreeditEvent>) -> Update {
+    let mut update = Update::default();
+    for event in events {
+        match event {
+            PreeditEvent::Commit { text } => {
+                update.commit.push_str(&text);
+                update.preedit.clear();
+                update.cursor = 0;
+            }
+            PreeditEvent::PreeditUpdate { text, cursor } => {
+                update.cursor = text.chars().take(cursor).map(char::len_utf8).sum();
+                update.preedit = text;
+            }
+            PreeditEvent::Cancel => {
+                update.preedit.clear();
+                update.cursor = 0;
+            }
+        }
+    }
+    update
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vietflux_core::EngineOptions;
+
+    fn ime() -> Ime {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            auto_capitalize: false,
+            ..EngineOptions::default()
+        });
+        Ime {
+            engine,
+            active: true,
+        }
+    }
+
+    fn type_text(ime: &mut Ime, text: &str) -> Option<Update> {
+        let mut last = None;
+        for ch in text.chars() {
+            let outcome = ime.key(ch as u32, ch as u32, Modifiers::default());
+            assert!(!outcome.forward, "{:?} should be used", ch);
+            last = outcome.update;
+        }
+        last
+    }
+
+    #[test]
+    fn test_preedit_and_commit() {
+        let mut ime = ime();
+        let update = type_text(&mut ime, "vieetj").unwrap();
+        assert_eq!(update.preedit, "việt");
+        assert_eq!(update.cursor, "việt".len());
+
+        let update = type_text(&mut ime, " ").unwrap();
+        assert_eq!(update.commit, "việt ");
+        assert_eq!(update.preedit, "");
+
+        // Nothing composed: the application gets the key
+        let outcome = ime.key(' ' as u32, ' ' as u32, Modifiers::default());
+        assert_eq!(outcome.update, None);
+        assert!(outcome.forward);
+    }
+
+    #[test]
+    fn test_other_keys_commit_and_forward() {
+        let mut ime = ime();
+        type_text(&mut ime, "ddi");
+        let outcome = ime.key(KEY_BACKSPACE, 0x08, Modifiers::default());
+        assert!(!outcome.forward);
+        assert_eq!(outcome.update.unwrap().preedit, "đ");
+
+        // Return
+        let outcome = ime.key(0xff0d, '\r' as u32, Modifiers::default());
+        assert!(outcome.forward);
+        assert_eq!(outcome.update.unwrap().commit, "đ");
+
+        // Ctrl+A
+        type_text(&mut ime, "as");
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::default()
+        };
+        let outcome = ime.key('a' as u32, 'a' as u32, ctrl);
+        assert!(outcome.forward);
+        assert_eq!(outcome.update.unwrap().commit, "á");
+
+        // Inactive: everything goes through untouched
+        ime.deactivate();
+        let outcome = ime.key('a' as u32, 'a' as u32, Modifiers::default());
+        assert_eq!(outcome.update, None);
+        assert!(outcome.forward);
+    }
+}
@@ -0,0 +1,145 @@
+//! Keymap handling through libxkbcommon
+//!
+//! The keyboard grab delivers evdev keycodes plus the compositor's keymap;
+//! libxkbcommon turns them into keysyms and characters. The library is
+//! loaded at runtime, like every Wayland toolkit does.
+
+use crate::ime::Modifiers;
+use std::ffi::CStr;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::OwnedFd;
+use xkbcommon_dl::{
+    xkb_context, xkb_context_flags, xkb_keymap, xkb_keymap_compile_flags, xkb_keymap_format,
+    xkb_state, xkb_state_component, xkbcommon_option, XkbCommon, XKB_MOD_NAME_ALT,
+    XKB_MOD_NAME_CTRL, XKB_MOD_NAME_LOGO, XKB_MOD_NAME_SHIFT,
+};
+
+/// Offset between evdev keycodes and XKB keycodes
+const EVDEV_OFFSET: u32 = 8;
+
+#[derive(Debug)]
+pub enum KeymapError {
+    /// libxkbcommon.so could not be loaded
+    NoLibrary,
+    /// The keymap fd could not be read
+    Read(std::io::Error),
+    /// libxkbcommon rejected the keymap
+    Compile,
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoLibrary => write!(f, "libxkbcommon could not be loaded"),
+            Self::Read(err) => write!(f, "cannot read keymap: {}", err),
+            Self::Compile => write!(f, "invalid keymap"),
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+/// Compiled keymap and its modifier state
+pub struct Keymap {
+    xkb: &'static XkbCommon,
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+}
+
+impl Keymap {
+    /// Compile the XKB_V1 text keymap the compositor sent
+    pub fn from_fd(fd: OwnedFd, size: u32) -> Result<Self, KeymapError> {
+        let xkb = xkbcommon_option().ok_or(KeymapError::NoLibrary)?;
+
+        let mut text = Vec::with_capacity(size as usize);
+        File::from(fd)
+            .take(size as u64)
+            .read_to_end(&mut text)
+            .map_err(KeymapError::Read)?;
+        while text.last() == Some(&0) {
+            text.pop();
+        }
+
+        unsafe {
+            let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() {
+                return Err(KeymapError::Compile);
+            }
+            let keymap = (xkb.xkb_keymap_new_from_buffer)(
+                context,
+                text.as_ptr().cast(),
+                text.len(),
+                xkb_keymap_format::XKB_KEYMAP_FORMAT_TEXT_V1,
+                xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+            );
+            if keymap.is_null() {
+                (xkb.xkb_context_unref)(context);
+                return Err(KeymapError::Compile);
+            }
+            let state = (xkb.xkb_state_new)(keymap);
+            if state.is_null() {
+                (xkb.xkb_keymap_unref)(keymap);
+                (xkb.xkb_context_unref)(context);
+                return Err(KeymapError::Compile);
+            }
+            Ok(Self {
+                xkb,
+                context,
+                keymap,
+                state,
+            })
+        }
+    }
+
+    /// Apply a modifiers event
+    pub fn update_modifiers(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        unsafe {
+            (self.xkb.xkb_state_update_mask)(self.state, depressed, latched, locked, 0, 0, group);
+        }
+    }
+
+    /// Keysym and character (0 if none) of an evdev keycode
+    pub fn key(&self, keycode: u32) -> (u32, u32) {
+        let keycode = keycode + EVDEV_OFFSET;
+        unsafe {
+            (
+                (self.xkb.xkb_state_key_get_one_sym)(self.state, keycode),
+                (self.xkb.xkb_state_key_get_utf32)(self.state, keycode),
+            )
+        }
+    }
+
+    /// Active modifiers
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            shift: self.is_active(XKB_MOD_NAME_SHIFT),
+            ctrl: self.is_active(XKB_MOD_NAME_CTRL),
+            alt: self.is_active(XKB_MOD_NAME_ALT),
+            logo: self.is_active(XKB_MOD_NAME_LOGO),
+        }
+    }
+
+    fn is_active(&self, name: &[u8]) -> bool {
+        let name = CStr::from_bytes_with_nul(name).expect("modifier names are NUL-terminated");
+        unsafe {
+            (self.xkb.xkb_state_mod_name_is_active)(
+                self.state,
+                name.as_ptr(),
+                xkb_state_component::XKB_STATE_MODS_EFFECTIVE,
+            ) > 0
+        }
+    }
+}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xkb.xkb_state_unref)(self.state);
+            (self.xkb.xkb_keymap_unref)(self.keymap);
+            (self.xkb.xkb_context_unref)(self.context);
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! VietFlux Wayland Input Method
+//!
+//! Wayland client implementing `zwp_input_method_v2` for compositors that
+//! support it (sway, Hyprland, other wlroots compositors, KWin). Neither
+//! a global key hook nor XTest works on Wayland, so the compositor hands
+//! the keyboard to the input method: the word being typed is sent as
+//! preedit, finished text with `commit_string`, and keys the engine does
+//! not use go back out through `zwp_virtual_keyboard_v1`.
+
+pub mod client;
+pub mod ime;
+pub mod keymap;
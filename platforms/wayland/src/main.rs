@@ -0,0 +1,17 @@
+//! vietflux-wayland - Wayland input method process
+//!
+//! Run it inside the Wayland session (e.g. `exec vietflux-wayland` in the
+//! sway config). Settings are read from the VietFlux app's config each
+//! time a text field is focused.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match vietflux_wayland::client::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("vietflux-wayland: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,31 @@
+#!/bin/sh
+# Run the headless integration test against sway with no outputs or
+# input devices. Needs sway (wlroots 0.17+) and libxkbcommon.
+set -eu
+
+cd "$(dirname "$0")/.."
+
+runtime_dir=$(mktemp -d)
+trap 'kill "$sway_pid" 2>/dev/null; rm -rf "$runtime_dir"' EXIT
+
+export XDG_RUNTIME_DIR="$runtime_dir"
+export WLR_BACKENDS=headless
+export WLR_HEADLESS_OUTPUTS=1
+export WLR_LIBINPUT_NO_DEVICES=1
+export WLR_RENDERER=pixman
+unset WAYLAND_DISPLAY DISPLAY
+
+sway -c /dev/null >"$runtime_dir/sway.log" 2>&1 &
+sway_pid=$!
+
+for _ in $(seq 50); do
+    socket=$(ls "$runtime_dir" | grep '^wayland-[0-9]*$' | head -n 1 || true)
+    [ -n "$socket" ] && break
+    sleep 0.1
+done
+if [ -z "$socket" ]; then
+    cat "$runtime_dir/sway.log" >&2
+    exit 1
+fi
+
+WAYLAND_DISPLAY="$socket" cargo test --test headless -- --ignored
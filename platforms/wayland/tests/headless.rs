@@ -0,0 +1,304 @@
+//! Type through the input method in a headless wlroots compositor
+//!
+//! Needs a compositor with input-method-v2, text-input-v3 and
+//! virtual-keyboard support, so it is ignored by default. Run it with
+//! `tests/headless-sway.sh`, which starts sway on the headless backend.
+//!
+//! The test opens a window with a text-input-v3 field, starts
+//! `vietflux-wayland` and types on its own virtual keyboard; the compositor
+//! routes the keys through the input method's grab like a real keyboard.
+
+use std::fs::{self, File};
+use std::os::fd::AsFd;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{
+    wl_buffer, wl_compositor, wl_registry, wl_seat, wl_shm, wl_shm_pool, wl_surface,
+};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols::wp::text_input::zv3::client::{
+    zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+    zwp_text_input_v3::{self, ContentHint, ContentPurpose, ZwpTextInputV3},
+};
+use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::{
+    zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
+    zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
+};
+
+// evdev keycodes
+const KEY_E: u32 = 18;
+const KEY_T: u32 = 20;
+const KEY_I: u32 = 23;
+const KEY_J: u32 = 36;
+const KEY_V: u32 = 47;
+const KEY_SPACE: u32 = 57;
+
+const WIDTH: i32 = 64;
+const HEIGHT: i32 = 64;
+
+/// The application's side: a window with one text field
+#[derive(Default)]
+struct TextField {
+    configured: bool,
+    focused: bool,
+    pending_preedit: Option<String>,
+    pending_commit: String,
+    preedit: String,
+    text: String,
+}
+
+/// Scratch file under the temp dir, removed on drop
+struct TempPath(PathBuf);
+
+impl TempPath {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("vietflux-wayland-{}-{}", std::process::id(), name));
+        Self(path)
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0).or_else(|_| fs::remove_file(&self.0));
+    }
+}
+
+/// Input method process, killed on drop
+struct InputMethod(Child);
+
+impl InputMethod {
+    /// Start with Telex and no auto-capitalization, given as the app's
+    /// saved settings
+    fn start(config_home: &TempPath) -> Self {
+        let dir = config_home.0.join("dev.vietflux.app");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("config.json"),
+            r#"{ "method": "telex", "options": { "auto_capitalize": false } }"#,
+        )
+        .unwrap();
+        let child = Command::new(env!("CARGO_BIN_EXE_vietflux-wayland"))
+            .env("XDG_CONFIG_HOME", &config_home.0)
+            .spawn()
+            .unwrap();
+        // Let it bind the input method before the field is focused
+        thread::sleep(Duration::from_millis(500));
+        Self(child)
+    }
+}
+
+impl Drop for InputMethod {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// The default (US) keymap as XKB_V1 text, NUL-terminated
+fn default_keymap() -> Vec<u8> {
+    use xkbcommon_dl::{
+        xkb_context_flags, xkb_keymap_compile_flags, xkb_keymap_format, xkbcommon_handle,
+    };
+    let xkb = xkbcommon_handle();
+    unsafe {
+        let context = (xkb.xkb_context_new)(xkb_context_flags::XKB_CONTEXT_NO_FLAGS);
+        let keymap = (xkb.xkb_keymap_new_from_names)(
+            context,
+            std::ptr::null(),
+            xkb_keymap_compile_flags::XKB_KEYMAP_COMPILE_NO_FLAGS,
+        );
+        assert!(!keymap.is_null(), "no default keymap");
+        let text =
+            (xkb.xkb_keymap_get_as_string)(keymap, xkb_keymap_format::XKB_KEYMAP_FORMAT_TEXT_V1);
+        let bytes = std::ffi::CStr::from_ptr(text).to_bytes_with_nul().to_vec();
+        (xkb.xkb_keymap_unref)(keymap);
+        (xkb.xkb_context_unref)(context);
+        bytes
+    }
+}
+
+/// Dispatch until `done` holds, failing after a few seconds
+fn wait_until(
+    queue: &mut EventQueue<TextField>,
+    field: &mut TextField,
+    what: &str,
+    done: impl Fn(&TextField) -> bool,
+) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done(field) {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for {} (preedit {:?}, text {:?})",
+            what,
+            field.preedit,
+            field.text
+        );
+        queue.roundtrip(field).unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+#[ignore = "needs a headless Wayland compositor, see tests/headless-sway.sh"]
+fn test_types_into_text_field() {
+    let config_home = TempPath::new("config");
+    let _input_method = InputMethod::start(&config_home);
+
+    let connection = Connection::connect_to_env().expect("WAYLAND_DISPLAY must be set");
+    let (globals, mut queue) = registry_queue_init::<TextField>(&connection).unwrap();
+    let qh = queue.handle();
+    let compositor: wl_compositor::WlCompositor = globals.bind(&qh, 1..=4, ()).unwrap();
+    let shm: wl_shm::WlShm = globals.bind(&qh, 1..=1, ()).unwrap();
+    let wm_base: xdg_wm_base::XdgWmBase = globals.bind(&qh, 1..=1, ()).unwrap();
+    let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=7, ()).unwrap();
+    let text_inputs: ZwpTextInputManagerV3 = globals.bind(&qh, 1..=1, ()).unwrap();
+    let keyboards: ZwpVirtualKeyboardManagerV1 = globals.bind(&qh, 1..=1, ()).unwrap();
+
+    // Our own keyboard to type on
+    let keymap = default_keymap();
+    let keymap_path = TempPath::new("keymap");
+    fs::write(&keymap_path.0, &keymap).unwrap();
+    let keymap_file = File::open(&keymap_path.0).unwrap();
+    let keyboard = keyboards.create_virtual_keyboard(&seat, &qh, ());
+    keyboard.keymap(1, keymap_file.as_fd(), keymap.len() as u32);
+
+    // A mapped window gets keyboard focus
+    let surface = compositor.create_surface(&qh, ());
+    let window = wm_base.get_xdg_surface(&surface, &qh, ());
+    let toplevel = window.get_toplevel(&qh, ());
+    toplevel.set_title("vietflux-test".to_string());
+    surface.commit();
+
+    let mut field = TextField::default();
+    wait_until(&mut queue, &mut field, "configure", |f| f.configured);
+
+    let pixels_path = TempPath::new("pixels");
+    let pixels = File::create(&pixels_path.0).unwrap();
+    pixels.set_len((WIDTH * HEIGHT * 4) as u64).unwrap();
+    let pool = shm.create_pool(pixels.as_fd(), WIDTH * HEIGHT * 4, &qh, ());
+    let buffer = pool.create_buffer(
+        0,
+        WIDTH,
+        HEIGHT,
+        WIDTH * 4,
+        wl_shm::Format::Argb8888,
+        &qh,
+        (),
+    );
+    surface.attach(Some(&buffer), 0, 0);
+    surface.commit();
+
+    let text_input = text_inputs.get_text_input(&seat, &qh, ());
+    wait_until(&mut queue, &mut field, "focus", |f| f.focused);
+    text_input.enable();
+    text_input.set_content_type(ContentHint::None, ContentPurpose::Normal);
+    text_input.commit();
+    queue.roundtrip(&mut field).unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    let mut time = 0;
+    let mut press = |key: u32| {
+        time += 10;
+        keyboard.key(time, key, 1);
+        keyboard.key(time + 5, key, 0);
+    };
+
+    for key in [KEY_V, KEY_I, KEY_E, KEY_E, KEY_T, KEY_J] {
+        press(key);
+    }
+    wait_until(&mut queue, &mut field, "preedit", |f| f.preedit == "việt");
+    assert_eq!(field.text, "");
+
+    press(KEY_SPACE);
+    wait_until(&mut queue, &mut field, "commit", |f| f.text == "việt ");
+    assert_eq!(field.preedit, "");
+}
+
+impl Dispatch<ZwpTextInputV3, ()> for TextField {
+    fn event(
+        field: &mut Self,
+        _: &ZwpTextInputV3,
+        event: zwp_text_input_v3::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwp_text_input_v3::Event::Enter { .. } => field.focused = true,
+            zwp_text_input_v3::Event::Leave { .. } => field.focused = false,
+            zwp_text_input_v3::Event::PreeditString { text, .. } => {
+                field.pending_preedit = Some(text.unwrap_or_default());
+            }
+            zwp_text_input_v3::Event::CommitString { text } => {
+                field.pending_commit.push_str(&text.unwrap_or_default());
+            }
+            // A preedit not sent again is gone
+            zwp_text_input_v3::Event::Done { .. } => {
+                field.preedit = field.pending_preedit.take().unwrap_or_default();
+                field
+                    .text
+                    .push_str(&std::mem::take(&mut field.pending_commit));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<xdg_wm_base::XdgWmBase, ()> for TextField {
+    fn event(
+        _: &mut Self,
+        wm_base: &xdg_wm_base::XdgWmBase,
+        event: xdg_wm_base::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<xdg_surface::XdgSurface, ()> for TextField {
+    fn event(
+        field: &mut Self,
+        window: &xdg_surface::XdgSurface,
+        event: xdg_surface::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            window.ack_configure(serial);
+            field.configured = true;
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for TextField {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(TextField: wl_compositor::WlCompositor);
+delegate_noop!(TextField: wl_shm_pool::WlShmPool);
+delegate_noop!(TextField: ignore wl_shm::WlShm);
+delegate_noop!(TextField: ignore wl_seat::WlSeat);
+delegate_noop!(TextField: ignore wl_surface::WlSurface);
+delegate_noop!(TextField: ignore wl_buffer::WlBuffer);
+delegate_noop!(TextField: ignore xdg_toplevel::XdgToplevel);
+delegate_noop!(TextField: ZwpTextInputManagerV3);
+delegate_noop!(TextField: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(TextField: ZwpVirtualKeyboardV1);
@@ -0,0 +1,98 @@
+//! Corrections to apply after a key reached the application
+
+use vietflux_core::engine::Action;
+use vietflux_core::Engine;
+
+/// Characters to delete before the caret, then text to type
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Edit {
+    pub backspace: usize,
+    pub text: String,
+}
+
+impl Edit {
+    fn new(backspace: usize, text: String) -> Option<Self> {
+        (backspace > 0 || !text.is_empty()).then_some(Self { backspace, text })
+    }
+}
+
+/// Feed a typed character; the application already shows it
+pub fn after_key(engine: &mut Engine, ch: char, shift: bool) -> Option<Edit> {
+    let result = engine.process_key(ch, shift);
+    match result.action {
+        Action::Update | Action::Restore => {
+            let mut output = result.output.chars();
+            if result.backspace == 0 && output.next() == Some(ch) && output.next().is_none() {
+                return None;
+            }
+            Edit::new(result.backspace + 1, result.output)
+        }
+        // The committed word is already on screen
+        Action::Commit | Action::Passthrough => None,
+    }
+}
+
+/// Feed a Backspace; the application already deleted one character
+pub fn after_backspace(engine: &mut Engine) -> Option<Edit> {
+    let result = engine.process_backspace();
+    if result.action != Action::Update || result.backspace == 0 {
+        return None;
+    }
+    Edit::new(result.backspace - 1, result.output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vietflux_core::EngineOptions;
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine.set_options(EngineOptions {
+            auto_capitalize: false,
+            ..EngineOptions::default()
+        });
+        engine
+    }
+
+    /// Replay keys against a plain text field that inserts every key
+    fn type_text(engine: &mut Engine, keys: &str) -> String {
+        let mut screen = String::new();
+        for ch in keys.chars() {
+            let edit = if ch == '\x08' {
+                screen.pop();
+                after_backspace(engine)
+            } else {
+                screen.push(ch);
+                after_key(engine, ch, false)
+            };
+            if let Some(edit) = edit {
+                for _ in 0..edit.backspace {
+                    screen.pop();
+                }
+                screen.push_str(&edit.text);
+            }
+        }
+        screen
+    }
+
+    #[test]
+    fn test_corrects_typed_keys() {
+        let mut engine = engine();
+        assert_eq!(type_text(&mut engine, "vieetj nam"), "việt nam");
+
+        // Plain keys need no correction
+        assert_eq!(after_key(&mut engine, ' ', false), None);
+        assert_eq!(after_key(&mut engine, 'b', false), None);
+    }
+
+    #[test]
+    fn test_backspace() {
+        let mut engine = engine();
+        // The tone moves back as the word shrinks
+        let expected = type_text(&mut self::engine(), "toas");
+        assert_eq!(type_text(&mut engine, "toans\x08"), expected);
+        engine.clear();
+        assert_eq!(type_text(&mut engine, "ab\x08"), "a");
+    }
+}
@@ -0,0 +1,322 @@
+//! RECORD loop
+//!
+//! As the RECORD spec suggests, one connection reads the recorded device
+//! events while a second one controls the context, queries the keyboard
+//! and injects corrections. Injected keys come back through the recording
+//! and are skipped.
+
+use crate::edit::{after_backspace, after_key, Edit};
+use crate::inject::Injector;
+use crate::keymap::{Keymap, ModifierKey, XK_BACKSPACE};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use vietflux_core::Engine;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError};
+use x11rb::protocol::record::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{self, ConnectionExt as _, KeyButMask, Mapping};
+use x11rb::protocol::xtest::{self, ConnectionExt as _};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+/// Reply categories of RECORD (not in the XML)
+const FROM_SERVER: u8 = 0;
+const START_OF_DATA: u8 = 4;
+
+#[derive(Debug)]
+pub enum X11Error {
+    /// No X display to connect to
+    Connect(String),
+    /// The server lacks RECORD or XTEST
+    MissingExtension(&'static str),
+    /// The connection failed
+    Connection(String),
+    /// No key produces BackSpace
+    NoBackspace,
+    /// Every keycode is taken, so no character can be typed
+    NoSpareKeycode,
+}
+
+impl fmt::Display for X11Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(err) => write!(f, "cannot connect to X: {}", err),
+            Self::MissingExtension(name) => write!(f, "X server lacks the {} extension", name),
+            Self::Connection(err) => write!(f, "X connection failed: {}", err),
+            Self::NoBackspace => write!(f, "keyboard has no BackSpace key"),
+            Self::NoSpareKeycode => write!(f, "keyboard mapping has no spare keycode"),
+        }
+    }
+}
+
+impl std::error::Error for X11Error {}
+
+impl From<ConnectError> for X11Error {
+    fn from(err: ConnectError) -> Self {
+        Self::Connect(err.to_string())
+    }
+}
+
+impl From<ConnectionError> for X11Error {
+    fn from(err: ConnectionError) -> Self {
+        Self::Connection(err.to_string())
+    }
+}
+
+impl From<ReplyError> for X11Error {
+    fn from(err: ReplyError) -> Self {
+        Self::Connection(err.to_string())
+    }
+}
+
+impl From<ReplyOrIdError> for X11Error {
+    fn from(err: ReplyOrIdError) -> Self {
+        Self::Connection(err.to_string())
+    }
+}
+
+/// Running keyboard hook; dropping it stops recording and gives the
+/// spare keycodes back
+pub struct Hook {
+    control: Arc<RustConnection>,
+    context: record::Context,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Hook {
+    /// Start recording keys on `display` (`None` for `$DISPLAY`)
+    ///
+    /// Typed keys go to the engine in `engine`; while it is `None` or
+    /// disabled, keys pass untouched.
+    pub fn start(
+        display: Option<&str>,
+        engine: &'static Mutex<Option<Engine>>,
+    ) -> Result<Self, X11Error> {
+        let (control, screen) = RustConnection::connect(display)?;
+        let (data, _) = RustConnection::connect(display)?;
+        for name in [record::X11_EXTENSION_NAME, xtest::X11_EXTENSION_NAME] {
+            if control.extension_information(name)?.is_none() {
+                return Err(X11Error::MissingExtension(name));
+            }
+        }
+        control
+            .record_query_version(
+                record::X11_XML_VERSION.0 as u16,
+                record::X11_XML_VERSION.1 as u16,
+            )?
+            .reply()?;
+        control
+            .xtest_get_version(
+                xtest::X11_XML_VERSION.0 as u8,
+                xtest::X11_XML_VERSION.1 as u16,
+            )?
+            .reply()?;
+
+        let root = control.setup().roots[screen].root;
+        let keymap = read_keymap(&control)?;
+        let injector = Injector::new(&keymap)?;
+        let caps_lock = control
+            .query_pointer(root)?
+            .reply()?
+            .mask
+            .contains(KeyButMask::LOCK);
+
+        let context = control.generate_id()?;
+        let none = record::Range8 { first: 0, last: 0 };
+        let no_ext = record::ExtRange {
+            major: none,
+            minor: record::Range16 { first: 0, last: 0 },
+        };
+        let range = record::Range {
+            core_requests: none,
+            core_replies: none,
+            ext_requests: no_ext,
+            ext_replies: no_ext,
+            delivered_events: none,
+            device_events: record::Range8 {
+                first: xproto::KEY_PRESS_EVENT,
+                last: xproto::BUTTON_PRESS_EVENT,
+            },
+            errors: none,
+            client_started: false,
+            client_died: false,
+        };
+        control
+            .record_create_context(context, 0, &[record::CS::ALL_CLIENTS.into()], &[range])?
+            .check()?;
+
+        let control = Arc::new(control);
+        let mut state = State {
+            engine,
+            control: Arc::clone(&control),
+            keymap,
+            injector,
+            shift: HashSet::new(),
+            shortcut: HashSet::new(),
+            caps_lock,
+            injected: VecDeque::new(),
+        };
+        let (started, recording) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            if let Err(e) = state.record(&data, context, started) {
+                eprintln!("vietflux-x11: {}", e);
+            }
+            let _ = state.injector.restore(state.control.as_ref());
+        });
+        let hook = Self {
+            control,
+            context,
+            thread: Some(thread),
+        };
+        // Keys typed before this point would be missed
+        recording
+            .recv()
+            .map_err(|_| X11Error::Connection("recording did not start".to_string()))?;
+        Ok(hook)
+    }
+}
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        // Ends the recording with an EndOfData reply
+        let _ = self.control.record_disable_context(self.context);
+        let _ = self.control.sync();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let _ = self.control.record_free_context(self.context);
+        let _ = self.control.flush();
+    }
+}
+
+fn read_keymap(conn: &impl Connection) -> Result<Keymap, X11Error> {
+    let setup = conn.setup();
+    let (min, max) = (setup.min_keycode, setup.max_keycode);
+    let reply = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+    Ok(Keymap::new(min, reply.keysyms_per_keycode, reply.keysyms))
+}
+
+/// State of the recording thread
+struct State {
+    engine: &'static Mutex<Option<Engine>>,
+    control: Arc<RustConnection>,
+    keymap: Keymap,
+    injector: Injector,
+    /// Shift keys held down
+    shift: HashSet<u8>,
+    /// Ctrl, Alt, Super... held down
+    shortcut: HashSet<u8>,
+    caps_lock: bool,
+    /// Keys we pressed that have not come back yet
+    injected: VecDeque<u8>,
+}
+
+impl State {
+    fn record(
+        &mut self,
+        data: &RustConnection,
+        context: record::Context,
+        started: mpsc::Sender<()>,
+    ) -> Result<(), X11Error> {
+        for reply in data.record_enable_context(context)? {
+            let reply = reply?;
+            if reply.category == START_OF_DATA {
+                let _ = started.send(());
+                continue;
+            }
+            if reply.category != FROM_SERVER || reply.client_swapped {
+                continue;
+            }
+            // Device events are plain 32-byte core events
+            for event in reply.data.chunks_exact(32) {
+                match event[0] & 0x7f {
+                    xproto::KEY_PRESS_EVENT => self.key_press(event[1])?,
+                    xproto::KEY_RELEASE_EVENT => {
+                        self.shift.remove(&event[1]);
+                        self.shortcut.remove(&event[1]);
+                    }
+                    // A click may move the caret
+                    xproto::BUTTON_PRESS_EVENT => self.with_engine(|engine| {
+                        engine.clear();
+                        None
+                    })?,
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn key_press(&mut self, keycode: u8) -> Result<(), X11Error> {
+        if self.injected.front() == Some(&keycode) {
+            self.injected.pop_front();
+            return Ok(());
+        }
+        self.refresh_keymap()?;
+
+        match self.keymap.modifier(keycode) {
+            Some(ModifierKey::Shift) => {
+                self.shift.insert(keycode);
+                return Ok(());
+            }
+            Some(ModifierKey::CapsLock) => {
+                self.caps_lock = !self.caps_lock;
+                return Ok(());
+            }
+            Some(ModifierKey::Other) => {
+                self.shortcut.insert(keycode);
+                return Ok(());
+            }
+            None => {}
+        }
+
+        let shift = !self.shift.is_empty();
+        let ch = self.keymap.char(keycode, shift, self.caps_lock);
+        let shortcut = !self.shortcut.is_empty();
+        let backspace = self.keymap.keysym(keycode) == XK_BACKSPACE;
+        self.with_engine(|engine| match ch {
+            _ if shortcut => {
+                engine.clear();
+                None
+            }
+            _ if backspace => after_backspace(engine),
+            Some(ch) => after_key(engine, ch, shift),
+            // Enter, Tab, arrows...: the word is over
+            None => {
+                engine.clear();
+                None
+            }
+        })
+    }
+
+    /// Run the engine and type its correction
+    fn with_engine(&mut self, f: impl FnOnce(&mut Engine) -> Option<Edit>) -> Result<(), X11Error> {
+        let edit = match self.engine.lock().unwrap().as_mut() {
+            Some(engine) => f(engine),
+            None => None,
+        };
+        if let Some(edit) = edit {
+            let keycodes = self.injector.send(self.control.as_ref(), &edit)?;
+            self.injected.extend(keycodes);
+        }
+        Ok(())
+    }
+
+    /// Pick up layout changes other than our own remaps
+    fn refresh_keymap(&mut self) -> Result<(), X11Error> {
+        let mut changed = false;
+        while let Some(event) = self.control.poll_for_event()? {
+            if let Event::MappingNotify(notify) = event {
+                changed |= notify.request == Mapping::KEYBOARD
+                    && !self.injector.is_spare(notify.first_keycode);
+            }
+        }
+        if changed {
+            self.keymap = read_keymap(self.control.as_ref())?;
+        }
+        Ok(())
+    }
+}
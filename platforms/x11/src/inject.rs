@@ -0,0 +1,100 @@
+//! Typing with XTEST
+//!
+//! XTEST only presses keycodes, so every character gets a spare keycode
+//! (one with no keysyms) remapped to its keysym, as xdotool does. A
+//! character keeps its keycode until the spare keycodes run out, which
+//! keeps remaps rare and never retargets a key an application may still be
+//! reading.
+
+use crate::edit::Edit;
+use crate::hook::X11Error;
+use crate::keymap::{char_to_keysym, Keymap, NO_SYMBOL, XK_BACKSPACE};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{self, ConnectionExt as _};
+use x11rb::protocol::xtest::ConnectionExt as _;
+
+pub struct Injector {
+    backspace: u8,
+    spare: Vec<u8>,
+    /// Keysym held by each spare keycode, least recently used first
+    assigned: Vec<(u8, u32)>,
+}
+
+impl Injector {
+    pub fn new(keymap: &Keymap) -> Result<Self, X11Error> {
+        let backspace = keymap.find(XK_BACKSPACE).ok_or(X11Error::NoBackspace)?;
+        let spare = keymap.unused();
+        if spare.is_empty() {
+            return Err(X11Error::NoSpareKeycode);
+        }
+        Ok(Self {
+            backspace,
+            spare,
+            assigned: Vec::new(),
+        })
+    }
+
+    pub fn is_spare(&self, keycode: u8) -> bool {
+        self.spare.contains(&keycode)
+    }
+
+    /// Type an edit; returns the keycodes pressed, in order
+    pub fn send(&mut self, conn: &impl Connection, edit: &Edit) -> Result<Vec<u8>, X11Error> {
+        let mut keycodes = vec![self.backspace; edit.backspace];
+        for ch in edit.text.chars() {
+            keycodes.push(self.keycode_for(conn, char_to_keysym(ch))?);
+        }
+        for &keycode in &keycodes {
+            conn.xtest_fake_input(
+                xproto::KEY_PRESS_EVENT,
+                keycode,
+                x11rb::CURRENT_TIME,
+                x11rb::NONE,
+                0,
+                0,
+                0,
+            )?;
+            conn.xtest_fake_input(
+                xproto::KEY_RELEASE_EVENT,
+                keycode,
+                x11rb::CURRENT_TIME,
+                x11rb::NONE,
+                0,
+                0,
+                0,
+            )?;
+        }
+        conn.flush()?;
+        Ok(keycodes)
+    }
+
+    /// Hand the spare keycodes back
+    pub fn restore(&mut self, conn: &impl Connection) -> Result<(), X11Error> {
+        for (keycode, _) in self.assigned.drain(..) {
+            conn.change_keyboard_mapping(1, keycode, 1, &[NO_SYMBOL])?;
+        }
+        conn.flush()?;
+        Ok(())
+    }
+
+    fn keycode_for(&mut self, conn: &impl Connection, keysym: u32) -> Result<u8, X11Error> {
+        if let Some(index) = self.assigned.iter().position(|&(_, k)| k == keysym) {
+            let entry = self.assigned.remove(index);
+            self.assigned.push(entry);
+            return Ok(entry.0);
+        }
+
+        let keycode = match self
+            .spare
+            .iter()
+            .find(|&&keycode| self.assigned.iter().all(|&(k, _)| k != keycode))
+        {
+            Some(&keycode) => keycode,
+            None => self.assigned.remove(0).0,
+        };
+        // Same keysym on both levels, so a held Shift does not matter
+        conn.change_keyboard_mapping(1, keycode, 2, &[keysym, keysym])?;
+        self.assigned.push((keycode, keysym));
+        Ok(keycode)
+    }
+}
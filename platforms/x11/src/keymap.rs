@@ -0,0 +1,173 @@
+//! Core keyboard mapping
+//!
+//! Keysyms per keycode as returned by `GetKeyboardMapping`, read the way
+//! XKB-aware applications do: Shift picks the second level and Caps Lock
+//! flips the case of letters.
+
+pub const NO_SYMBOL: u32 = 0;
+pub const XK_BACKSPACE: u32 = 0xff08;
+pub const XK_CAPS_LOCK: u32 = 0xffe5;
+
+/// Offset of keysyms that stand for a Unicode code point
+const UNICODE_KEYSYM: u32 = 0x0100_0000;
+
+/// What a modifier key does to the hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifierKey {
+    Shift,
+    CapsLock,
+    /// Ctrl, Alt, Super...: the key combination is a shortcut
+    Other,
+}
+
+pub struct Keymap {
+    min_keycode: u8,
+    per_keycode: usize,
+    keysyms: Vec<u32>,
+}
+
+impl Keymap {
+    pub fn new(min_keycode: u8, per_keycode: u8, keysyms: Vec<u32>) -> Self {
+        Self {
+            min_keycode,
+            per_keycode: per_keycode as usize,
+            keysyms,
+        }
+    }
+
+    /// Keysyms of a keycode, empty if out of range
+    pub fn keysyms(&self, keycode: u8) -> &[u32] {
+        let Some(index) = keycode.checked_sub(self.min_keycode) else {
+            return &[];
+        };
+        let start = index as usize * self.per_keycode;
+        self.keysyms
+            .get(start..start + self.per_keycode)
+            .unwrap_or(&[])
+    }
+
+    /// Unshifted keysym of a keycode
+    pub fn keysym(&self, keycode: u8) -> u32 {
+        self.keysyms(keycode).first().copied().unwrap_or(NO_SYMBOL)
+    }
+
+    /// Character a key types, if any
+    pub fn char(&self, keycode: u8, shift: bool, caps_lock: bool) -> Option<char> {
+        let keysyms = self.keysyms(keycode);
+        let lower = keysym_to_char(keysyms.first().copied()?)?;
+        let upper = match keysyms.get(1).copied().unwrap_or(NO_SYMBOL) {
+            NO_SYMBOL => lower.to_uppercase().next().unwrap_or(lower),
+            keysym => keysym_to_char(keysym)?,
+        };
+        let ch = if shift { upper } else { lower };
+        if caps_lock && ch.is_alphabetic() {
+            let flipped = if shift {
+                ch.to_lowercase().next()
+            } else {
+                ch.to_uppercase().next()
+            };
+            return flipped;
+        }
+        Some(ch)
+    }
+
+    pub fn modifier(&self, keycode: u8) -> Option<ModifierKey> {
+        match self.keysym(keycode) {
+            // Shift_L, Shift_R
+            0xffe1 | 0xffe2 => Some(ModifierKey::Shift),
+            XK_CAPS_LOCK => Some(ModifierKey::CapsLock),
+            // Control, Shift_Lock, Meta, Alt, Super, Hyper, ISO_Level3_Shift
+            0xffe3 | 0xffe4 | 0xffe6..=0xffee | 0xfe03 => Some(ModifierKey::Other),
+            _ => None,
+        }
+    }
+
+    /// First keycode whose unshifted keysym is `keysym`
+    pub fn find(&self, keysym: u32) -> Option<u8> {
+        self.keycodes()
+            .find(|&keycode| self.keysym(keycode) == keysym)
+    }
+
+    /// Keycodes with no keysyms at all
+    pub fn unused(&self) -> Vec<u8> {
+        self.keycodes()
+            .filter(|&keycode| self.keysyms(keycode).iter().all(|&k| k == NO_SYMBOL))
+            .collect()
+    }
+
+    fn keycodes(&self) -> impl Iterator<Item = u8> + '_ {
+        let count = self.keysyms.len() / self.per_keycode.max(1);
+        (0..count).filter_map(|index| self.min_keycode.checked_add(u8::try_from(index).ok()?))
+    }
+}
+
+/// Character of a Latin-1 or Unicode keysym
+pub fn keysym_to_char(keysym: u32) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        _ if keysym > UNICODE_KEYSYM => char::from_u32(keysym - UNICODE_KEYSYM),
+        _ => None,
+    }
+}
+
+/// Keysym that types `ch`
+pub fn char_to_keysym(ch: char) -> u32 {
+    match ch as u32 {
+        code @ (0x20..=0x7e | 0xa0..=0xff) => code,
+        code => UNICODE_KEYSYM + code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keycodes 10..=14: a A, 1 !, ê (one level), Shift_L, nothing
+    fn keymap() -> Keymap {
+        Keymap::new(
+            10,
+            2,
+            vec![
+                0x61,
+                0x41,
+                0x31,
+                0x21,
+                char_to_keysym('ê'),
+                NO_SYMBOL,
+                0xffe1,
+                NO_SYMBOL,
+                NO_SYMBOL,
+                NO_SYMBOL,
+            ],
+        )
+    }
+
+    #[test]
+    fn test_chars() {
+        let keymap = keymap();
+        assert_eq!(keymap.char(10, false, false), Some('a'));
+        assert_eq!(keymap.char(10, true, false), Some('A'));
+        assert_eq!(keymap.char(10, false, true), Some('A'));
+        assert_eq!(keymap.char(10, true, true), Some('a'));
+        assert_eq!(keymap.char(11, true, true), Some('!'));
+        assert_eq!(keymap.char(12, true, false), Some('Ê'));
+        assert_eq!(keymap.char(13, false, false), None);
+        assert_eq!(keymap.char(9, false, false), None);
+        assert_eq!(keymap.char(15, false, false), None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let keymap = keymap();
+        assert_eq!(keymap.modifier(13), Some(ModifierKey::Shift));
+        assert_eq!(keymap.modifier(10), None);
+        assert_eq!(keymap.find(0x31), Some(11));
+        assert_eq!(keymap.find(XK_BACKSPACE), None);
+        assert_eq!(keymap.unused(), vec![14]);
+
+        assert_eq!(char_to_keysym('a'), 0x61);
+        assert_eq!(char_to_keysym('ệ'), 0x1001ec7);
+        assert_eq!(keysym_to_char(0x1001ec7), Some('ệ'));
+        assert_eq!(keysym_to_char(XK_BACKSPACE), None);
+    }
+}
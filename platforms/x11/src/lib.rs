@@ -0,0 +1,15 @@
+//! X11 keyboard hook for VietFlux
+//!
+//! Fallback for X11 applications that are reached through neither IBus nor
+//! Fcitx5: the Linux counterpart of the Windows `WH_KEYBOARD_LL` +
+//! `SendInput` hook in the app. Key presses are observed with the RECORD
+//! extension, which cannot swallow them, so a correction also deletes the
+//! key the application just received. Replacement text is typed with XTEST
+//! on spare keycodes remapped to the needed keysyms.
+
+pub mod edit;
+pub mod hook;
+pub mod inject;
+pub mod keymap;
+
+pub use hook::{Hook, X11Error};
@@ -0,0 +1,184 @@
+//! Type into an X11 window through the hook
+//!
+//! Needs an X server with RECORD and XTEST, so it is ignored by default:
+//!
+//! ```text
+//! xvfb-run -a -s "+extension RECORD" cargo test --test xvfb -- --ignored
+//! ```
+//!
+//! XTEST plays the physical keyboard while a test window reads back the
+//! text its key events produce, the way a plain Xlib application would.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use vietflux_core::{Engine, EngineOptions};
+use vietflux_x11::keymap::{char_to_keysym, Keymap, XK_BACKSPACE};
+use vietflux_x11::Hook;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    self, ConnectionExt as _, CreateWindowAux, EventMask, InputFocus, KeyButMask, WindowClass,
+};
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::COPY_DEPTH_FROM_PARENT;
+
+static ENGINE: Mutex<Option<Engine>> = Mutex::new(None);
+
+fn read_keymap(conn: &RustConnection) -> Keymap {
+    let setup = conn.setup();
+    let (min, max) = (setup.min_keycode, setup.max_keycode);
+    let reply = conn
+        .get_keyboard_mapping(min, max - min + 1)
+        .unwrap()
+        .reply()
+        .unwrap();
+    Keymap::new(min, reply.keysyms_per_keycode, reply.keysyms)
+}
+
+/// Focused window that inserts the character of every key press
+struct TextWindow {
+    conn: RustConnection,
+    keymap: Keymap,
+    text: String,
+}
+
+impl TextWindow {
+    fn open() -> Self {
+        let (conn, screen) = RustConnection::connect(None).expect("DISPLAY must be set");
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            100,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new().event_mask(EventMask::KEY_PRESS | EventMask::STRUCTURE_NOTIFY),
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        conn.flush().unwrap();
+        while !matches!(conn.wait_for_event().unwrap(), Event::MapNotify(_)) {}
+        conn.set_input_focus(InputFocus::POINTER_ROOT, window, x11rb::CURRENT_TIME)
+            .unwrap();
+        conn.sync().unwrap();
+
+        let keymap = read_keymap(&conn);
+        Self {
+            conn,
+            keymap,
+            text: String::new(),
+        }
+    }
+
+    /// Apply the key events received so far
+    fn read(&mut self) {
+        while let Some(event) = self.conn.poll_for_event().unwrap() {
+            match event {
+                // Sent before any key on a remapped keycode
+                Event::MappingNotify(_) => self.keymap = read_keymap(&self.conn),
+                Event::KeyPress(key) => {
+                    let shift = key.state.contains(KeyButMask::SHIFT);
+                    if self.keymap.keysym(key.detail) == XK_BACKSPACE {
+                        self.text.pop();
+                    } else if let Some(ch) = self.keymap.char(key.detail, shift, false) {
+                        self.text.push(ch);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn wait_for(&mut self, expected: &str) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            self.read();
+            if self.text == expected {
+                return;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "expected {:?}, window has {:?}",
+                expected,
+                self.text
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+/// The physical keyboard
+struct Keyboard {
+    conn: RustConnection,
+    keymap: Keymap,
+}
+
+impl Keyboard {
+    fn connect() -> Self {
+        let (conn, _) = RustConnection::connect(None).unwrap();
+        let keymap = read_keymap(&conn);
+        Self { conn, keymap }
+    }
+
+    fn press(&self, keysym: u32) {
+        let keycode = self.keymap.find(keysym).expect("key on the keyboard");
+        for event in [xproto::KEY_PRESS_EVENT, xproto::KEY_RELEASE_EVENT] {
+            self.conn
+                .xtest_fake_input(event, keycode, x11rb::CURRENT_TIME, x11rb::NONE, 0, 0, 0)
+                .unwrap();
+        }
+        self.conn.sync().unwrap();
+        // Give the hook time to correct, as a typist would
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    fn type_text(&self, text: &str) {
+        for ch in text.chars() {
+            self.press(char_to_keysym(ch));
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs an X server, run under xvfb-run"]
+fn test_types_into_window() {
+    let mut window = TextWindow::open();
+    let spare = window.keymap.unused();
+
+    let mut engine = Engine::new();
+    engine.set_options(EngineOptions {
+        auto_capitalize: false,
+        ..EngineOptions::default()
+    });
+    *ENGINE.lock().unwrap() = Some(engine);
+    let hook = Hook::start(None, &ENGINE).unwrap();
+    let keyboard = Keyboard::connect();
+
+    keyboard.type_text("vieetj ");
+    window.wait_for("việt ");
+
+    // Backspace takes the whole composed character
+    keyboard.type_text("ddas");
+    window.wait_for("việt đá");
+    keyboard.press(XK_BACKSPACE);
+    window.wait_for("việt đ");
+
+    // Switched off, keys pass untouched
+    ENGINE.lock().unwrap().as_mut().unwrap().toggle();
+    keyboard.type_text(" aa");
+    window.wait_for("việt đ aa");
+
+    drop(hook);
+    window.read();
+    window.keymap = read_keymap(&window.conn);
+    assert_eq!(window.keymap.unused(), spare);
+}